@@ -0,0 +1,297 @@
+use proc_macro2::{ TokenStream, Literal, Span };
+use quote::{ quote, format_ident };
+use syn::{ Attribute, DeriveInput, Data as SynData, Fields, Member, Ident, Type, LitStr, LitInt, Expr, Lit, UnOp };
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Layout {
+    Map,
+    Array
+}
+
+#[derive(PartialEq)]
+pub enum Key {
+    Str(String),
+    Int(i64)
+}
+
+impl Key {
+    /// Expression that encodes the key.
+    pub fn value(&self) -> TokenStream {
+        match self {
+            Key::Str(s) => quote!{ #s },
+            Key::Int(n) => {
+                let n = Literal::i64_suffixed(*n);
+                quote!{ (#n) }
+            }
+        }
+    }
+
+    /// Key in the runtime key table.
+    pub fn entry(&self) -> TokenStream {
+        match self {
+            Key::Str(s) => quote!{ ::cbor4ii::core::dec::derive::Key::Str(#s) },
+            Key::Int(n) => {
+                let n = Literal::i64_suffixed(*n);
+                quote!{ ::cbor4ii::core::dec::derive::Key::Int(#n) }
+            }
+        }
+    }
+}
+
+pub struct Field {
+    pub member: Member,
+    pub name: String,
+    pub ty: Type,
+    pub key: Key,
+    pub optional: bool,
+    pub default: bool,
+    pub bind: Ident
+}
+
+pub struct Body {
+    pub layout: Layout,
+    pub unit: bool,
+    pub fields: Vec<Field>
+}
+
+pub struct Variant {
+    pub ident: Ident,
+    pub key: Key,
+    pub newtype: bool,
+    pub body: Body
+}
+
+pub enum Data {
+    Struct(Body),
+    Enum(Vec<Variant>)
+}
+
+pub struct Container {
+    pub transparent: bool,
+    pub tag: Option<u64>,
+    pub data: Data
+}
+
+#[derive(Default)]
+struct Attrs {
+    layout: Option<Layout>,
+    transparent: bool,
+    tag: Option<u64>,
+    index: bool,
+    rename: Option<String>,
+    key: Option<i64>,
+    optional: bool,
+    default: bool
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Position {
+    Container,
+    Variant,
+    Field
+}
+
+impl Attrs {
+    fn parse(attrs: &[Attribute], pos: Position) -> syn::Result<Attrs> {
+        let mut out = Attrs::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("cbor4ii")) {
+            attr.parse_nested_meta(|meta| {
+                let path = &meta.path;
+                let allow = |ok: bool| if ok {
+                    Ok(())
+                } else {
+                    Err(meta.error("attribute is not allowed here"))
+                };
+
+                if path.is_ident("map") || path.is_ident("array") {
+                    allow(pos != Position::Field)?;
+                    out.layout = Some(if path.is_ident("map") { Layout::Map } else { Layout::Array });
+                } else if path.is_ident("transparent") {
+                    allow(pos == Position::Container)?;
+                    out.transparent = true;
+                } else if path.is_ident("tag") {
+                    allow(pos == Position::Container)?;
+                    let n: LitInt = meta.value()?.parse()?;
+                    out.tag = Some(n.base10_parse()?);
+                } else if path.is_ident("index") {
+                    allow(pos == Position::Container)?;
+                    out.index = true;
+                } else if path.is_ident("rename") {
+                    allow(pos != Position::Container)?;
+                    let s: LitStr = meta.value()?.parse()?;
+                    out.rename = Some(s.value());
+                } else if path.is_ident("key") {
+                    allow(pos != Position::Container)?;
+                    let expr: Expr = meta.value()?.parse()?;
+                    out.key = Some(parse_i64(&expr)?);
+                } else if path.is_ident("optional") {
+                    allow(pos == Position::Field)?;
+                    out.optional = true;
+                } else if path.is_ident("default") {
+                    allow(pos == Position::Field)?;
+                    out.default = true;
+                } else {
+                    return Err(meta.error("unknown cbor4ii attribute"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(out)
+    }
+
+    fn key(&self, span: Span, name: impl FnOnce() -> Key) -> syn::Result<Key> {
+        match (&self.rename, self.key) {
+            (Some(_), Some(_)) => Err(syn::Error::new(span, "`rename` and `key` can not be used together")),
+            (Some(s), None) => Ok(Key::Str(s.clone())),
+            (None, Some(n)) => Ok(Key::Int(n)),
+            (None, None) => Ok(name())
+        }
+    }
+}
+
+fn parse_i64(expr: &Expr) -> syn::Result<i64> {
+    match expr {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Int(n) => n.base10_parse(),
+            lit => Err(syn::Error::new(lit.span(), "expected integer key"))
+        },
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => match &*unary.expr {
+            Expr::Lit(lit) => match &lit.lit {
+                // parse with sign, so that `i64::MIN` is allowed
+                Lit::Int(n) => format!("-{}", n.base10_digits())
+                    .parse()
+                    .map_err(|_| syn::Error::new(n.span(), "integer key out of range")),
+                lit => Err(syn::Error::new(lit.span(), "expected integer key"))
+            },
+            expr => Err(syn::Error::new_spanned(expr, "expected integer key"))
+        },
+        expr => Err(syn::Error::new_spanned(expr, "expected integer key"))
+    }
+}
+
+impl Body {
+    fn parse(fields: &Fields, layout: Option<Layout>, span: Span) -> syn::Result<Body> {
+        let (unit, default_layout) = match fields {
+            Fields::Named(_) => (false, Layout::Map),
+            Fields::Unnamed(_) => (false, Layout::Array),
+            Fields::Unit => (true, Layout::Map)
+        };
+        let layout = layout.unwrap_or(default_layout);
+
+        let mut out = Vec::new();
+        for (i, field) in fields.iter().enumerate() {
+            let attrs = Attrs::parse(&field.attrs, Position::Field)?;
+            let span = field.ident.as_ref()
+                .map(Ident::span)
+                .unwrap_or(span);
+            let (member, name) = match &field.ident {
+                Some(ident) => {
+                    let name = ident.to_string();
+                    let name = name.strip_prefix("r#").map(String::from).unwrap_or(name);
+                    (Member::Named(ident.clone()), name)
+                },
+                None => (Member::Unnamed(i.into()), i.to_string())
+            };
+            let key = attrs.key(span, || match &member {
+                Member::Named(_) => Key::Str(name.clone()),
+                Member::Unnamed(_) => Key::Int(i as i64)
+            })?;
+
+            if attrs.optional && attrs.default {
+                return Err(syn::Error::new(span, "`optional` and `default` can not be used together"));
+            }
+
+            if layout == Layout::Map && out.iter().any(|f: &Field| f.key == key) {
+                return Err(syn::Error::new(span, "duplicate field key"));
+            }
+
+            out.push(Field {
+                member, name,
+                ty: field.ty.clone(),
+                key,
+                optional: attrs.optional,
+                default: attrs.default,
+                bind: format_ident!("__f{}", i)
+            });
+        }
+
+        Ok(Body { layout, unit, fields: out })
+    }
+
+    /// Pattern or struct expression fields, `{ a: __f0, b: __f1 }`
+    pub fn bindings(&self) -> TokenStream {
+        let fields = self.fields.iter().map(|field| {
+            let member = &field.member;
+            let bind = &field.bind;
+            quote!{ #member: #bind }
+        });
+        quote!{ { #(#fields),* } }
+    }
+}
+
+impl Container {
+    pub fn parse(input: &DeriveInput) -> syn::Result<Container> {
+        let attrs = Attrs::parse(&input.attrs, Position::Container)?;
+        let span = input.ident.span();
+
+        let data = match &input.data {
+            SynData::Struct(data) => {
+                if attrs.index {
+                    return Err(syn::Error::new(span, "`index` is only allowed on enum"));
+                }
+
+                let body = Body::parse(&data.fields, attrs.layout, span)?;
+
+                if attrs.transparent && body.fields.len() != 1 {
+                    return Err(syn::Error::new(span, "`transparent` requires exactly one field"));
+                }
+
+                Data::Struct(body)
+            },
+            SynData::Enum(data) => {
+                if attrs.transparent || attrs.layout.is_some() {
+                    return Err(syn::Error::new(span, "`transparent`, `map` and `array` are not allowed on enum"));
+                }
+
+                if data.variants.is_empty() {
+                    return Err(syn::Error::new(span, "enum without variants is not supported"));
+                }
+
+                let mut variants: Vec<Variant> = Vec::new();
+                for (i, variant) in data.variants.iter().enumerate() {
+                    let vattrs = Attrs::parse(&variant.attrs, Position::Variant)?;
+                    let vspan = variant.ident.span();
+                    let key = vattrs.key(vspan, || if attrs.index {
+                        Key::Int(i as i64)
+                    } else {
+                        let name = variant.ident.to_string();
+                        Key::Str(name.strip_prefix("r#").map(String::from).unwrap_or(name))
+                    })?;
+
+                    if variants.iter().any(|v| v.key == key) {
+                        return Err(syn::Error::new(vspan, "duplicate variant key"));
+                    }
+
+                    let newtype = vattrs.layout.is_none()
+                        && matches!(&variant.fields, Fields::Unnamed(fields) if fields.unnamed.len() == 1);
+                    let body = Body::parse(&variant.fields, vattrs.layout, vspan)?;
+
+                    variants.push(Variant { ident: variant.ident.clone(), key, newtype, body });
+                }
+
+                Data::Enum(variants)
+            },
+            SynData::Union(_) => return Err(syn::Error::new(span, "union is not supported"))
+        };
+
+        Ok(Container {
+            transparent: attrs.transparent,
+            tag: attrs.tag,
+            data
+        })
+    }
+}
@@ -0,0 +1,237 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{ DeriveInput, GenericParam, Lifetime, LifetimeParam, parse_quote };
+use crate::attr::{ Container, Data, Body, Layout };
+
+
+pub fn expand(input: &DeriveInput, container: &Container) -> TokenStream {
+    let ident = &input.ident;
+    let name = ident.to_string();
+
+    // borrow from the first lifetime, or add a new one
+    let mut generics = input.generics.clone();
+    let de: Lifetime = match input.generics.lifetimes().next() {
+        Some(param) => param.lifetime.clone(),
+        None => {
+            let de: Lifetime = parse_quote!{ 'de };
+            generics.params.insert(0, GenericParam::Lifetime(LifetimeParam::new(de.clone())));
+            de
+        }
+    };
+    for param in input.generics.type_params() {
+        let param = &param.ident;
+        generics.make_where_clause()
+            .predicates
+            .push(parse_quote!{ #param: ::cbor4ii::core::dec::Decode<#de> });
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let tag = container.tag.map(|tag| quote!{
+        ::cbor4ii::core::dec::derive::expect_tag(__NAME, __reader, #tag)?;
+    });
+
+    let body = match &container.data {
+        Data::Struct(body) if container.transparent => {
+            let member = &body.fields[0].member;
+            quote!{
+                ::core::result::Result::Ok(Self {
+                    #member: ::cbor4ii::core::dec::Decode::decode(__reader)?
+                })
+            }
+        },
+        Data::Struct(body) if body.unit => quote!{
+            <() as ::cbor4ii::core::dec::Decode>::decode(__reader)?;
+            ::core::result::Result::Ok(Self {})
+        },
+        Data::Struct(body) => decode_body(body, &quote!{ Self }),
+        Data::Enum(variants) => {
+            let entries = variants.iter().map(|variant| variant.key.entry());
+
+            let units = variants.iter()
+                .enumerate()
+                .filter(|(_, variant)| variant.body.unit)
+                .map(|(i, variant)| {
+                    let vident = &variant.ident;
+                    quote!{
+                        ::core::option::Option::Some(#i) => ::core::result::Result::Ok(Self::#vident {}),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let contents = variants.iter()
+                .enumerate()
+                .filter(|(_, variant)| !variant.body.unit)
+                .map(|(i, variant)| {
+                    let vident = &variant.ident;
+                    let content = if variant.newtype {
+                        quote!{
+                            Self::#vident { 0: ::cbor4ii::core::dec::Decode::decode(__reader)? }
+                        }
+                    } else {
+                        let body = decode_body(&variant.body, &quote!{ Self::#vident });
+                        quote!{ { #body }? }
+                    };
+                    quote!{
+                        ::core::option::Option::Some(#i) => #content,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let with_content = if contents.is_empty() {
+                None
+            } else {
+                Some(quote!{
+                    if ::cbor4ii::core::dec::if_major(__byte) == ::cbor4ii::core::major::MAP {
+                        let __len = ::cbor4ii::core::types::Map::len(__reader)?;
+                        return ::cbor4ii::core::dec::derive::nested(__NAME, __reader, |__reader| {
+                            let mut __items = ::cbor4ii::core::dec::derive::Items::new(__len);
+                            if !__items.has_next(__reader)? {
+                                return ::core::result::Result::Err(
+                                    ::cbor4ii::core::dec::derive::require_length(__NAME, __len)
+                                );
+                            }
+                            let __value = match ::cbor4ii::core::dec::derive::decode_key(__NAME, __reader, __VARIANTS)? {
+                                #(#contents)*
+                                _ => return ::core::result::Result::Err(
+                                    ::cbor4ii::core::dec::derive::mismatch(__NAME, __byte)
+                                )
+                            };
+                            __items.end(__NAME, __reader)?;
+                            ::core::result::Result::Ok(__value)
+                        });
+                    }
+                })
+            };
+
+            quote!{
+                const __VARIANTS: &[::cbor4ii::core::dec::derive::Key] = &[#(#entries),*];
+                let __byte = ::cbor4ii::core::dec::derive::peek(__NAME, __reader)?;
+                #with_content
+                match ::cbor4ii::core::dec::derive::decode_key(__NAME, __reader, __VARIANTS)? {
+                    #(#units)*
+                    _ => ::core::result::Result::Err(::cbor4ii::core::dec::derive::mismatch(__NAME, __byte))
+                }
+            }
+        }
+    };
+
+    quote!{
+        #[automatically_derived]
+        impl #impl_generics ::cbor4ii::core::dec::Decode<#de> for #ident #ty_generics #where_clause {
+            fn decode<__R: ::cbor4ii::core::dec::Read<#de>>(__reader: &mut __R)
+                -> ::core::result::Result<Self, ::cbor4ii::core::dec::Error<__R::Error>>
+            {
+                const __NAME: &&str = &#name;
+                #tag
+                #body
+            }
+        }
+    }
+}
+
+/// Decode fields and construct with `ctor`, evaluates to `Result<Self, Error>`.
+fn decode_body(body: &Body, ctor: &TokenStream) -> TokenStream {
+    let bindings = body.bindings();
+
+    match body.layout {
+        Layout::Array => {
+            let fields = body.fields.iter().map(|field| {
+                let bind = &field.bind;
+                let ty = &field.ty;
+                let missing = if field.optional {
+                    quote!{ ::core::option::Option::None }
+                } else if field.default {
+                    quote!{ ::core::default::Default::default() }
+                } else {
+                    quote!{
+                        return ::core::result::Result::Err(
+                            ::cbor4ii::core::dec::derive::require_length(__NAME, __len)
+                        )
+                    }
+                };
+
+                quote!{
+                    let #bind: #ty = if __items.has_next(__reader)? {
+                        ::cbor4ii::core::dec::Decode::decode(__reader)?
+                    } else {
+                        #missing
+                    };
+                }
+            });
+
+            quote!{
+                let __len = ::cbor4ii::core::types::Array::len(__reader)?;
+                ::cbor4ii::core::dec::derive::nested(__NAME, __reader, |__reader| {
+                    let mut __items = ::cbor4ii::core::dec::derive::Items::new(__len);
+                    #(#fields)*
+                    __items.end(__NAME, __reader)?;
+                    ::core::result::Result::Ok(#ctor #bindings)
+                })
+            }
+        },
+        Layout::Map => {
+            let entries = body.fields.iter().map(|field| field.key.entry());
+            let slots = body.fields.iter().map(|field| {
+                let bind = &field.bind;
+                let ty = &field.ty;
+                quote!{
+                    let mut #bind: ::core::option::Option<#ty> = ::core::option::Option::None;
+                }
+            });
+            let arms = body.fields.iter().enumerate().map(|(i, field)| {
+                let bind = &field.bind;
+                quote!{
+                    ::core::option::Option::Some(#i) => if #bind.is_none()
+                        || ::cbor4ii::core::dec::derive::replace_duplicate(__NAME, __reader)?
+                    {
+                        #bind = ::core::option::Option::Some(::cbor4ii::core::dec::Decode::decode(__reader)?);
+                    } else {
+                        let _ = <::cbor4ii::core::dec::IgnoredAny as ::cbor4ii::core::dec::Decode>::decode(__reader)?;
+                    },
+                }
+            });
+            let fields = body.fields.iter().map(|field| {
+                let bind = &field.bind;
+                let missing = if field.optional {
+                    quote!{ ::core::option::Option::None }
+                } else if field.default {
+                    quote!{ ::core::default::Default::default() }
+                } else {
+                    let name = &field.name;
+                    quote!{
+                        return ::core::result::Result::Err(
+                            ::cbor4ii::core::dec::derive::missing_field(&#name)
+                        )
+                    }
+                };
+
+                quote!{
+                    let #bind = match #bind {
+                        ::core::option::Option::Some(__value) => __value,
+                        ::core::option::Option::None => #missing
+                    };
+                }
+            });
+
+            quote!{
+                let __len = ::cbor4ii::core::types::Map::len(__reader)?;
+                ::cbor4ii::core::dec::derive::nested(__NAME, __reader, |__reader| {
+                    const __KEYS: &[::cbor4ii::core::dec::derive::Key] = &[#(#entries),*];
+                    #(#slots)*
+                    let mut __items = ::cbor4ii::core::dec::derive::Items::new(__len);
+                    while __items.has_next(__reader)? {
+                        match __items.decode_key(__NAME, __reader, __KEYS)? {
+                            #(#arms)*
+                            _ => {
+                                let _ = <::cbor4ii::core::dec::IgnoredAny as ::cbor4ii::core::dec::Decode>::decode(__reader)?;
+                            }
+                        }
+                    }
+                    #(#fields)*
+                    ::core::result::Result::Ok(#ctor #bindings)
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{ DeriveInput, parse_quote };
+use crate::attr::{ Container, Data, Body, Layout };
+
+
+pub fn expand(input: &DeriveInput, container: &Container) -> TokenStream {
+    let ident = &input.ident;
+
+    let mut generics = input.generics.clone();
+    for param in input.generics.type_params() {
+        let param = &param.ident;
+        generics.make_where_clause()
+            .predicates
+            .push(parse_quote!{ #param: ::cbor4ii::core::enc::Encode });
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let tag = container.tag.map(|tag| quote!{
+        ::cbor4ii::core::enc::Encode::encode(
+            &::cbor4ii::core::types::Tag(#tag, ::cbor4ii::core::types::Nothing),
+            __writer
+        )?;
+    });
+
+    let body = match &container.data {
+        Data::Struct(body) if container.transparent => {
+            let member = &body.fields[0].member;
+            quote!{ ::cbor4ii::core::enc::Encode::encode(&self.#member, __writer)?; }
+        },
+        Data::Struct(body) if body.unit => quote!{
+            ::cbor4ii::core::enc::Encode::encode(&(), __writer)?;
+        },
+        Data::Struct(body) => {
+            let bindings = body.bindings();
+            let fields = encode_body(body);
+            quote!{
+                let Self #bindings = self;
+                #fields
+            }
+        },
+        Data::Enum(variants) => {
+            let arms = variants.iter().map(|variant| {
+                let vident = &variant.ident;
+                let bindings = variant.body.bindings();
+                let key = variant.key.value();
+
+                let content = if variant.body.unit {
+                    None
+                } else if variant.newtype {
+                    let bind = &variant.body.fields[0].bind;
+                    Some(quote!{ ::cbor4ii::core::enc::Encode::encode(#bind, __writer)?; })
+                } else {
+                    Some(encode_body(&variant.body))
+                };
+
+                match content {
+                    Some(content) => quote!{
+                        Self::#vident #bindings => {
+                            ::cbor4ii::core::types::Map::bounded(1, __writer)?;
+                            ::cbor4ii::core::enc::Encode::encode(&#key, __writer)?;
+                            #content
+                        }
+                    },
+                    None => quote!{
+                        Self::#vident #bindings => {
+                            ::cbor4ii::core::enc::Encode::encode(&#key, __writer)?;
+                        }
+                    }
+                }
+            });
+
+            quote!{
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    };
+
+    quote!{
+        #[automatically_derived]
+        impl #impl_generics ::cbor4ii::core::enc::Encode for #ident #ty_generics #where_clause {
+            fn encode<__W: ::cbor4ii::core::enc::Write>(&self, __writer: &mut __W)
+                -> ::core::result::Result<(), ::cbor4ii::core::enc::Error<__W::Error>>
+            {
+                #tag
+                #body
+                ::core::result::Result::Ok(())
+            }
+        }
+    }
+}
+
+/// Encode fields bound by [`Body::bindings`].
+fn encode_body(body: &Body) -> TokenStream {
+    match body.layout {
+        Layout::Array => {
+            let len = body.fields.len();
+            let binds = body.fields.iter().map(|field| &field.bind);
+            quote!{
+                ::cbor4ii::core::types::Array::bounded(#len, __writer)?;
+                #(
+                    ::cbor4ii::core::enc::Encode::encode(#binds, __writer)?;
+                )*
+            }
+        },
+        Layout::Map => {
+            let required = body.fields.iter().filter(|field| !field.optional).count();
+            let optional = body.fields.iter()
+                .filter(|field| field.optional)
+                .map(|field| &field.bind);
+            let entries = body.fields.iter().map(|field| {
+                let bind = &field.bind;
+                let key = field.key.value();
+
+                if field.optional {
+                    quote!{
+                        if let ::core::option::Option::Some(__value) = #bind {
+                            ::cbor4ii::core::enc::Encode::encode(&#key, __writer)?;
+                            ::cbor4ii::core::enc::Encode::encode(__value, __writer)?;
+                        }
+                    }
+                } else {
+                    quote!{
+                        ::cbor4ii::core::enc::Encode::encode(&#key, __writer)?;
+                        ::cbor4ii::core::enc::Encode::encode(#bind, __writer)?;
+                    }
+                }
+            });
+
+            quote!{
+                let __len: usize = #required #( + usize::from(#optional.is_some()) )*;
+                ::cbor4ii::core::types::Map::bounded(__len, __writer)?;
+                #(#entries)*
+            }
+        }
+    }
+}
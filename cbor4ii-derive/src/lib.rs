@@ -0,0 +1,61 @@
+//! Derive `Encode` and `Decode` of [cbor4ii](https://docs.rs/cbor4ii).
+//!
+//! # Attributes
+//!
+//! Container, `#[cbor4ii(...)]` on struct or enum
+//!
+//! * `map` - encode struct as map, this is the default of struct with named fields.
+//! * `array` - encode struct as array, this is the default of tuple struct.
+//! * `transparent` - encode struct with only one field as that field.
+//! * `tag = N` - wrap the value in tag `N`.
+//! * `index` - identify enum variants by integer index instead of name.
+//!
+//! Field
+//!
+//! * `rename = "name"` - use the given string key.
+//! * `key = N` - use the given integer key.
+//! * `optional` - `Option` field that is omitted from map when it is `None`,
+//!   and is `None` when it is missing.
+//! * `default` - use `Default::default()` when the field is missing.
+//!
+//! Variant
+//!
+//! * `rename = "name"` - use the given string as variant name.
+//! * `key = N` - use the given integer as variant name.
+//! * `map`, `array` - layout of variant fields.
+//!
+//! # Layout
+//!
+//! Unit struct is encoded as an empty array, like the serde support of cbor4ii.
+//! Enum uses the same layout as the serde support of cbor4ii,
+//! unit variant is encoded as its name,
+//! other variants are encoded as a map with one entry from its name to its content.
+//!
+//! If the type has lifetime parameters, the first one is used as the `'de` lifetime of `Decode`,
+//! so fields like `&'de str` and `&'de [u8]` can be decoded without copy.
+
+mod attr;
+mod enc;
+mod dec;
+
+use proc_macro::TokenStream;
+use syn::{ parse_macro_input, DeriveInput };
+
+
+#[proc_macro_derive(Encode, attributes(cbor4ii))]
+pub fn derive_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    attr::Container::parse(&input)
+        .map(|container| enc::expand(&input, &container))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(Decode, attributes(cbor4ii))]
+pub fn derive_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    attr::Container::parse(&input)
+        .map(|container| dec::expand(&input, &container))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -1,21 +1,27 @@
 //! core module
 
 mod raw_value;
+#[cfg(feature = "use_alloc")]
+mod value_ref;
 
 pub mod error;
 pub mod types;
 pub mod enc;
 pub mod dec;
 pub mod utils;
+pub mod diag;
 
 #[cfg(feature = "use_alloc")]
 use crate::alloc::{ vec::Vec, boxed::Box, string::String };
 
-pub use raw_value::RawValue;
+pub use raw_value::{ RawValue, RawArrayIter, RawMapIter };
 
 #[cfg(feature = "use_alloc")]
 pub use raw_value::boxed::BoxedRawValue;
 
+#[cfg(feature = "use_alloc")]
+pub use value_ref::ValueRef;
+
 
 /// Major type
 pub mod major {
@@ -50,6 +56,8 @@ pub mod marker {
 #[non_exhaustive]
 pub enum Value {
     Null,
+    /// Through serde, `Option` decodes `undefined` as `None`.
+    Undefined,
     Bool(bool),
     Integer(i128),
     Float(f64),
@@ -57,7 +65,9 @@ pub enum Value {
     Text(String),
     Array(Vec<Value>),
     Map(Vec<(Value, Value)>),
-    Tag(u64, Box<Value>)
+    Tag(u64, Box<Value>),
+    /// Simple value other than `false`, `true`, `null` and `undefined`.
+    Simple(u8)
 }
 
 #[cfg(feature = "use_alloc")]
@@ -65,6 +75,7 @@ impl enc::Encode for Value {
     fn encode<W: enc::Write>(&self, writer: &mut W) -> Result<(), enc::Error<W::Error>> {
         match self {
             Value::Null => types::Null.encode(writer),
+            Value::Undefined => types::Undefined.encode(writer),
             Value::Bool(v) => v.encode(writer),
             Value::Integer(v) => v.encode(writer),
             Value::Float(v) => v.encode(writer),
@@ -72,7 +83,47 @@ impl enc::Encode for Value {
             Value::Text(v) => v.as_str().encode(writer),
             Value::Array(v) => v.as_slice().encode(writer),
             Value::Map(v) => types::Map(v.as_slice()).encode(writer),
-            Value::Tag(tag, v) => types::Tag(*tag, &**v).encode(writer)
+            Value::Tag(tag, v) => types::Tag(*tag, &**v).encode(writer),
+            Value::Simple(v) => types::Simple(*v).encode(writer)
+        }
+    }
+}
+
+/// Deterministic encoding of value.
+///
+/// Map keys are sorted bytewise by their encoded form at every level,
+/// duplicate keys are rejected, and floats use preferred serialization.
+#[cfg(feature = "use_alloc")]
+impl enc::Encode for types::Canonical<&'_ Value> {
+    fn encode<W: enc::Write>(&self, writer: &mut W) -> Result<(), enc::Error<W::Error>> {
+        match self.0 {
+            Value::Array(v) => {
+                types::Array::bounded(v.len(), writer)?;
+                for value in v.iter() {
+                    types::Canonical(value).encode(writer)?;
+                }
+                Ok(())
+            },
+            Value::Map(v) => {
+                let mut keys = Vec::with_capacity(v.len());
+                for (i, (k, _)) in v.iter().enumerate() {
+                    let mut buf = utils::VecWriter(Vec::new());
+                    types::Canonical(k).encode(&mut buf).map_err(enc::Error::cast)?;
+                    keys.push((buf.0, i));
+                }
+
+                enc::sort_keys(&mut keys)?;
+
+                types::Map::bounded(keys.len(), writer)?;
+                for (k, i) in keys.iter() {
+                    writer.push(k)?;
+                    types::Canonical(&v[*i].1).encode(writer)?;
+                }
+                Ok(())
+            },
+            Value::Tag(tag, v) => types::Tag(*tag, types::Canonical(&**v)).encode(writer),
+            Value::Float(v) => types::Preferred(*v).encode(writer),
+            value => value.encode(writer)
         }
     }
 }
@@ -112,6 +163,7 @@ impl<'de> dec::Decode<'de> for Value {
                 .map(|map| Value::Map(map.0)),
             major::TAG => {
                 let tag = <types::Tag<Value>>::decode(reader)?;
+                dec::check_limit(&"tag", reader, dec::Limit::Alloc(core::mem::size_of::<Value>()))?;
                 Ok(Value::Tag(tag.0, Box::new(tag.1)))
             },
             major::SIMPLE => match byte {
@@ -123,18 +175,17 @@ impl<'de> dec::Decode<'de> for Value {
                     reader.advance(1);
                     Ok(Value::Bool(true))
                 },
-                marker::NULL | marker::UNDEFINED => {
+                marker::NULL => {
                     reader.advance(1);
                     Ok(Value::Null)
                 },
-                #[cfg(feature = "half-f16")]
-                marker::F16 => {
-                    let v = half::f16::decode(reader)?;
-                    Ok(Value::Float(v.into()))
+                marker::UNDEFINED => {
+                    reader.advance(1);
+                    Ok(Value::Undefined)
                 },
-                marker::F32 => f32::decode(reader)
-                    .map(|v| Value::Float(v.into())),
-                marker::F64 => f64::decode(reader)
+                0xe0 ..= 0xf3 | 0xf8 => types::Simple::decode(reader)
+                    .map(|v| Value::Simple(v.0)),
+                marker::F16 | marker::F32 | marker::F64 => f64::decode(reader)
                     .map(Value::Float),
                 _ => Err(dec::Error::unsupported(name, byte))
             },
@@ -149,10 +200,11 @@ impl serde::Serialize for Value {
     where
         S: serde::Serializer
     {
-        use serde::ser::{ Error, SerializeMap, SerializeSeq };
+        use serde::ser::{ SerializeMap, SerializeSeq };
 
         match self {
             Value::Null => serializer.serialize_none(),
+            Value::Undefined => crate::serde::simple::serialize(23, serializer),
             Value::Bool(v) => serializer.serialize_bool(*v),
             Value::Integer(v) => serializer.serialize_i128(*v),
             Value::Float(v) => serializer.serialize_f64(*v),
@@ -172,154 +224,228 @@ impl serde::Serialize for Value {
                 }
                 map.end()
             },
-            Value::Tag(..) =>
-                Err(S::Error::custom("serialization Tag via serde is not supported"))
+            Value::Tag(tag, v) => crate::serde::Tagged(*tag, &**v).serialize(serializer),
+            Value::Simple(v) => crate::serde::simple::serialize(*v, serializer)
         }
     }
 }
 
+/// Implement `Deserialize` for `Value` and `ValueRef`.
+///
+/// Text and bytes are converted with `From`,
+/// so that `ValueRef` borrows them when the deserializer allows it.
 #[cfg(feature = "serde1")]
-impl<'de> serde::Deserialize<'de> for Value {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>
-    {
-        use serde::de::{ Error, Visitor, SeqAccess, MapAccess };
-
-        struct ValueVisitor;
-
-        impl<'de> Visitor<'de> for ValueVisitor {
-            type Value = Value;
-
-            fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-                formatter.write_str("invalid input or unsupported type")
-            }
-
-            #[inline]
-            fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
-            where E: Error
-            {
-                Ok(Value::Bool(v))
-            }
-
-            #[inline]
-            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
-            where E: Error
-            {
-                Ok(Value::Integer(v.into()))
-            }
-
-            #[inline]
-            fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E>
-            where E: Error
-            {
-                Ok(Value::Integer(v))
-            }
-
-            #[inline]
-            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
-            where E: Error,
-            {
-                Ok(Value::Integer(v.into()))
-            }
-
-            #[inline]
-            fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E>
-            where E: Error,
+macro_rules! value_deserialize {
+    ( $t:ty, $value:ident ) => {
+        impl<'de> serde::Deserialize<'de> for $t {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>
             {
-                use core::convert::TryFrom;
-
-                let v = i128::try_from(v).map_err(E::custom)?;
-                Ok(Value::Integer(v))
-            }
-
-            #[inline]
-            fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
-            where E: Error,
-            {
-                Ok(Value::Float(v))
-            }
-
-            #[inline]
-            fn visit_char<E>(self, v: char) -> Result<Self::Value, E>
-            where E: Error,
-            {
-                Ok(Value::Text(v.into()))
-            }
-
-            #[inline]
-            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
-            where E: Error,
-            {
-                Ok(Value::Text(v.into()))
-            }
-
-            #[inline]
-            fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
-            where E: Error,
-            {
-                Ok(Value::Text(v))
-            }
-
-            #[inline]
-            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
-            where E: Error,
-            {
-                Ok(Value::Bytes(v.into()))
-            }
-
-            #[inline]
-            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
-            where E: Error,
-            {
-                Ok(Value::Bytes(v))
-            }
-
-            #[inline]
-            fn visit_none<E>(self) -> Result<Self::Value, E>
-            where E: Error,
-            {
-                Ok(Value::Null)
-            }
-
-            #[inline]
-            fn visit_unit<E>(self) -> Result<Self::Value, E>
-            where E: Error,
-            {
-                self.visit_none()
-            }
-
-            #[inline]
-            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
-            where A: SeqAccess<'de>,
-            {
-                let mut list = seq.size_hint()
-                    .map(|n| Vec::with_capacity(core::cmp::min(n, 256)))
-                    .unwrap_or_else(Vec::new);
-
-                while let Some(v) = seq.next_element()? {
-                    list.push(v);
+                use crate::alloc::{ vec::Vec, boxed::Box, string::String };
+                use serde::de::{ Error, Visitor, SeqAccess, MapAccess, EnumAccess, VariantAccess };
+
+                struct ValueVisitor;
+
+                impl<'de> Visitor<'de> for ValueVisitor {
+                    type Value = $t;
+
+                    fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                        formatter.write_str("invalid input or unsupported type")
+                    }
+
+                    #[inline]
+                    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+                    where E: Error
+                    {
+                        Ok($value::Bool(v))
+                    }
+
+                    #[inline]
+                    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+                    where E: Error
+                    {
+                        Ok($value::Integer(v.into()))
+                    }
+
+                    #[inline]
+                    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E>
+                    where E: Error
+                    {
+                        Ok($value::Integer(v))
+                    }
+
+                    #[inline]
+                    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        Ok($value::Integer(v.into()))
+                    }
+
+                    #[inline]
+                    fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        use core::convert::TryFrom;
+
+                        match i128::try_from(v) {
+                            Ok(v) => Ok($value::Integer(v)),
+                            // bignum
+                            Err(_) => {
+                                let bytes = v.to_be_bytes().to_vec().into();
+                                Ok($value::Tag(2, Box::new($value::Bytes(bytes))))
+                            }
+                        }
+                    }
+
+                    #[inline]
+                    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        Ok($value::Float(v))
+                    }
+
+                    #[inline]
+                    fn visit_char<E>(self, v: char) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        Ok($value::Text(String::from(v).into()))
+                    }
+
+                    #[inline]
+                    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        Ok($value::Text(String::from(v).into()))
+                    }
+
+                    #[inline]
+                    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        Ok($value::Text(v.into()))
+                    }
+
+                    #[inline]
+                    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        Ok($value::Text(v.into()))
+                    }
+
+                    #[inline]
+                    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        Ok($value::Bytes(Vec::from(v).into()))
+                    }
+
+                    #[inline]
+                    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        Ok($value::Bytes(v.into()))
+                    }
+
+                    #[inline]
+                    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        Ok($value::Bytes(v.into()))
+                    }
+
+                    #[inline]
+                    fn visit_none<E>(self) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        Ok($value::Null)
+                    }
+
+                    #[inline]
+                    fn visit_unit<E>(self) -> Result<Self::Value, E>
+                    where E: Error,
+                    {
+                        self.visit_none()
+                    }
+
+                    #[inline]
+                    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                    where A: SeqAccess<'de>,
+                    {
+                        let mut list = seq.size_hint()
+                            .map(|n| Vec::with_capacity(core::cmp::min(n, 256)))
+                            .unwrap_or_else(Vec::new);
+
+                        while let Some(v) = seq.next_element()? {
+                            list.push(v);
+                        }
+
+                        Ok($value::Array(list))
+                    }
+
+                    #[inline]
+                    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+                    where A: MapAccess<'de>,
+                    {
+                        let mut list = map.size_hint()
+                            .map(|n| Vec::with_capacity(core::cmp::min(n, 256)))
+                            .unwrap_or_else(Vec::new);
+
+                        while let Some((k, v)) = map.next_entry()? {
+                            list.push((k, v));
+                        }
+
+                        Ok($value::Map(list))
+                    }
+
+                    #[inline]
+                    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+                    where A: EnumAccess<'de>,
+                    {
+                        let (name, variant) = data.variant::<String>()?;
+
+                        if name == crate::serde::tag::NAME {
+                            variant.tuple_variant(2, TagVisitor)
+                        } else if name == crate::serde::simple::NAME {
+                            variant.newtype_variant().map(|v| match v {
+                                23 => $value::Undefined,
+                                v => $value::Simple(v)
+                            })
+                        } else {
+                            Err(A::Error::invalid_type(serde::de::Unexpected::Enum, &self))
+                        }
+                    }
                 }
 
-                Ok(Value::Array(list))
-            }
-
-            #[inline]
-            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
-            where A: MapAccess<'de>,
-            {
-                let mut list = map.size_hint()
-                    .map(|n| Vec::with_capacity(core::cmp::min(n, 256)))
-                    .unwrap_or_else(Vec::new);
-
-                while let Some((k, v)) = map.next_entry()? {
-                    list.push((k, v));
+                struct TagVisitor;
+
+                impl<'de> Visitor<'de> for TagVisitor {
+                    type Value = $t;
+
+                    fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                        formatter.write_str("tagged value")
+                    }
+
+                    #[inline]
+                    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                    where A: SeqAccess<'de>,
+                    {
+                        let tag = seq.next_element()?
+                            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+                        let value = seq.next_element()?
+                            .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+                        Ok($value::Tag(tag, Box::new(value)))
+                    }
                 }
 
-                Ok(Value::Map(list))
+                deserializer.deserialize_any(ValueVisitor)
             }
         }
-
-        deserializer.deserialize_any(ValueVisitor)
     }
 }
+
+#[cfg(feature = "serde1")]
+pub(crate) use value_deserialize;
+
+#[cfg(feature = "serde1")]
+value_deserialize!(Value, Value);
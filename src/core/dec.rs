@@ -1,6 +1,7 @@
 //! decode module
 
 use core::convert::TryFrom;
+use core::marker::PhantomData;
 use crate::core::{ major, marker, types, error };
 use crate::util::ScopeGuard;
 pub use crate::core::error::DecodeError as Error;
@@ -8,6 +9,18 @@ pub use crate::core::error::DecodeError as Error;
 #[cfg(feature = "use_alloc")]
 use crate::alloc::{ boxed::Box, vec::Vec, string::String };
 
+#[cfg(feature = "use_alloc")]
+use crate::alloc::collections::{ BTreeMap, BTreeSet, BinaryHeap, VecDeque };
+
+mod tokenizer;
+#[doc(hidden)]
+pub mod derive;
+
+pub use tokenizer::{ Tokenizer, Token };
+
+#[cfg(feature = "derive")]
+pub use cbor4ii_derive::Decode;
+
 
 /// Read trait
 ///
@@ -45,6 +58,166 @@ pub trait Read<'de> {
     /// to calculate the decode depth.
     #[inline]
     fn step_out(&mut self) {}
+
+    /// Strict mode
+    ///
+    /// If it returns true, the decoder will reject input that
+    /// is accepted but not in deterministic form (rfc8949 section 4.2),
+    /// that is non-shortest integers and lengths, indefinite length items,
+    /// non-preferred floats, and unsorted or duplicate map keys.
+    ///
+    /// Map keys are only checked when the `use_alloc` feature is enabled.
+    #[inline]
+    fn is_strict(&self) -> bool {
+        false
+    }
+
+    /// Duplicate key policy
+    ///
+    /// This decides how map and set types, such as `BTreeMap` and `HashSet`,
+    /// handle duplicate keys. The default keeps the last one.
+    ///
+    /// Strict mode rejects duplicate map keys regardless of this.
+    #[inline]
+    fn duplicate_key(&self) -> DuplicateKey {
+        DuplicateKey::Last
+    }
+
+    /// Decode limits
+    ///
+    /// This method is called before the decoder allocates memory or
+    /// accepts array and map items, to bound the resources used by untrusted input.
+    /// If it returns false, the decode will return a limit exceeded error.
+    ///
+    /// See [`Limits`] for an implementation.
+    #[inline]
+    fn check_limit(&mut self, _limit: Limit) -> bool {
+        true
+    }
+
+    /// Byte offset
+    ///
+    /// Returns the number of bytes consumed so far, if the reader tracks it.
+    /// After a decode error, this is the offset at which the error was detected,
+    /// which is usually the start of the offending item.
+    #[inline]
+    fn offset(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Duplicate key policy, see [`Read::duplicate_key`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKey {
+    /// Return [`Error::DuplicateKey`].
+    Error,
+    /// Keep the first entry.
+    First,
+    /// Keep the last entry.
+    Last
+}
+
+/// Resource usage checked by [`Read::check_limit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Limit {
+    /// Bytes about to be allocated.
+    Alloc(usize),
+    /// Length of string or bytes about to be buffered.
+    BytesLen(usize),
+    /// Declared length of array or map.
+    Len(usize),
+    /// Number of array items or map entries about to be decoded.
+    Items(usize)
+}
+
+/// Decode limits for untrusted input
+///
+/// All limits are unlimited by default.
+/// The allocated bytes and items are accumulated over all values decoded by a reader,
+/// use [`Limits::reset`] to start over.
+///
+/// Items of indefinite length arrays and maps are only counted
+/// when they are decoded into an allocated collection,
+/// and the allocated bytes of collections are estimated from the size of their items.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    max_alloc: usize,
+    max_bytes_len: usize,
+    max_len: usize,
+    max_items: usize,
+    alloc: usize,
+    items: usize
+}
+
+impl Limits {
+    pub const fn new() -> Limits {
+        Limits {
+            max_alloc: usize::MAX,
+            max_bytes_len: usize::MAX,
+            max_len: usize::MAX,
+            max_items: usize::MAX,
+            alloc: 0,
+            items: 0
+        }
+    }
+
+    /// Maximum total bytes allocated.
+    pub const fn max_alloc(mut self, n: usize) -> Self {
+        self.max_alloc = n;
+        self
+    }
+
+    /// Maximum length of string or bytes.
+    pub const fn max_bytes_len(mut self, n: usize) -> Self {
+        self.max_bytes_len = n;
+        self
+    }
+
+    /// Maximum length of array or map.
+    pub const fn max_len(mut self, n: usize) -> Self {
+        self.max_len = n;
+        self
+    }
+
+    /// Maximum total number of array items and map entries.
+    pub const fn max_items(mut self, n: usize) -> Self {
+        self.max_items = n;
+        self
+    }
+
+    /// Reset the accumulated usage.
+    pub fn reset(&mut self) {
+        self.alloc = 0;
+        self.items = 0;
+    }
+
+    /// Check and account the usage, returns false if a limit is exceeded.
+    pub fn check(&mut self, limit: Limit) -> bool {
+        fn add(used: &mut usize, n: usize, max: usize) -> bool {
+            match used.checked_add(n) {
+                Some(n) if n <= max => {
+                    *used = n;
+                    true
+                },
+                _ => false
+            }
+        }
+
+        match limit {
+            Limit::Alloc(n) => add(&mut self.alloc, n, self.max_alloc),
+            Limit::BytesLen(n) => n <= self.max_bytes_len,
+            Limit::Len(n) => n <= self.max_len,
+            Limit::Items(n) => add(&mut self.items, n, self.max_items)
+        }
+    }
+}
+
+impl Default for Limits {
+    #[inline]
+    fn default() -> Limits {
+        Limits::new()
+    }
 }
 
 /// Bytes reference
@@ -104,6 +277,26 @@ impl<'de, T: Read<'de>> Read<'de> for &mut T {
     fn step_out(&mut self) {
         (**self).step_out()
     }
+
+    #[inline]
+    fn is_strict(&self) -> bool {
+        (**self).is_strict()
+    }
+
+    #[inline]
+    fn duplicate_key(&self) -> DuplicateKey {
+        (**self).duplicate_key()
+    }
+
+    #[inline]
+    fn check_limit(&mut self, limit: Limit) -> bool {
+        (**self).check_limit(limit)
+    }
+
+    #[inline]
+    fn offset(&self) -> Option<usize> {
+        (**self).offset()
+    }
 }
 
 #[inline]
@@ -128,7 +321,7 @@ pub(crate) fn pull_one<'de, R: Read<'de>>(name: error::StaticStr, reader: &mut R
 }
 
 #[inline]
-fn pull_exact<'de, R: Read<'de>>(name: error::StaticStr, reader: &mut R, mut buf: &mut [u8])
+pub(crate) fn pull_exact<'de, R: Read<'de>>(name: error::StaticStr, reader: &mut R, mut buf: &mut [u8])
     -> Result<(), Error<R::Error>>
 {
     let buf_len = buf.len();
@@ -169,6 +362,42 @@ fn skip_exact<'de, R: Read<'de>>(name: error::StaticStr, reader: &mut R, mut len
     Ok(())
 }
 
+#[inline]
+pub(crate) fn check_limit<'de, R: Read<'de>>(name: error::StaticStr, reader: &mut R, limit: Limit)
+    -> Result<(), Error<R::Error>>
+{
+    if reader.check_limit(limit) {
+        Ok(())
+    } else {
+        Err(Error::limit_exceeded(name))
+    }
+}
+
+/// Reserve space for more items, and account the allocated bytes.
+///
+/// It grows the capacity like `Vec::reserve`,
+/// but checks the limit before allocating and does not panic if allocation fails.
+#[cfg(feature = "use_alloc")]
+#[inline]
+pub(crate) fn try_reserve<'de, R: Read<'de>, T>(
+    name: error::StaticStr,
+    reader: &mut R,
+    vec: &mut Vec<T>,
+    additional: usize
+) -> Result<(), Error<R::Error>> {
+    if vec.capacity() - vec.len() >= additional {
+        return Ok(());
+    }
+
+    let cap = vec.len().checked_add(additional)
+        .map(|cap| core::cmp::max(cap, vec.capacity().saturating_mul(2)))
+        .ok_or_else(|| Error::limit_exceeded(name))?;
+    let bytes = (cap - vec.capacity()).saturating_mul(core::mem::size_of::<T>());
+    check_limit(name, reader, Limit::Alloc(bytes))?;
+    vec.try_reserve_exact(cap - vec.len())
+        .map_err(|_| Error::limit_exceeded(name))
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct TypeNum {
     name: error::StaticStr,
@@ -181,56 +410,99 @@ impl TypeNum {
         TypeNum { name, major: major << 5 }
     }
 
+    /// Check that the argument is encoded in the shortest form in strict mode.
+    #[inline]
+    fn check_shortest<'de, R: Read<'de>>(self, reader: &R, byte: u8, value: u64)
+        -> Result<(), Error<R::Error>>
+    {
+        let min = match low(byte) {
+            0x18 => 0x18,
+            0x19 => 0x100,
+            0x1a => 0x1_0000,
+            0x1b => 0x1_0000_0000,
+            _ => 0
+        };
+
+        if value < min && reader.is_strict() {
+            Err(Error::non_shortest(self.name, byte))
+        } else {
+            Ok(())
+        }
+    }
+
     #[inline]
     pub fn decode_u8<'de, R: Read<'de>>(self, reader: &mut R) -> Result<u8, Error<R::Error>> {
-        let byte = pull_one(self.name, reader)?;
+        let byte = peek_one(self.name, reader)?;
         let is_major = high(byte) ^ self.major;
         match low(byte) | is_major {
-            x @ 0 ..= 0x17 => Ok(x),
-            0x18 => pull_one(self.name, reader),
+            x @ 0 ..= 0x17 => {
+                reader.advance(1);
+                Ok(x)
+            },
+            0x18 => {
+                reader.advance(1);
+                let x = pull_one(self.name, reader)?;
+                self.check_shortest(reader, byte, x.into())?;
+                Ok(x)
+            },
             _ => Err(Error::mismatch(self.name, byte))
         }
     }
 
     #[inline]
     fn decode_u16<'de, R: Read<'de>>(self, reader: &mut R) -> Result<u16, Error<R::Error>> {
-        let byte = pull_one(self.name, reader)?;
+        let byte = peek_one(self.name, reader)?;
         let is_major = high(byte) ^ self.major;
         let mut buf = [0; 2];
         let n = match low(byte) | is_major {
-            x @ 0 ..= 0x17 => return Ok(x.into()),
+            x @ 0 ..= 0x17 => {
+                reader.advance(1);
+                return Ok(x.into());
+            },
             0x18 => 1,
             0x19 => 0,
             _ => return Err(Error::mismatch(self.name, byte))
         };
+        reader.advance(1);
         pull_exact(self.name, reader, &mut buf[n..])?;
-        Ok(u16::from_be_bytes(buf))
+        let x = u16::from_be_bytes(buf);
+        self.check_shortest(reader, byte, x.into())?;
+        Ok(x)
     }
 
     #[inline]
     fn decode_u32<'de, R: Read<'de>>(self, reader: &mut R) -> Result<u32, Error<R::Error>> {
-        let byte = pull_one(self.name, reader)?;
+        let byte = peek_one(self.name, reader)?;
         let is_major = high(byte) ^ self.major;
         let mut buf = [0; 4];
         let n = match low(byte) | is_major {
-            x @ 0 ..= 0x17 => return Ok(x.into()),
+            x @ 0 ..= 0x17 => {
+                reader.advance(1);
+                return Ok(x.into());
+            },
             0x1b.. => return Err(Error::mismatch(self.name, byte)),
             x => 4-(1 << (x-0x17-1)),
             // 0x18 => 4-1,
             // 0x19 => 4-2,
             // 0x1a => 4-4,
         };
+        reader.advance(1);
         pull_exact(self.name, reader, &mut buf[n..])?;
-        Ok(u32::from_be_bytes(buf))
+        let x = u32::from_be_bytes(buf);
+        self.check_shortest(reader, byte, x.into())?;
+        Ok(x)
     }
 
     #[inline]
     pub(crate) fn decode_u64<'de, R: Read<'de>>(self, reader: &mut R) -> Result<u64, Error<R::Error>> {
-        let byte = pull_one(self.name, reader)?;
+        let byte = peek_one(self.name, reader)?;
         let is_major = high(byte) ^ self.major;
         let mut buf = [0; 8];
         let n = match low(byte) | is_major {
-            x @ 0 ..= 0x17 => return Ok(x.into()),
+            x @ 0 ..= 0x17 => {
+                reader.advance(1);
+                return Ok(x.into());
+            },
             0x1c.. => return Err(Error::mismatch(self.name, byte)),
             x => 8-(1 << (x-0x17-1)),
             // 0x18 => 8-1,
@@ -238,8 +510,11 @@ impl TypeNum {
             // 0x1a => 8-4,
             // 0x1b => 8-8,
         };
+        reader.advance(1);
         pull_exact(self.name, reader, &mut buf[n..])?;
-        Ok(u64::from_be_bytes(buf))
+        let x = u64::from_be_bytes(buf);
+        self.check_shortest(reader, byte, x)?;
+        Ok(x)
     }
 }
 
@@ -416,7 +691,15 @@ fn decode_len<'de, R: Read<'de>>(num: TypeNum, reader: &mut R)
     if byte != (marker::START | num.major) {
         let len = num.decode_u64(reader)?;
         let len = usize::try_from(len).map_err(|_| Error::cast_overflow(num.name))?;
+
+        if num.major == major::ARRAY << 5 || num.major == major::MAP << 5 {
+            check_limit(num.name, reader, Limit::Len(len))?;
+            check_limit(num.name, reader, Limit::Items(len))?;
+        }
+
         Ok(Some(len))
+    } else if reader.is_strict() {
+        Err(Error::indefinite_length(num.name))
     } else {
         reader.advance(1);
         Ok(None)
@@ -448,19 +731,25 @@ fn decode_bytes_buf<'a, R: Read<'a>>(num: TypeNum, reader: &mut R, len: usize, b
 {
     const CAP_LIMIT: usize = 16 * 1024;
 
+    let total = buf.len().checked_add(len)
+        .ok_or_else(|| Error::limit_exceeded(num.name))?;
+    check_limit(num.name, reader, Limit::BytesLen(total))?;
+
     let mut len = len;
-    buf.reserve(core::cmp::min(len, CAP_LIMIT)); // TODO try_reserve ?
+    try_reserve(num.name, reader, buf, core::cmp::min(len, CAP_LIMIT))?;
 
     while len != 0 {
-        let readbuf = reader.fill(len)?;
-        let readbuf = readbuf.as_ref();
-
-        if readbuf.is_empty() {
-            return Err(Error::eof(num.name, len));
-        }
+        let readlen = match reader.fill(len)?.as_ref() {
+            [] => return Err(Error::eof(num.name, len)),
+            readbuf => core::cmp::min(readbuf.len(), len)
+        };
 
-        let readlen = core::cmp::min(readbuf.len(), len);
+        // reserve before extend, so that allocation failure is an error
+        try_reserve(num.name, reader, buf, readlen)?;
 
+        let readbuf = reader.fill(len)?;
+        let readbuf = readbuf.as_ref();
+        let readlen = core::cmp::min(readbuf.len(), readlen);
         buf.extend_from_slice(&readbuf[..readlen]);
         reader.advance(readlen);
         len -= readlen;
@@ -515,6 +804,8 @@ where
 {
     let mut buf = Vec::new();
     if let Some(buf_ref) = decode_bytes(num, reader, &mut buf)? {
+        check_limit(num.name, reader, Limit::BytesLen(buf_ref.len()))?;
+        try_reserve(num.name, reader, &mut buf, buf_ref.len())?;
         buf.extend_from_slice(buf_ref);
     }
     Ok(buf)
@@ -651,15 +942,18 @@ impl<'de, T: Decode<'de>> Decode<'de> for Vec<T> {
         let reader = &mut *reader;
 
         if let Some(len) = types::Array::len(reader)? {
-            arr.reserve(core::cmp::min(len, 256)); // TODO try_reserve ?
+            try_reserve(name, reader, &mut arr, core::cmp::min(len, 256))?;
 
             for _ in 0..len {
                 let value = T::decode(reader)?;
+                try_reserve(name, reader, &mut arr, 1)?;
                 arr.push(value);
             }
         } else {
             while !is_break(reader)? {
+                check_limit(name, reader, Limit::Items(1))?;
                 let value = T::decode(reader)?;
+                try_reserve(name, reader, &mut arr, 1)?;
                 arr.push(value);
             }
         }
@@ -668,6 +962,136 @@ impl<'de, T: Decode<'de>> Decode<'de> for Vec<T> {
     }
 }
 
+/// A reader that records the bytes consumed by a map key.
+#[cfg(feature = "use_alloc")]
+pub(crate) struct KeyRecorder<'a, 'de, E> {
+    reader: &'a mut dyn Read<'de, Error = E>,
+    filled: Vec<u8>,
+    key: Vec<u8>
+}
+
+#[cfg(feature = "use_alloc")]
+impl<'a, 'de, E> KeyRecorder<'a, 'de, E> {
+    #[inline]
+    pub(crate) fn new(reader: &'a mut dyn Read<'de, Error = E>) -> KeyRecorder<'a, 'de, E> {
+        KeyRecorder {
+            reader,
+            filled: Vec::new(),
+            key: Vec::new()
+        }
+    }
+
+    /// Returns the recorded bytes.
+    #[inline]
+    pub(crate) fn into_key(self) -> Vec<u8> {
+        self.key
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<'de, E: core::error::Error + 'static> Read<'de> for KeyRecorder<'_, 'de, E> {
+    type Error = E;
+
+    #[inline]
+    fn fill<'short>(&'short mut self, want: usize) -> Result<Reference<'de, 'short>, Self::Error> {
+        let buf = self.reader.fill(want)?;
+        self.filled.clear();
+        self.filled.extend_from_slice(buf.take(want).as_ref());
+        Ok(buf)
+    }
+
+    #[inline]
+    fn advance(&mut self, n: usize) {
+        let len = core::cmp::min(self.filled.len(), n);
+        self.key.extend(self.filled.drain(..len));
+        self.reader.advance(n);
+    }
+
+    #[inline]
+    fn step_in(&mut self) -> bool {
+        self.reader.step_in()
+    }
+
+    #[inline]
+    fn step_out(&mut self) {
+        self.reader.step_out()
+    }
+
+    #[inline]
+    fn is_strict(&self) -> bool {
+        self.reader.is_strict()
+    }
+
+    #[inline]
+    fn duplicate_key(&self) -> DuplicateKey {
+        self.reader.duplicate_key()
+    }
+
+    #[inline]
+    fn check_limit(&mut self, limit: Limit) -> bool {
+        self.reader.check_limit(limit)
+    }
+
+    #[inline]
+    fn offset(&self) -> Option<usize> {
+        self.reader.offset()
+    }
+}
+
+/// Check the order of map keys in strict mode.
+pub(crate) struct KeyOrder {
+    #[cfg(feature = "use_alloc")]
+    last: Option<Vec<u8>>
+}
+
+impl KeyOrder {
+    #[inline]
+    pub(crate) const fn new() -> KeyOrder {
+        KeyOrder {
+            #[cfg(feature = "use_alloc")]
+            last: None
+        }
+    }
+
+    /// Check that the encoded key is greater than the previous key.
+    #[cfg(feature = "use_alloc")]
+    pub(crate) fn check<E>(&mut self, name: error::StaticStr, key: Vec<u8>)
+        -> Result<(), Error<E>>
+    {
+        use core::cmp::Ordering;
+
+        if let Some(last) = self.last.as_ref() {
+            match last.cmp(&key) {
+                Ordering::Less => (),
+                Ordering::Equal => return Err(Error::duplicate_key(name)),
+                Ordering::Greater => return Err(Error::unsorted_key(name))
+            }
+        }
+
+        self.last = Some(key);
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn decode<'de, R, K>(&mut self, name: error::StaticStr, reader: &mut R)
+        -> Result<K, Error<R::Error>>
+    where
+        R: Read<'de>,
+        K: Decode<'de>
+    {
+        #[cfg(feature = "use_alloc")]
+        if reader.is_strict() {
+            let mut recorder = KeyRecorder::new(reader);
+            let key = K::decode(&mut recorder)?;
+            self.check(name, recorder.into_key())?;
+            return Ok(key);
+        }
+
+        let _ = name;
+        K::decode(reader)
+    }
+}
+
 impl<'de> types::Map<()> {
     #[inline]
     pub fn len<R: Read<'de>>(reader: &mut R) -> Result<Option<usize>, Error<R::Error>> {
@@ -688,18 +1112,23 @@ impl<'de, K: Decode<'de>, V: Decode<'de>> Decode<'de> for types::Map<Vec<(K, V)>
         let mut reader = ScopeGuard(reader, |reader| reader.step_out());
         let reader = &mut *reader;
 
+        let mut order = KeyOrder::new();
+
         if let Some(len) = types::Map::len(reader)? {
-            map.reserve(core::cmp::min(len, 256)); // TODO try_reserve ?
+            try_reserve(name, reader, &mut map, core::cmp::min(len, 256))?;
 
             for _ in 0..len {
-                let k = K::decode(reader)?;
+                let k = order.decode(name, reader)?;
                 let v = V::decode(reader)?;
+                try_reserve(name, reader, &mut map, 1)?;
                 map.push((k, v));
             }
         } else {
             while !is_break(reader)? {
-                let k = K::decode(reader)?;
+                check_limit(name, reader, Limit::Items(1))?;
+                let k = order.decode(name, reader)?;
                 let v = V::decode(reader)?;
+                try_reserve(name, reader, &mut map, 1)?;
                 map.push((k, v));
             }
         }
@@ -708,6 +1137,197 @@ impl<'de, K: Decode<'de>, V: Decode<'de>> Decode<'de> for types::Map<Vec<(K, V)>
     }
 }
 
+/// Decode array items, and resolve duplicate items by [`Read::duplicate_key`].
+///
+/// The `insert` returns false if the item already exists.
+#[cfg(feature = "use_alloc")]
+fn decode_set<'de, R, T, F>(name: error::StaticStr, reader: &mut R, mut insert: F)
+    -> Result<(), Error<R::Error>>
+where
+    R: Read<'de>,
+    T: Decode<'de>,
+    F: FnMut(T, DuplicateKey) -> bool
+{
+    if !reader.step_in() {
+        return Err(Error::depth_overflow(name));
+    }
+    let mut reader = ScopeGuard(reader, |reader| reader.step_out());
+    let reader = &mut *reader;
+
+    let policy = reader.duplicate_key();
+    let mut insert = |value| -> Result<(), Error<R::Error>> {
+        if insert(value, policy) || policy != DuplicateKey::Error {
+            Ok(())
+        } else {
+            Err(Error::duplicate_key(name))
+        }
+    };
+
+    let size = core::mem::size_of::<T>();
+
+    if let Some(len) = types::Array::len(reader)? {
+        for _ in 0..len {
+            let value = T::decode(reader)?;
+            check_limit(name, reader, Limit::Alloc(size))?;
+            insert(value)?;
+        }
+    } else {
+        while !is_break(reader)? {
+            check_limit(name, reader, Limit::Items(1))?;
+            let value = T::decode(reader)?;
+            check_limit(name, reader, Limit::Alloc(size))?;
+            insert(value)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Decode map entries, and resolve duplicate keys by [`Read::duplicate_key`].
+///
+/// The `insert` returns false if the key already exists.
+#[cfg(feature = "use_alloc")]
+fn decode_map<'de, R, K, V, F>(name: error::StaticStr, reader: &mut R, mut insert: F)
+    -> Result<(), Error<R::Error>>
+where
+    R: Read<'de>,
+    K: Decode<'de>,
+    V: Decode<'de>,
+    F: FnMut(K, V, DuplicateKey) -> bool
+{
+    if !reader.step_in() {
+        return Err(Error::depth_overflow(name));
+    }
+    let mut reader = ScopeGuard(reader, |reader| reader.step_out());
+    let reader = &mut *reader;
+
+    let policy = reader.duplicate_key();
+    let mut insert = |k, v| -> Result<(), Error<R::Error>> {
+        if insert(k, v, policy) || policy != DuplicateKey::Error {
+            Ok(())
+        } else {
+            Err(Error::duplicate_key(name))
+        }
+    };
+
+    let mut order = KeyOrder::new();
+    let size = core::mem::size_of::<(K, V)>();
+
+    if let Some(len) = types::Map::len(reader)? {
+        for _ in 0..len {
+            let k = order.decode(name, reader)?;
+            let v = V::decode(reader)?;
+            check_limit(name, reader, Limit::Alloc(size))?;
+            insert(k, v)?;
+        }
+    } else {
+        while !is_break(reader)? {
+            check_limit(name, reader, Limit::Items(1))?;
+            let k = order.decode(name, reader)?;
+            let v = V::decode(reader)?;
+            check_limit(name, reader, Limit::Alloc(size))?;
+            insert(k, v)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "use_alloc")]
+impl<'de, T: Decode<'de>> Decode<'de> for VecDeque<T> {
+    #[inline]
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        Vec::decode(reader).map(VecDeque::from)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<'de, T: Decode<'de> + Ord> Decode<'de> for BinaryHeap<T> {
+    #[inline]
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        Vec::decode(reader).map(BinaryHeap::from)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<'de, T: Decode<'de> + Ord> Decode<'de> for BTreeSet<T> {
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        let mut set = BTreeSet::new();
+        decode_set(&"set", reader, |value, policy| if policy == DuplicateKey::Last {
+            set.replace(value).is_none()
+        } else {
+            set.insert(value)
+        })?;
+        Ok(set)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<'de, K: Decode<'de> + Ord, V: Decode<'de>> Decode<'de> for BTreeMap<K, V> {
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        use crate::alloc::collections::btree_map::Entry;
+
+        let mut map = BTreeMap::new();
+        decode_map(&"map", reader, |k, v, policy| match map.entry(k) {
+            Entry::Vacant(entry) => {
+                entry.insert(v);
+                true
+            },
+            Entry::Occupied(mut entry) => {
+                if policy == DuplicateKey::Last {
+                    entry.insert(v);
+                }
+                false
+            }
+        })?;
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "use_std")]
+impl<'de, T, S> Decode<'de> for std::collections::HashSet<T, S>
+where
+    T: Decode<'de> + Eq + core::hash::Hash,
+    S: core::hash::BuildHasher + Default
+{
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        let mut set = std::collections::HashSet::default();
+        decode_set(&"set", reader, |value, policy| if policy == DuplicateKey::Last {
+            set.replace(value).is_none()
+        } else {
+            set.insert(value)
+        })?;
+        Ok(set)
+    }
+}
+
+#[cfg(feature = "use_std")]
+impl<'de, K, V, S> Decode<'de> for std::collections::HashMap<K, V, S>
+where
+    K: Decode<'de> + Eq + core::hash::Hash,
+    V: Decode<'de>,
+    S: core::hash::BuildHasher + Default
+{
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        use std::collections::hash_map::Entry;
+
+        let mut map = std::collections::HashMap::default();
+        decode_map(&"map", reader, |k, v, policy| match map.entry(k) {
+            Entry::Vacant(entry) => {
+                entry.insert(v);
+                true
+            },
+            Entry::Occupied(mut entry) => {
+                if policy == DuplicateKey::Last {
+                    entry.insert(v);
+                }
+                false
+            }
+        })?;
+        Ok(map)
+    }
+}
+
 impl<'de> types::Tag<()> {
     #[inline]
     pub fn tag<R: Read<'de>>(reader: &mut R) -> Result<u64, Error<R::Error>> {
@@ -760,6 +1380,94 @@ impl<'de, T: Decode<'de>> Decode<'de> for Option<T> {
     }
 }
 
+/// Decode the head of an array of exactly `n` items.
+///
+/// Returns true if it is indefinite length, then the items must be followed by break.
+#[inline]
+fn fixed_len<'de, R: Read<'de>>(name: error::StaticStr, reader: &mut R, n: usize)
+    -> Result<bool, Error<R::Error>>
+{
+    match types::Array::len(reader)? {
+        Some(len) if len == n => Ok(false),
+        None => Ok(true),
+        len => Err(Error::require_length(name, len))
+    }
+}
+
+/// Decode from an array of exactly `N` items, without allocation.
+impl<'de, T: Decode<'de>, const N: usize> Decode<'de> for [T; N] {
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        let name = &"array";
+
+        if !reader.step_in() {
+            return Err(Error::depth_overflow(name));
+        }
+        let mut reader = ScopeGuard(reader, |reader| reader.step_out());
+        let reader = &mut *reader;
+
+        let indefinite = fixed_len(name, reader, N)?;
+
+        let mut err = None;
+        let arr = [(); N].map(|_| match err {
+            None => T::decode(reader).map_err(|e| err = Some(e)).ok(),
+            Some(_) => None
+        });
+
+        if let Some(err) = err {
+            return Err(err);
+        }
+
+        if indefinite && !is_break(reader)? {
+            return Err(Error::require_length(name, None));
+        }
+
+        Ok(arr.map(|value| value.unwrap_or_else(|| unreachable!())))
+    }
+}
+
+macro_rules! decode_tuple {
+    ( $( $len:expr => ( $( $t:ident ),+ ) );* $( ; )? ) => {
+        $(
+            impl<'de, $( $t: Decode<'de> ),+> Decode<'de> for ( $( $t, )+ ) {
+                #[inline]
+                fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+                    let name = &"tuple";
+
+                    if !reader.step_in() {
+                        return Err(Error::depth_overflow(name));
+                    }
+                    let mut reader = ScopeGuard(reader, |reader| reader.step_out());
+                    let reader = &mut *reader;
+
+                    let indefinite = fixed_len(name, reader, $len)?;
+                    let value = ( $( $t::decode(reader)?, )+ );
+
+                    if indefinite && !is_break(reader)? {
+                        return Err(Error::require_length(name, None));
+                    }
+
+                    Ok(value)
+                }
+            }
+        )*
+    }
+}
+
+decode_tuple!(
+    1 => (A);
+    2 => (A, B);
+    3 => (A, B, C);
+    4 => (A, B, C, D);
+    5 => (A, B, C, D, E);
+    6 => (A, B, C, D, E, F);
+    7 => (A, B, C, D, E, F, G);
+    8 => (A, B, C, D, E, F, G, H);
+    9 => (A, B, C, D, E, F, G, H, I);
+    10 => (A, B, C, D, E, F, G, H, I, J);
+    11 => (A, B, C, D, E, F, G, H, I, J, K);
+    12 => (A, B, C, D, E, F, G, H, I, J, K, L);
+);
+
 impl<'de, T: Decode<'de>> Decode<'de> for types::Maybe<Option<T>> {
     #[inline]
     fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
@@ -798,36 +1506,55 @@ impl<'de> Decode<'de> for half::f16 {
     }
 }
 
+/// Half precision float is also accepted.
 impl<'de> Decode<'de> for f32 {
     #[inline]
     fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
         let name = &"f32";
         let byte = peek_one(name, reader)?;
 
-        if byte == marker::F32 {
-            reader.advance(1);
-            let mut buf = [0; 4];
-            pull_exact(name, reader, &mut buf)?;
-            Ok(f32::from_be_bytes(buf))
-        } else {
-            Err(Error::mismatch(name, byte))
+        match byte {
+            marker::F16 => types::F16::decode(reader).map(f32::from),
+            marker::F32 => {
+                reader.advance(1);
+                let mut buf = [0; 4];
+                pull_exact(name, reader, &mut buf)?;
+                let v = f32::from_be_bytes(buf);
+
+                if reader.is_strict() && crate::util::f32_is_f16(v) {
+                    return Err(Error::non_preferred_float(name));
+                }
+
+                Ok(v)
+            },
+            _ => Err(Error::mismatch(name, byte))
         }
     }
 }
 
+/// Half and single precision floats are also accepted.
 impl<'de> Decode<'de> for f64 {
     #[inline]
     fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
         let name = &"f64";
         let byte = peek_one(name, reader)?;
 
-        if byte == marker::F64 {
-            reader.advance(1);
-            let mut buf = [0; 8];
-            pull_exact(name, reader, &mut buf)?;
-            Ok(f64::from_be_bytes(buf))
-        } else {
-            Err(Error::mismatch(name, byte))
+        match byte {
+            marker::F16 => types::F16::decode(reader).map(f64::from),
+            marker::F32 => f32::decode(reader).map(f64::from),
+            marker::F64 => {
+                reader.advance(1);
+                let mut buf = [0; 8];
+                pull_exact(name, reader, &mut buf)?;
+                let v = f64::from_be_bytes(buf);
+
+                if reader.is_strict() && crate::util::f64_is_f32(v) {
+                    return Err(Error::non_preferred_float(name));
+                }
+
+                Ok(v)
+            },
+            _ => Err(Error::mismatch(name, byte))
         }
     }
 }
@@ -847,6 +1574,197 @@ impl<'de, T: Decode<'de>> Decode<'de> for Box<T> {
     }
 }
 
+#[cfg(feature = "use_alloc")]
+impl<'de> Decode<'de> for Box<str> {
+    #[inline]
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        String::decode(reader).map(String::into_boxed_str)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<'de, T: Decode<'de>> Decode<'de> for Box<[T]> {
+    #[inline]
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        Vec::decode(reader).map(Vec::into_boxed_slice)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<'de, T: Decode<'de>> Decode<'de> for crate::alloc::rc::Rc<T> {
+    #[inline]
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        T::decode(reader).map(crate::alloc::rc::Rc::new)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<'de, T: Decode<'de>> Decode<'de> for crate::alloc::sync::Arc<T> {
+    #[inline]
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        T::decode(reader).map(crate::alloc::sync::Arc::new)
+    }
+}
+
+/// Decode a definite length string that fits in the buffer, without allocation.
+fn decode_short_str<'a, 'de, R: Read<'de>>(name: error::StaticStr, reader: &mut R, buf: &'a mut [u8])
+    -> Result<&'a str, Error<R::Error>>
+{
+    let len = decode_len(TypeNum::new(name, major::STRING), reader)?;
+    match len {
+        Some(len) if len <= buf.len() => {
+            let buf = &mut buf[..len];
+            pull_exact(name, reader, buf)?;
+            core::str::from_utf8(buf).map_err(|_| Error::require_utf8(name))
+        },
+        _ => Err(Error::require_length(name, len))
+    }
+}
+
+/// Decode from a text string of exactly one char.
+impl<'de> Decode<'de> for char {
+    #[inline]
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        let name = &"char";
+        let mut buf = [0; 4];
+        let s = decode_short_str(name, reader, &mut buf)?;
+        let mut iter = s.chars();
+
+        match (iter.next(), iter.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(Error::require_length(name, Some(s.len())))
+        }
+    }
+}
+
+/// Decode from an empty array.
+impl<'de> Decode<'de> for () {
+    #[inline]
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        let name = &"unit";
+        let byte = pull_one(name, reader)?;
+
+        if byte == (major::ARRAY << 5) {
+            Ok(())
+        } else {
+            Err(Error::mismatch(name, byte))
+        }
+    }
+}
+
+/// Decode from a single entry map, `{"Ok": value}` or `{"Err": error}`.
+impl<'de, T: Decode<'de>, E: Decode<'de>> Decode<'de> for Result<T, E> {
+    #[inline]
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        let name = &"result";
+        let len = types::Map::len(reader)?;
+        if len != Some(1) {
+            return Err(Error::require_length(name, len));
+        }
+
+        let byte = peek_one(name, reader)?;
+        let mut buf = [0; 3];
+        match decode_short_str(name, reader, &mut buf) {
+            Ok("Ok") => T::decode(reader).map(Ok),
+            Ok("Err") => E::decode(reader).map(Err),
+            Ok(_) | Err(Error::RequireLength { .. }) => Err(Error::mismatch(name, byte)),
+            Err(err) => Err(err)
+        }
+    }
+}
+
+/// Decode from a map, `{"secs": u64, "nanos": u32}`, or an array, `[secs, nanos]`.
+/// Field of duration map
+enum DurationField {
+    Secs,
+    Nanos
+}
+
+impl<'de> Decode<'de> for DurationField {
+    #[inline]
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        let name = &"duration";
+        let byte = peek_one(name, reader)?;
+        let mut buf = [0; 5];
+        match decode_short_str(name, reader, &mut buf) {
+            Ok("secs") => Ok(DurationField::Secs),
+            Ok("nanos") => Ok(DurationField::Nanos),
+            Ok(_) | Err(Error::RequireLength { .. }) => Err(Error::mismatch(name, byte)),
+            Err(err) => Err(err)
+        }
+    }
+}
+
+impl<'de> Decode<'de> for core::time::Duration {
+    fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+        let name = &"duration";
+        let byte = peek_one(name, reader)?;
+
+        let (secs, nanos) = if if_major(byte) == major::ARRAY {
+            <(u64, u32)>::decode(reader)?
+        } else {
+            if !reader.step_in() {
+                return Err(Error::depth_overflow(name));
+            }
+            let mut reader = ScopeGuard(reader, |reader| reader.step_out());
+            let reader = &mut *reader;
+
+            let len = types::Map::len(reader)?;
+            if len != Some(2) {
+                return Err(Error::require_length(name, len));
+            }
+
+            let mut order = KeyOrder::new();
+            let mut secs = None;
+            let mut nanos = None;
+            for _ in 0..2 {
+                match order.decode(name, reader)? {
+                    DurationField::Secs if secs.is_none() => secs = Some(u64::decode(reader)?),
+                    DurationField::Nanos if nanos.is_none() => nanos = Some(u32::decode(reader)?),
+                    _ => return Err(Error::duplicate_key(name))
+                }
+            }
+
+            match (secs, nanos) {
+                (Some(secs), Some(nanos)) => (secs, nanos),
+                _ => unreachable!()
+            }
+        };
+
+        // same as `Duration::new`, but without panic
+        secs.checked_add(u64::from(nanos / 1_000_000_000))
+            .map(|secs| core::time::Duration::new(secs, nanos % 1_000_000_000))
+            .ok_or_else(|| Error::arithmetic_overflow(name, error::ArithmeticOverflow::Overflow))
+    }
+}
+
+macro_rules! decode_nonzero {
+    ( $( $t:ty = $t2:ty ),* ) => {
+        $(
+            impl<'de> Decode<'de> for $t {
+                #[inline]
+                fn decode<R: Read<'de>>(reader: &mut R) -> Result<Self, Error<R::Error>> {
+                    let n = <$t2>::decode(reader)?;
+                    <$t>::new(n).ok_or_else(|| Error::cast_overflow(&"nonzero"))
+                }
+            }
+        )*
+    }
+}
+
+decode_nonzero!(
+    core::num::NonZeroU8 = u8,
+    core::num::NonZeroU16 = u16,
+    core::num::NonZeroU32 = u32,
+    core::num::NonZeroU64 = u64,
+    core::num::NonZeroU128 = u128,
+    core::num::NonZeroI8 = i8,
+    core::num::NonZeroI16 = i16,
+    core::num::NonZeroI32 = i32,
+    core::num::NonZeroI64 = i64,
+    core::num::NonZeroI128 = i128
+);
+
 /// Ignore an arbitrary object
 pub struct IgnoredAny;
 
@@ -863,38 +1781,32 @@ impl<'de> Decode<'de> for IgnoredAny {
         let byte = peek_one(name, reader)?;
 
         match if_major(byte) {
-            major::UNSIGNED | major::NEGATIVE => {
-                let skip = match low(byte) {
-                    0 ..= 0x17 => 0,
-                    0x18 => 1,
-                    0x19 => 2,
-                    0x1a => 4,
-                    0x1b => 8,
-                    _ => return Err(Error::mismatch(name, byte))
-                };
-                skip_exact(name, reader, skip + 1)?;
+            major @ major::UNSIGNED | major @ major::NEGATIVE => {
+                let _n = TypeNum::new(name, major).decode_u64(reader)?;
             },
             major @ major::BYTES | major @ major::STRING |
             major @ major::ARRAY | major @ major::MAP => {
+                let mut order = KeyOrder::new();
+
                 if let Some(len) = decode_len(TypeNum::new(name, major), reader)? {
                     match major {
                         major::BYTES | major::STRING => skip_exact(name, reader, len)?,
-                        major::ARRAY | major::MAP => for _ in 0..len {
+                        major::ARRAY => for _ in 0..len {
+                            let _ignore = IgnoredAny::decode(reader)?;
+                        },
+                        major::MAP => for _ in 0..len {
+                            let _ignore: IgnoredAny = order.decode(name, reader)?;
                             let _ignore = IgnoredAny::decode(reader)?;
-
-                            if major == major::MAP {
-                                let _ignore = IgnoredAny::decode(reader)?;
-                            }
                         },
                         _ => ()
                     }
                 } else {
                     while !is_break(reader)? {
-                        let _ignore = IgnoredAny::decode(reader)?;
-
                         if major == major::MAP {
-                            let _ignore = IgnoredAny::decode(reader)?;
+                            let _ignore: IgnoredAny = order.decode(name, reader)?;
                         }
+
+                        let _ignore = IgnoredAny::decode(reader)?;
                     }
                 }
             },
@@ -903,17 +1815,24 @@ impl<'de> Decode<'de> for IgnoredAny {
                 let _ignore = IgnoredAny::decode(reader)?;
             },
             major::SIMPLE => {
-                let skip = match byte {
+                match byte {
                     marker::FALSE
                         | marker::TRUE
                         | marker::NULL
-                        | marker::UNDEFINED => 0,
-                    marker::F16 => 2,
-                    marker::F32 => 4,
-                    marker::F64 => 8,
+                        | marker::UNDEFINED => reader.advance(1),
+                    0xe0 ..= 0xf3 | 0xf8 => {
+                        let _ignore = types::Simple::decode(reader)?;
+                    },
+                    marker::F16 => skip_exact(name, reader, 3)?,
+                    // check for preferred float in strict mode
+                    marker::F32 => {
+                        let _ignore = f32::decode(reader)?;
+                    },
+                    marker::F64 => {
+                        let _ignore = f64::decode(reader)?;
+                    },
                     _ => return Err(Error::unsupported(name, byte))
-                };
-                skip_exact(name, reader, skip + 1)?;
+                }
             },
             _ => return Err(Error::unsupported(name, byte))
         }
@@ -922,6 +1841,52 @@ impl<'de> Decode<'de> for IgnoredAny {
     }
 }
 
+/// Iterator of items in a CBOR sequence (rfc8742)
+///
+/// It decodes items until the reader ends cleanly between items.
+/// A truncated item returns an `Eof` error, and the iteration stops after any error.
+pub struct Sequence<'de, R, T> {
+    reader: R,
+    done: bool,
+    _phantom: PhantomData<fn(&'de [u8]) -> T>
+}
+
+impl<'de, R, T> Sequence<'de, R, T> {
+    pub fn new(reader: R) -> Sequence<'de, R, T> {
+        Sequence { reader, done: false, _phantom: PhantomData }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<'de, R: Read<'de>, T: Decode<'de>> Iterator for Sequence<'de, R, T> {
+    type Item = Result<T, Error<R::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.reader.fill(1) {
+            Ok(buf) if buf.as_ref().is_empty() => {
+                self.done = true;
+                return None;
+            },
+            Ok(_) => (),
+            Err(err) => {
+                self.done = true;
+                return Some(Err(Error::Read(err)));
+            }
+        }
+
+        let ret = T::decode(&mut self.reader);
+        self.done = ret.is_err();
+        Some(ret)
+    }
+}
+
 #[inline]
 pub fn is_break<'de, R: Read<'de>>(reader: &mut R) -> Result<bool, Error<R::Error>> {
     if peek_one(&"break", reader)? == marker::BREAK {
@@ -932,6 +1897,18 @@ pub fn is_break<'de, R: Read<'de>>(reader: &mut R) -> Result<bool, Error<R::Erro
     }
 }
 
+/// Check that the reader has no data left.
+///
+/// Returns [`TrailingData`](crate::core::error::DecodeError::TrailingData) if there is data after the decoded item.
+#[inline]
+pub fn end<'de, R: Read<'de>>(reader: &mut R) -> Result<(), Error<R::Error>> {
+    if reader.fill(1)?.as_ref().is_empty() {
+        Ok(())
+    } else {
+        Err(Error::trailing_data(reader.offset()))
+    }
+}
+
 /// Determine the object type from the given byte.
 #[inline]
 pub fn if_major(byte: u8) -> u8 {
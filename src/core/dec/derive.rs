@@ -0,0 +1,261 @@
+//! Support for `cbor4ii-derive`, this is not a public API.
+
+use crate::core::{ major, types, error::StaticStr };
+use crate::core::dec::{ Read, Decode, Error, DuplicateKey, TypeNum, IgnoredAny, peek_one, decode_len, is_break, if_major };
+
+#[cfg(feature = "use_alloc")]
+use crate::core::dec::{ KeyOrder, KeyRecorder };
+
+
+/// Field or variant key
+pub enum Key {
+    Str(&'static str),
+    Int(i64)
+}
+
+/// Peek the first byte of the next item.
+#[inline]
+pub fn peek<'de, R: Read<'de>>(name: StaticStr, reader: &mut R) -> Result<u8, Error<R::Error>> {
+    peek_one(name, reader)
+}
+
+#[cold]
+pub fn missing_field<E>(name: StaticStr) -> Error<E> {
+    Error::missing_field(name)
+}
+
+#[cold]
+pub fn duplicate_key<E>(name: StaticStr) -> Error<E> {
+    Error::duplicate_key(name)
+}
+
+#[cold]
+pub fn mismatch<E>(name: StaticStr, found: u8) -> Error<E> {
+    Error::mismatch(name, found)
+}
+
+#[cold]
+pub fn require_length<E>(name: StaticStr, found: Option<usize>) -> Error<E> {
+    Error::require_length(name, found)
+}
+
+/// Decode with depth accounting.
+#[inline]
+pub fn nested<'de, R, T, F>(name: StaticStr, reader: &mut R, f: F) -> Result<T, Error<R::Error>>
+where
+    R: Read<'de>,
+    F: FnOnce(&mut R) -> Result<T, Error<R::Error>>
+{
+    if !reader.step_in() {
+        return Err(Error::depth_overflow(name));
+    }
+    let ret = f(reader);
+    reader.step_out();
+    ret
+}
+
+/// Decode the tag and check that it is the expected one.
+#[inline]
+pub fn expect_tag<'de, R: Read<'de>>(name: StaticStr, reader: &mut R, tag: u64)
+    -> Result<(), Error<R::Error>>
+{
+    let byte = peek_one(name, reader)?;
+    if types::Tag::tag(reader)? == tag {
+        Ok(())
+    } else {
+        Err(Error::mismatch(name, byte))
+    }
+}
+
+/// Decode a key, and return its index in keys if found.
+///
+/// Unknown integer and string keys are consumed, other keys are ignored.
+/// String keys are compared as they are read, so this works without allocation
+/// and with readers that can not borrow.
+pub fn decode_key<'de, R: Read<'de>>(name: StaticStr, reader: &mut R, keys: &[Key])
+    -> Result<Option<usize>, Error<R::Error>>
+{
+    let byte = peek_one(name, reader)?;
+    match if_major(byte) {
+        major::UNSIGNED | major::NEGATIVE => {
+            let n = i128::decode(reader)?;
+            Ok(keys.iter().position(|key| matches!(key, Key::Int(k) if i128::from(*k) == n)))
+        },
+        major::STRING => {
+            let num = TypeNum::new(name, major::STRING);
+            let mut matcher = KeyMatcher::new(keys);
+
+            if let Some(len) = decode_len(num, reader)? {
+                matcher.read(name, reader, len)?;
+            } else {
+                while !is_break(reader)? {
+                    let len = decode_len(num, reader)?
+                        .ok_or_else(|| Error::require_length(name, None))?;
+                    matcher.read(name, reader, len)?;
+                }
+            }
+
+            Ok(matcher.finish())
+        },
+        _ => {
+            let _ignore = IgnoredAny::decode(reader)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Find the first string key that matches the bytes read.
+struct KeyMatcher<'a> {
+    keys: &'a [Key],
+    candidate: Option<usize>,
+    pos: usize
+}
+
+impl<'a> KeyMatcher<'a> {
+    fn new(keys: &'a [Key]) -> KeyMatcher<'a> {
+        let candidate = keys.iter().position(|key| matches!(key, Key::Str(_)));
+        KeyMatcher { keys, candidate, pos: 0 }
+    }
+
+    fn key(&self, i: usize) -> Option<&'a [u8]> {
+        match self.keys.get(i) {
+            Some(Key::Str(key)) => Some(key.as_bytes()),
+            _ => None
+        }
+    }
+
+    fn update(&mut self, chunk: &[u8]) {
+        let start = self.pos;
+        let end = start + chunk.len();
+
+        // the bytes read so far are the prefix of the current candidate,
+        // so a later key can only match if it has the same prefix.
+        if let Some(i) = self.candidate {
+            let read = self.key(i).map(|key| &key[..start]);
+            self.candidate = read.and_then(|read| (i..self.keys.len())
+                .find(|&j| match self.key(j) {
+                    Some(key) => key.len() >= end
+                        && &key[..start] == read
+                        && &key[start..end] == chunk,
+                    None => false
+                })
+            );
+        }
+
+        self.pos = end;
+    }
+
+    fn read<'de, R: Read<'de>>(&mut self, name: StaticStr, reader: &mut R, mut len: usize)
+        -> Result<(), Error<R::Error>>
+    {
+        while len != 0 {
+            let buf = reader.fill(len)?;
+            let buf = buf.as_ref();
+
+            if buf.is_empty() {
+                return Err(Error::eof(name, len));
+            }
+
+            let n = core::cmp::min(buf.len(), len);
+            self.update(&buf[..n]);
+            reader.advance(n);
+            len -= n;
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Option<usize> {
+        // the candidate may be longer than the bytes read,
+        // so find the first key that is exactly the bytes read.
+        let i = self.candidate?;
+        let read = self.key(i).map(|key| &key[..self.pos])?;
+        (i..self.keys.len()).find(|&j| self.key(j) == Some(read))
+    }
+}
+
+/// Items of array or map, definite or indefinite length.
+pub struct Items {
+    len: Option<usize>,
+    count: usize,
+    done: bool,
+    #[cfg(feature = "use_alloc")]
+    order: KeyOrder
+}
+
+impl Items {
+    #[inline]
+    pub fn new(len: Option<usize>) -> Items {
+        Items {
+            len,
+            count: 0,
+            done: false,
+            #[cfg(feature = "use_alloc")]
+            order: KeyOrder::new()
+        }
+    }
+
+    /// Decode a map key like [`decode_key`],
+    /// and check the order of keys in strict mode.
+    #[inline]
+    pub fn decode_key<'de, R: Read<'de>>(&mut self, name: StaticStr, reader: &mut R, keys: &[Key])
+        -> Result<Option<usize>, Error<R::Error>>
+    {
+        #[cfg(feature = "use_alloc")]
+        if reader.is_strict() {
+            let mut recorder = KeyRecorder::new(reader);
+            let key = decode_key(name, &mut recorder, keys)?;
+            self.order.check(name, recorder.into_key())?;
+            return Ok(key);
+        }
+
+        decode_key(name, reader, keys)
+    }
+
+    /// Returns true if there is a next item.
+    #[inline]
+    pub fn has_next<'de, R: Read<'de>>(&mut self, reader: &mut R) -> Result<bool, Error<R::Error>> {
+        if self.done {
+            return Ok(false);
+        }
+
+        let next = match self.len {
+            Some(len) => self.count < len,
+            None => !is_break(reader)?
+        };
+
+        if next {
+            self.count += 1;
+        } else {
+            self.done = true;
+        }
+
+        Ok(next)
+    }
+
+    /// Check that all items have been read.
+    #[inline]
+    pub fn end<'de, R: Read<'de>>(mut self, name: StaticStr, reader: &mut R)
+        -> Result<(), Error<R::Error>>
+    {
+        if self.has_next(reader)? {
+            Err(Error::require_length(name, self.len))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Returns true if the duplicate field should replace the previous one,
+/// according to [`Read::duplicate_key`].
+#[inline]
+pub fn replace_duplicate<'de, R: Read<'de>>(name: StaticStr, reader: &mut R)
+    -> Result<bool, Error<R::Error>>
+{
+    match reader.duplicate_key() {
+        _ if reader.is_strict() => Err(Error::duplicate_key(name)),
+        DuplicateKey::Error => Err(Error::duplicate_key(name)),
+        DuplicateKey::First => Ok(false),
+        DuplicateKey::Last => Ok(true)
+    }
+}
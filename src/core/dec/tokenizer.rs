@@ -0,0 +1,389 @@
+use core::convert::TryFrom;
+use crate::core::{ major, marker, types };
+use crate::core::dec::{
+    Read, Reference, Error, Decode, TypeNum,
+    peek_one, decode_len, skip_exact, if_major, low
+};
+
+
+/// Maximum nesting of containers and tags.
+const STACK_SIZE: usize = 256;
+
+/// Decode event
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Token {
+    Unsigned(u64),
+    /// Negative integer `-1 - n`
+    Negative(u64),
+    /// Byte string with definite length, or the start of indefinite length byte string.
+    ///
+    /// The content can be read by [`Tokenizer::read`].
+    /// An indefinite length byte string is followed by chunks, which are
+    /// definite length byte strings, and `Break`.
+    Bytes(Option<usize>),
+    /// Text string, same as `Bytes`
+    Text(Option<usize>),
+    ArrayStart(Option<usize>),
+    /// Map start, with the number of pairs
+    MapStart(Option<usize>),
+    /// Tag, followed by its content item
+    Tag(u64),
+    /// Simple value, including `false` (20), `true` (21), `null` (22) and `undefined` (23)
+    Simple(u8),
+    /// Half, single or double precision float
+    Float(f64),
+    /// End of indefinite length item
+    Break
+}
+
+/// Pull parser
+///
+/// This yields a token for each head of the items from reader,
+/// and tracks the nesting of items without allocation.
+///
+/// Depth is accounted by [`Read::step_in`] for containers and tags.
+/// In strict mode, map key order is not checked.
+pub struct Tokenizer<R> {
+    reader: R,
+    /// remaining items of open containers, `None` for indefinite length
+    stack: [Option<u64>; STACK_SIZE],
+    depth: usize,
+    /// major type of the chunks if in indefinite length string
+    chunks: Option<u8>,
+    /// unread length of string content
+    string: usize,
+    /// length of string content returned by `read` but not advanced
+    pending: usize
+}
+
+impl<'de, R: Read<'de>> Tokenizer<R> {
+    pub fn new(reader: R) -> Tokenizer<R> {
+        Tokenizer {
+            reader,
+            stack: [None; STACK_SIZE],
+            depth: 0,
+            chunks: None,
+            string: 0,
+            pending: 0
+        }
+    }
+
+    /// Number of open containers and tags.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the reader.
+    ///
+    /// If an item is not complete, the depth of its open containers and tags
+    /// is stepped out of the reader, and the rest of the item is left unread.
+    pub fn into_inner(mut self) -> R {
+        for _ in 0..self.depth {
+            self.reader.step_out();
+        }
+        self.reader
+    }
+
+    /// Returns the next token,
+    /// or `None` if the reader ends after a complete top level item.
+    ///
+    /// Unread string content is skipped.
+    pub fn next_token(&mut self) -> Result<Option<Token>, Error<R::Error>> {
+        let name = &"token";
+
+        self.reader.advance(self.pending);
+        self.pending = 0;
+
+        if self.string != 0 {
+            skip_exact(name, &mut self.reader, self.string)?;
+            self.string = 0;
+        }
+
+        if self.depth == 0 && self.chunks.is_none() && self.reader.fill(1)?.as_ref().is_empty() {
+            return Ok(None);
+        }
+
+        let byte = peek_one(name, &mut self.reader)?;
+
+        if let Some(major) = self.chunks {
+            return if byte == marker::BREAK {
+                self.reader.advance(1);
+                self.chunks = None;
+                self.complete();
+                Ok(Some(Token::Break))
+            } else if if_major(byte) == major && low(byte) != marker::START {
+                Ok(Some(self.string(major)?))
+            } else {
+                Err(Error::mismatch(&"token::chunk", byte))
+            };
+        }
+
+        if byte == marker::BREAK {
+            return match self.depth.checked_sub(1) {
+                Some(depth) if self.stack[depth].is_none() => {
+                    self.reader.advance(1);
+                    self.pop();
+                    Ok(Some(Token::Break))
+                },
+                _ => Err(Error::unsupported(name, byte))
+            };
+        }
+
+        let token = match if_major(byte) {
+            major::UNSIGNED => Token::Unsigned(TypeNum::new(name, major::UNSIGNED).decode_u64(&mut self.reader)?),
+            major::NEGATIVE => Token::Negative(TypeNum::new(name, major::NEGATIVE).decode_u64(&mut self.reader)?),
+            major @ major::BYTES | major @ major::STRING => {
+                match decode_len(TypeNum::new(name, major), &mut self.reader)? {
+                    Some(len) => {
+                        self.string = len;
+                        self.complete();
+                        len_token(major, Some(len))
+                    },
+                    None => {
+                        self.chunks = Some(major);
+                        len_token(major, None)
+                    }
+                }
+            },
+            major::ARRAY => {
+                let len = types::Array::len(&mut self.reader)?;
+                self.push(name, len.map(|len| len as u64))?;
+                Token::ArrayStart(len)
+            },
+            major::MAP => {
+                let len = types::Map::len(&mut self.reader)?;
+                let count = match len {
+                    Some(len) => Some((len as u64).checked_mul(2)
+                        .ok_or_else(|| Error::length_overflow(name, len))?),
+                    None => None
+                };
+                self.push(name, count)?;
+                Token::MapStart(len)
+            },
+            major::TAG => {
+                let tag = types::Tag::tag(&mut self.reader)?;
+                self.push(name, Some(1))?;
+                return Ok(Some(Token::Tag(tag)));
+            },
+            _ => {
+                let token = match byte {
+                    marker::F16 => {
+                        let types::F16(bits) = types::F16::decode(&mut self.reader)?;
+                        Token::Float(crate::util::f16_to_f64(bits))
+                    },
+                    marker::F32 => Token::Float(f32::decode(&mut self.reader)?.into()),
+                    marker::F64 => Token::Float(f64::decode(&mut self.reader)?),
+                    _ => Token::Simple(types::Simple::decode(&mut self.reader)?.0)
+                };
+                self.complete();
+                token
+            }
+        };
+
+        if let Token::Unsigned(_) | Token::Negative(_) = token {
+            self.complete();
+        }
+
+        Ok(Some(token))
+    }
+
+    /// Read the content of current string token.
+    ///
+    /// It returns the content in one or more parts, and `None` at the end.
+    /// Text content is not checked for UTF-8.
+    pub fn read(&mut self) -> Result<Option<Reference<'de, '_>>, Error<R::Error>> {
+        let name = &"token::read";
+
+        self.reader.advance(self.pending);
+        self.pending = 0;
+
+        if self.string == 0 {
+            return Ok(None);
+        }
+
+        let buf = self.reader.fill(self.string)?.take(self.string);
+        let len = buf.as_ref().len();
+        if len == 0 {
+            return Err(Error::eof(name, self.string));
+        }
+
+        self.string -= len;
+        self.pending = len;
+        Ok(Some(buf))
+    }
+
+    fn string(&mut self, major: u8) -> Result<Token, Error<R::Error>> {
+        let len = TypeNum::new(&"token::chunk", major).decode_u64(&mut self.reader)?;
+        let len = usize::try_from(len)
+            .map_err(|_| Error::cast_overflow(&"token::chunk"))?;
+        self.string = len;
+        Ok(len_token(major, Some(len)))
+    }
+
+    fn push(&mut self, name: crate::core::error::StaticStr, count: Option<u64>)
+        -> Result<(), Error<R::Error>>
+    {
+        if self.depth == STACK_SIZE || !self.reader.step_in() {
+            return Err(Error::depth_overflow(name));
+        }
+
+        self.stack[self.depth] = count;
+        self.depth += 1;
+
+        if count == Some(0) {
+            self.pop();
+        }
+
+        Ok(())
+    }
+
+    /// Close the innermost container, which completes an item of its parent.
+    fn pop(&mut self) {
+        self.depth -= 1;
+        self.reader.step_out();
+        self.complete();
+    }
+
+    /// Complete an item of the innermost container.
+    fn complete(&mut self) {
+        if let Some(depth) = self.depth.checked_sub(1) {
+            if let Some(count) = self.stack[depth].as_mut() {
+                *count -= 1;
+                if *count == 0 {
+                    self.pop();
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+fn len_token(major: u8, len: Option<usize>) -> Token {
+    if major == major::BYTES {
+        Token::Bytes(len)
+    } else {
+        Token::Text(len)
+    }
+}
+
+#[test]
+#[cfg(feature = "use_std")]
+fn test_tokenizer() {
+    use crate::core::utils::{ SliceReader, IoReader };
+    use crate::alloc::vec::Vec;
+
+    type List = Vec<(Token, usize, Vec<u8>)>;
+
+    fn tokens<'de, R: Read<'de>>(reader: R) -> Result<List, Error<R::Error>> {
+        let mut tokenizer = Tokenizer::new(reader);
+        let mut list = Vec::new();
+
+        while let Some(token) = tokenizer.next_token()? {
+            let mut buf = Vec::new();
+            while let Some(part) = tokenizer.read()? {
+                buf.extend_from_slice(part.as_ref());
+            }
+            list.push((token, tokenizer.depth(), buf));
+        }
+
+        Ok(list)
+    }
+
+    // [1, {"a": [_ h'01', (_ h'02', h'0304')]}, 2(-1), 1.5, true]
+    // followed by another top level item
+    let input = data_encoding::HEXLOWER.decode(
+        b"8501a161619f41015f410242\
+          0304ffffc220f93e00f5\
+          f97e00"
+    ).unwrap();
+
+    let expected = [
+        (Token::ArrayStart(Some(5)), 1, &[][..]),
+        (Token::Unsigned(1), 1, &[]),
+        (Token::MapStart(Some(1)), 2, &[]),
+        (Token::Text(Some(1)), 2, b"a"),
+        (Token::ArrayStart(None), 3, &[]),
+        (Token::Bytes(Some(1)), 3, &[1]),
+        (Token::Bytes(None), 3, &[]),
+        (Token::Bytes(Some(1)), 3, &[2]),
+        (Token::Bytes(Some(2)), 3, &[3, 4]),
+        (Token::Break, 3, &[]),
+        (Token::Break, 1, &[]),
+        (Token::Tag(2), 2, &[]),
+        (Token::Negative(0), 1, &[]),
+        (Token::Float(1.5), 1, &[]),
+        (Token::Simple(21), 0, &[]),
+    ];
+
+    let list = tokens(SliceReader::new(&input)).unwrap();
+    assert_eq!(list.len(), expected.len() + 1);
+    for (found, expected) in list.iter().zip(expected.iter()) {
+        assert_eq!((found.0, found.1, found.2.as_slice()), *expected);
+    }
+    assert!(matches!(list[expected.len()].0, Token::Float(v) if v.is_nan()));
+
+    // read one byte at a time
+    let reader = std::io::BufReader::with_capacity(1, input.as_slice());
+    let list2 = tokens(IoReader::new(reader)).unwrap();
+    assert_eq!(list.len(), list2.len());
+    for (a, b) in list.iter().zip(list2.iter()).take(expected.len()) {
+        assert_eq!(a, b);
+    }
+
+    // unread content is skipped
+    let mut tokenizer = Tokenizer::new(SliceReader::new(&[0x82, 0x43, 1, 2, 3, 0x00]));
+    assert_eq!(tokenizer.next_token().unwrap(), Some(Token::ArrayStart(Some(2))));
+    assert_eq!(tokenizer.next_token().unwrap(), Some(Token::Bytes(Some(3))));
+    assert_eq!(tokenizer.next_token().unwrap(), Some(Token::Unsigned(0)));
+    assert_eq!(tokenizer.depth(), 0);
+    assert_eq!(tokenizer.next_token().unwrap(), None);
+
+    // empty containers
+    let list = tokens(SliceReader::new(&[0x80, 0xa0, 0xc1, 0x80])).unwrap();
+    let depths = list.iter().map(|(_, depth, _)| *depth).collect::<Vec<_>>();
+    assert_eq!(depths, [0, 0, 1, 0]);
+
+    // errors
+    let cases: &[&[u8]] = &[
+        // unexpected break
+        &[0xff],
+        &[0x81, 0xff],
+        // eof in container
+        &[0x82, 0x01],
+        &[0x9f, 0x01],
+        &[0xc1],
+        // eof in string
+        &[0x43, 0x01],
+        // bad chunk
+        &[0x5f, 0x61, 0x61, 0xff],
+        &[0x5f, 0x5f, 0xff, 0xff],
+    ];
+    for &input in cases {
+        assert!(tokens(SliceReader::new(input)).is_err(), "{:?}", input);
+    }
+
+    // depth limit
+    let input = [0x81; 300];
+    assert!(matches!(
+        tokens(SliceReader::new(&input)),
+        Err(Error::DepthOverflow { .. })
+    ));
+
+    // depth is restored when the reader is returned in the middle of an item
+    let mut input = vec![0x81; 200];
+    input.push(0x00);
+    input.extend_from_slice(&input.clone());
+    let mut reader = SliceReader::new(&input);
+    let mut tokenizer = Tokenizer::new(&mut reader);
+    for _ in 0..200 {
+        assert_eq!(tokenizer.next_token().unwrap(), Some(Token::ArrayStart(Some(1))));
+    }
+    let _ = tokenizer.into_inner();
+    assert_eq!(tokens(&mut reader).unwrap().len(), 202);
+
+    // strict mode
+    assert!(tokens(SliceReader::new(&[0x18, 0x01]).strict()).is_err());
+    assert!(tokens(SliceReader::new(&[0x9f, 0xff]).strict()).is_err());
+}
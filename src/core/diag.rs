@@ -0,0 +1,1662 @@
+//! diagnostic notation, see rfc8949 section 8
+//!
+//! `Diag` implements `Display` for values,
+//! and the alternate flag (`{:#}`) enables pretty printing.
+//! `parse` converts diagnostic notation back to CBOR.
+
+use core::fmt;
+use crate::core::{ major, marker, types, enc, dec, RawValue };
+use crate::core::enc::Encode;
+use crate::core::error::{ StaticStr, Never };
+use crate::util::ScopeGuard;
+
+#[cfg(feature = "use_alloc")]
+use crate::core::{ Value, BoxedRawValue };
+
+
+/// Diagnostic notation formatter
+pub struct Diag<T>(pub T);
+
+/// Diagnostic notation error
+#[derive(Debug)]
+pub enum Error<E> {
+    Decode(dec::Error<E>),
+    Format(fmt::Error)
+}
+
+impl<E> From<dec::Error<E>> for Error<E> {
+    #[cold]
+    fn from(err: dec::Error<E>) -> Error<E> {
+        Error::Decode(err)
+    }
+}
+
+impl<E> From<fmt::Error> for Error<E> {
+    #[cold]
+    fn from(err: fmt::Error) -> Error<E> {
+        Error::Format(err)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for Error<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Error::Decode(err) => Some(err),
+            Error::Format(err) => Some(err)
+        }
+    }
+}
+
+/// Write the diagnostic notation of an item from reader.
+///
+/// This does not allocate.
+pub fn write<'de, R, W>(reader: &mut R, writer: &mut W, pretty: bool)
+    -> Result<(), Error<R::Error>>
+where
+    R: dec::Read<'de>,
+    W: fmt::Write
+{
+    Printer::new(writer, pretty).item(reader)
+}
+
+impl fmt::Display for Diag<&'_ RawValue<'_>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut reader = crate::core::utils::SliceReader::new(self.0.as_bytes());
+        let pretty = f.alternate();
+        write(&mut reader, f, pretty).map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl fmt::Display for Diag<&'_ BoxedRawValue> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut reader = crate::core::utils::SliceReader::new(self.0.as_bytes());
+        let pretty = f.alternate();
+        write(&mut reader, f, pretty).map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl fmt::Display for Diag<&'_ Value> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pretty = f.alternate();
+        Printer::new(f, pretty).value(self.0)
+    }
+}
+
+struct Printer<'a, W> {
+    writer: &'a mut W,
+    pretty: bool,
+    indent: usize
+}
+
+/// Container start marker, such as `[` or `[_`.
+struct Open {
+    open: &'static str,
+    close: &'static str,
+    indefinite: bool,
+    indicator: Option<u8>
+}
+
+impl<'a, W: fmt::Write> Printer<'a, W> {
+    fn new(writer: &'a mut W, pretty: bool) -> Printer<'a, W> {
+        Printer { writer, pretty, indent: 0 }
+    }
+
+    fn open(&mut self, open: &Open) -> fmt::Result {
+        self.writer.write_str(open.open)?;
+        if open.indefinite {
+            self.writer.write_char('_')?;
+        }
+        indicator(self.writer, open.indicator)?;
+        self.indent += 1;
+        Ok(())
+    }
+
+    fn next(&mut self, open: &Open, first: bool) -> fmt::Result {
+        if !first {
+            self.writer.write_char(',')?;
+        }
+
+        if self.pretty {
+            self.newline()
+        } else if !first || open.indefinite || open.indicator.is_some() {
+            self.writer.write_char(' ')
+        } else {
+            Ok(())
+        }
+    }
+
+    fn close(&mut self, open: &Open, empty: bool) -> fmt::Result {
+        self.indent -= 1;
+
+        if !empty && self.pretty {
+            self.newline()?;
+        } else if empty && (open.indefinite || open.indicator.is_some()) {
+            self.writer.write_char(' ')?;
+        }
+
+        self.writer.write_str(open.close)
+    }
+
+    fn newline(&mut self) -> fmt::Result {
+        self.writer.write_char('\n')?;
+        for _ in 0..self.indent {
+            self.writer.write_str("  ")?;
+        }
+        Ok(())
+    }
+
+    fn item<'de, R: dec::Read<'de>>(&mut self, reader: &mut R) -> Result<(), Error<R::Error>> {
+        let name = &"diag";
+
+        if !reader.step_in() {
+            return Err(dec::Error::depth_overflow(name).into());
+        }
+        let mut reader = ScopeGuard(reader, |reader| reader.step_out());
+        let reader = &mut *reader;
+
+        let byte = dec::peek_one(name, reader)?;
+        let major = dec::if_major(byte);
+
+        if major == major::SIMPLE {
+            return self.simple(reader, byte);
+        }
+
+        if dec::low(byte) == marker::START {
+            reader.advance(1);
+
+            return match major {
+                major::BYTES | major::STRING => self.chunks(reader, major),
+                major::ARRAY => self.array(reader, None, Open {
+                    open: "[",
+                    close: "]",
+                    indefinite: true,
+                    indicator: None
+                }),
+                major::MAP => self.map(reader, None, Open {
+                    open: "{",
+                    close: "}",
+                    indefinite: true,
+                    indicator: None
+                }),
+                _ => Err(dec::Error::unsupported(name, byte).into())
+            };
+        }
+
+        let (arg, ind) = head(name, reader)?;
+
+        match major {
+            major::UNSIGNED => write!(self.writer, "{}", arg)?,
+            major::NEGATIVE => write!(self.writer, "{}", -1 - i128::from(arg))?,
+            major::BYTES => {
+                let len = len(name, arg)?;
+                self.writer.write_str("h'")?;
+                bytes(name, reader, self.writer, len)?;
+                self.writer.write_char('\'')?;
+            },
+            major::STRING => {
+                let len = len(name, arg)?;
+                self.writer.write_char('"')?;
+                text(name, reader, self.writer, len)?;
+                self.writer.write_char('"')?;
+            },
+            major::ARRAY => {
+                let len = len(name, arg)?;
+                return self.array(reader, Some(len), Open {
+                    open: "[",
+                    close: "]",
+                    indefinite: false,
+                    indicator: ind
+                });
+            },
+            major::MAP => {
+                let len = len(name, arg)?;
+                return self.map(reader, Some(len), Open {
+                    open: "{",
+                    close: "}",
+                    indefinite: false,
+                    indicator: ind
+                });
+            },
+            _ => {
+                write!(self.writer, "{}", arg)?;
+                indicator(self.writer, ind)?;
+                self.writer.write_char('(')?;
+                self.item(reader)?;
+                self.writer.write_char(')')?;
+                return Ok(());
+            }
+        }
+
+        indicator(self.writer, ind)?;
+        Ok(())
+    }
+
+    fn simple<'de, R: dec::Read<'de>>(&mut self, reader: &mut R, byte: u8)
+        -> Result<(), Error<R::Error>>
+    {
+        let name = &"diag::simple";
+
+        match byte {
+            marker::FALSE => self.writer.write_str("false")?,
+            marker::TRUE => self.writer.write_str("true")?,
+            marker::NULL => self.writer.write_str("null")?,
+            marker::UNDEFINED => self.writer.write_str("undefined")?,
+            marker::F16 => {
+                let mut buf = [0; 2];
+                reader.advance(1);
+                dec::pull_exact(name, reader, &mut buf)?;
+                let v = crate::util::f16_to_f64(u16::from_be_bytes(buf));
+                float(self.writer, v, 1)?;
+                return Ok(());
+            },
+            marker::F32 => {
+                let mut buf = [0; 4];
+                reader.advance(1);
+                dec::pull_exact(name, reader, &mut buf)?;
+                let v = f32::from_be_bytes(buf);
+                if v.is_finite() {
+                    write!(self.writer, "{:?}_2", v)?;
+                } else {
+                    float(self.writer, v.into(), 2)?;
+                }
+                return Ok(());
+            },
+            marker::F64 => {
+                let mut buf = [0; 8];
+                reader.advance(1);
+                dec::pull_exact(name, reader, &mut buf)?;
+                float(self.writer, f64::from_be_bytes(buf), 3)?;
+                return Ok(());
+            },
+            0xe0 ..= 0xf3 => write!(self.writer, "simple({})", dec::low(byte))?,
+            0xf8 => {
+                reader.advance(1);
+                let n = dec::pull_one(name, reader)?;
+                write!(self.writer, "simple({})", n)?;
+                return Ok(());
+            },
+            _ => return Err(dec::Error::unsupported(name, byte).into())
+        }
+
+        reader.advance(1);
+        Ok(())
+    }
+
+    fn chunks<'de, R: dec::Read<'de>>(&mut self, reader: &mut R, major: u8)
+        -> Result<(), Error<R::Error>>
+    {
+        let name = &"diag::chunk";
+        let open = Open {
+            open: "(",
+            close: ")",
+            indefinite: true,
+            indicator: None
+        };
+
+        self.open(&open)?;
+        let mut first = true;
+
+        while !dec::is_break(reader)? {
+            // chunks must be definite length strings of the same type
+            let byte = dec::peek_one(name, reader)?;
+            if dec::if_major(byte) != major || dec::low(byte) == marker::START {
+                return Err(dec::Error::mismatch(name, byte).into());
+            }
+
+            self.next(&open, first)?;
+            self.item(reader)?;
+            first = false;
+        }
+
+        self.close(&open, first)?;
+        Ok(())
+    }
+
+    fn array<'de, R: dec::Read<'de>>(&mut self, reader: &mut R, len: Option<usize>, open: Open)
+        -> Result<(), Error<R::Error>>
+    {
+        self.open(&open)?;
+
+        let mut count = 0;
+        while more(reader, len, count)? {
+            self.next(&open, count == 0)?;
+            self.item(reader)?;
+            count += 1;
+        }
+
+        self.close(&open, count == 0)?;
+        Ok(())
+    }
+
+    fn map<'de, R: dec::Read<'de>>(&mut self, reader: &mut R, len: Option<usize>, open: Open)
+        -> Result<(), Error<R::Error>>
+    {
+        self.open(&open)?;
+
+        let mut count = 0;
+        while more(reader, len, count)? {
+            self.next(&open, count == 0)?;
+            self.item(reader)?;
+            self.writer.write_str(": ")?;
+            self.item(reader)?;
+            count += 1;
+        }
+
+        self.close(&open, count == 0)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "use_alloc")]
+    fn value(&mut self, value: &Value) -> fmt::Result {
+        match value {
+            Value::Null => self.writer.write_str("null"),
+            Value::Undefined => self.writer.write_str("undefined"),
+            Value::Bool(true) => self.writer.write_str("true"),
+            Value::Bool(false) => self.writer.write_str("false"),
+            Value::Integer(v) => write!(self.writer, "{}", v),
+            Value::Float(v) => float(self.writer, *v, 0),
+            Value::Bytes(v) => {
+                self.writer.write_str("h'")?;
+                hex(self.writer, v)?;
+                self.writer.write_char('\'')
+            },
+            Value::Text(v) => {
+                self.writer.write_char('"')?;
+                escape(self.writer, v)?;
+                self.writer.write_char('"')
+            },
+            Value::Array(v) => {
+                let open = Open { open: "[", close: "]", indefinite: false, indicator: None };
+                self.open(&open)?;
+                for (i, value) in v.iter().enumerate() {
+                    self.next(&open, i == 0)?;
+                    self.value(value)?;
+                }
+                self.close(&open, v.is_empty())
+            },
+            Value::Map(v) => {
+                let open = Open { open: "{", close: "}", indefinite: false, indicator: None };
+                self.open(&open)?;
+                for (i, (k, value)) in v.iter().enumerate() {
+                    self.next(&open, i == 0)?;
+                    self.value(k)?;
+                    self.writer.write_str(": ")?;
+                    self.value(value)?;
+                }
+                self.close(&open, v.is_empty())
+            },
+            Value::Tag(tag, value) => {
+                write!(self.writer, "{}(", tag)?;
+                self.value(value)?;
+                self.writer.write_char(')')
+            },
+            Value::Simple(v) => write!(self.writer, "simple({})", v)
+        }
+    }
+}
+
+/// Read the argument of head,
+/// and the encoding indicator if it is not in the shortest form.
+fn head<'de, R: dec::Read<'de>>(name: StaticStr, reader: &mut R)
+    -> Result<(u64, Option<u8>), dec::Error<R::Error>>
+{
+    let byte = dec::pull_one(name, reader)?;
+    let (n, min) = match dec::low(byte) {
+        x @ 0 ..= 0x17 => return Ok((x.into(), None)),
+        0x18 => (1, 0x18),
+        0x19 => (2, 0x100),
+        0x1a => (4, 0x1_0000),
+        0x1b => (8, 0x1_0000_0000),
+        _ => return Err(dec::Error::unsupported(name, byte))
+    };
+
+    let mut buf = [0; 8];
+    dec::pull_exact(name, reader, &mut buf[8 - n..])?;
+    let arg = u64::from_be_bytes(buf);
+
+    let ind = if arg < min {
+        Some(dec::low(byte) - 0x18)
+    } else {
+        None
+    };
+
+    Ok((arg, ind))
+}
+
+#[inline]
+fn len<E>(name: StaticStr, arg: u64) -> Result<usize, dec::Error<E>> {
+    use core::convert::TryFrom;
+
+    usize::try_from(arg).map_err(|_| dec::Error::cast_overflow(name))
+}
+
+#[inline]
+fn more<'de, R: dec::Read<'de>>(reader: &mut R, len: Option<usize>, count: usize)
+    -> Result<bool, dec::Error<R::Error>>
+{
+    match len {
+        Some(len) => Ok(count < len),
+        None => dec::is_break(reader).map(|is_break| !is_break)
+    }
+}
+
+fn indicator<W: fmt::Write>(writer: &mut W, ind: Option<u8>) -> fmt::Result {
+    match ind {
+        Some(ind) => write!(writer, "_{}", ind),
+        None => Ok(())
+    }
+}
+
+/// Write float, with the encoding indicator `_1`, `_2` or `_3`
+/// for half, single and double precision, or without it if `ind` is zero.
+fn float<W: fmt::Write>(writer: &mut W, v: f64, ind: u8) -> fmt::Result {
+    if v.is_nan() {
+        writer.write_str("NaN")?;
+    } else if v.is_infinite() {
+        writer.write_str(if v.is_sign_positive() { "Infinity" } else { "-Infinity" })?;
+    } else {
+        write!(writer, "{:?}", v)?;
+    }
+
+    if ind != 0 {
+        write!(writer, "_{}", ind)?;
+    }
+
+    Ok(())
+}
+
+fn hex<W: fmt::Write>(writer: &mut W, buf: &[u8]) -> fmt::Result {
+    for b in buf {
+        write!(writer, "{:02x}", b)?;
+    }
+    Ok(())
+}
+
+fn escape<W: fmt::Write>(writer: &mut W, s: &str) -> fmt::Result {
+    for c in s.chars() {
+        match c {
+            '"' => writer.write_str("\\\"")?,
+            '\\' => writer.write_str("\\\\")?,
+            '\n' => writer.write_str("\\n")?,
+            '\r' => writer.write_str("\\r")?,
+            '\t' => writer.write_str("\\t")?,
+            c if c.is_control() => write!(writer, "\\u{:04x}", u32::from(c))?,
+            c => writer.write_char(c)?
+        }
+    }
+    Ok(())
+}
+
+fn bytes<'de, R: dec::Read<'de>, W: fmt::Write>(
+    name: StaticStr,
+    reader: &mut R,
+    writer: &mut W,
+    mut len: usize
+) -> Result<(), Error<R::Error>> {
+    while len != 0 {
+        let buf = reader.fill(len).map_err(dec::Error::Read)?;
+        let buf = buf.as_ref();
+        let buf = &buf[..core::cmp::min(buf.len(), len)];
+
+        if buf.is_empty() {
+            return Err(dec::Error::eof(name, len).into());
+        }
+
+        hex(writer, buf)?;
+        let n = buf.len();
+        reader.advance(n);
+        len -= n;
+    }
+
+    Ok(())
+}
+
+fn text<'de, R: dec::Read<'de>, W: fmt::Write>(
+    name: StaticStr,
+    reader: &mut R,
+    writer: &mut W,
+    mut len: usize
+) -> Result<(), Error<R::Error>> {
+    // a char may be split between two reads
+    let mut partial = [0; 4];
+    let mut partial_len = 0;
+
+    while len != 0 {
+        let buf = reader.fill(len).map_err(dec::Error::Read)?;
+        let buf = buf.as_ref();
+        let buf = &buf[..core::cmp::min(buf.len(), len)];
+
+        if buf.is_empty() {
+            return Err(dec::Error::eof(name, len).into());
+        }
+
+        let n = if partial_len != 0 {
+            let width = match partial[0] {
+                0xc0 ..= 0xdf => 2,
+                0xe0 ..= 0xef => 3,
+                _ => 4
+            };
+            let n = core::cmp::min(width - partial_len, buf.len());
+            partial[partial_len..][..n].copy_from_slice(&buf[..n]);
+            partial_len += n;
+
+            if partial_len == width {
+                let s = core::str::from_utf8(&partial[..width])
+                    .map_err(|_| dec::Error::require_utf8(name))?;
+                escape(writer, s)?;
+                partial_len = 0;
+            }
+
+            n
+        } else {
+            match core::str::from_utf8(buf) {
+                Ok(s) => escape(writer, s)?,
+                Err(err) if err.error_len().is_none() => {
+                    let (s, rest) = buf.split_at(err.valid_up_to());
+                    let s = core::str::from_utf8(s)
+                        .map_err(|_| dec::Error::require_utf8(name))?;
+                    escape(writer, s)?;
+                    partial[..rest.len()].copy_from_slice(rest);
+                    partial_len = rest.len();
+                },
+                Err(_) => return Err(dec::Error::require_utf8(name).into())
+            }
+
+            buf.len()
+        };
+
+        reader.advance(n);
+        len -= n;
+    }
+
+    if partial_len != 0 {
+        return Err(dec::Error::require_utf8(name).into());
+    }
+
+    Ok(())
+}
+
+/// Diagnostic notation parse error
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ParseError<E> {
+    Encode(enc::Error<E>),
+    /// Parsed item can not be decoded as `Value`
+    Decode(dec::Error<Never>),
+    /// Invalid input at the byte offset
+    Syntax {
+        name: StaticStr,
+        offset: usize
+    }
+}
+
+impl<E> From<enc::Error<E>> for ParseError<E> {
+    #[cold]
+    fn from(err: enc::Error<E>) -> ParseError<E> {
+        ParseError::Encode(err)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for ParseError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for ParseError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            ParseError::Encode(err) => Some(err),
+            ParseError::Decode(err) => Some(err),
+            ParseError::Syntax { .. } => None
+        }
+    }
+}
+
+/// Parse the diagnostic notation of an item, and write its CBOR encoding to writer.
+///
+/// In addition to rfc8949 section 8, this accepts the extensions of rfc8610 appendix G:
+/// `h''` and `b64''` byte strings, `''` text as byte string, `<< >>` embedded CBOR,
+/// encoding indicators such as `1_0` or `[_1 ]`, and `/ comments /`.
+///
+/// Integers out of range of major type 0 and 1 are written as bignum (tag 2 and 3).
+/// Floats without encoding indicator use the shortest form that preserves the value,
+/// and literals that overflow to infinity are rejected, use `Infinity` instead.
+///
+/// This does not allocate.
+pub fn parse<W: enc::Write>(input: &str, writer: &mut W) -> Result<(), ParseError<W::Error>> {
+    let mut parser = Parser { input: input.as_bytes(), pos: 0, depth: 256 };
+    let mut output = Output { writer: Some(writer), len: 0 };
+
+    parser.space()?;
+    parser.item(&mut output)?;
+    parser.space()?;
+
+    if parser.pos != parser.input.len() {
+        return Err(parser.error(&"diag::trailing"));
+    }
+
+    Ok(())
+}
+
+/// Parse the diagnostic notation of an item as `Value`.
+#[cfg(feature = "use_alloc")]
+pub fn parse_value(input: &str) -> Result<Value, ParseError<Never>> {
+    use crate::alloc::vec::Vec;
+    use crate::core::dec::Decode;
+    use crate::core::utils::{ VecWriter, SliceReader };
+
+    let mut writer = VecWriter(Vec::new());
+    parse(input, &mut writer)?;
+    Value::decode(&mut SliceReader::new(&writer.0)).map_err(ParseError::Decode)
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    depth: usize
+}
+
+/// Parser output
+///
+/// Definite length items are parsed twice,
+/// first without writer to count the length, then to write the content.
+struct Output<'a, W> {
+    writer: Option<&'a mut W>,
+    len: usize
+}
+
+impl<W: enc::Write> enc::Write for Output<'_, W> {
+    type Error = W::Error;
+
+    #[inline]
+    fn push(&mut self, input: &[u8]) -> Result<(), Self::Error> {
+        self.len += input.len();
+        match self.writer.as_mut() {
+            Some(writer) => writer.push(input),
+            None => Ok(())
+        }
+    }
+}
+
+impl<W: enc::Write> Output<'_, W> {
+    #[inline]
+    fn put(&mut self, input: &[u8]) -> Result<(), ParseError<W::Error>> {
+        enc::Write::push(self, input).map_err(|err| ParseError::Encode(enc::Error::Write(err)))
+    }
+}
+
+/// Float literal
+enum Float<'a> {
+    Number(&'a str),
+    NaN,
+    Infinity(bool)
+}
+
+impl<'a> Parser<'a> {
+    #[cold]
+    fn error<E>(&self, name: StaticStr) -> ParseError<E> {
+        ParseError::Syntax { name, offset: self.pos }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    #[inline]
+    fn starts_with(&self, s: &[u8]) -> bool {
+        self.input[self.pos..].starts_with(s)
+    }
+
+    #[inline]
+    fn eat(&mut self, s: &[u8]) -> bool {
+        if self.starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect<E>(&mut self, s: &[u8]) -> Result<(), ParseError<E>> {
+        if self.eat(s) {
+            Ok(())
+        } else {
+            Err(self.error(&"diag::expect"))
+        }
+    }
+
+    /// Skip whitespace and comments.
+    fn space<E>(&mut self) -> Result<(), ParseError<E>> {
+        while let Some(b) = self.peek() {
+            match b {
+                b' ' | b'\t' | b'\r' | b'\n' => self.pos += 1,
+                b'/' => {
+                    let start = self.pos;
+                    self.pos += 1;
+                    match self.input[self.pos..].iter().position(|&b| b == b'/') {
+                        Some(n) => self.pos += n + 1,
+                        None => {
+                            self.pos = start;
+                            return Err(self.error(&"diag::comment"));
+                        }
+                    }
+                },
+                b'#' => match self.input[self.pos..].iter().position(|&b| b == b'\n') {
+                    Some(n) => self.pos += n + 1,
+                    None => self.pos = self.input.len()
+                },
+                _ => break
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parse encoding indicator `_0` to `_3`.
+    fn indicator<E>(&mut self) -> Result<Option<u8>, ParseError<E>> {
+        if self.peek() != Some(b'_') {
+            return Ok(None);
+        }
+
+        match self.input.get(self.pos + 1) {
+            Some(&b) if (b'0'..=b'3').contains(&b) => {
+                self.pos += 2;
+                Ok(Some(b - b'0'))
+            },
+            _ => Err(self.error(&"diag::indicator"))
+        }
+    }
+
+    /// Parse indefinite length marker `_` or encoding indicator after `[` or `{`.
+    fn container_indicator<E>(&mut self) -> Result<Result<Option<u8>, ()>, ParseError<E>> {
+        if self.peek() == Some(b'_') {
+            match self.input.get(self.pos + 1) {
+                Some(b) if b.is_ascii_digit() => self.indicator().map(Ok),
+                _ => {
+                    self.pos += 1;
+                    Ok(Err(()))
+                }
+            }
+        } else {
+            Ok(Ok(None))
+        }
+    }
+
+    fn head<W: enc::Write>(&self, output: &mut Output<'_, W>, major: u8, arg: u64, ind: Option<u8>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        let major = major << 5;
+        let ind = match ind {
+            Some(ind) => ind,
+            None if arg < 0x18 => return output.put(&[major | arg as u8]),
+            None if arg <= 0xff => 0,
+            None if arg <= 0xffff => 1,
+            None if arg <= 0xffff_ffff => 2,
+            None => 3
+        };
+
+        let n = 1 << ind;
+        if n < 8 && arg >> (n * 8) != 0 {
+            return Err(self.error(&"diag::indicator"));
+        }
+
+        let mut buf = [0; 9];
+        buf[0] = major | (0x18 + ind);
+        buf[1..][..n].copy_from_slice(&arg.to_be_bytes()[8 - n..]);
+        output.put(&buf[..n + 1])
+    }
+
+    /// Parse a definite length item.
+    ///
+    /// `body` parses the content and returns the argument of head,
+    /// it is called again to write the content after the head.
+    fn definite<W, F>(&mut self, output: &mut Output<'_, W>, major: u8, body: F)
+        -> Result<(), ParseError<W::Error>>
+    where
+        W: enc::Write,
+        F: Fn(&mut Self, &mut Output<'_, W>) -> Result<(u64, Option<u8>), ParseError<W::Error>>
+    {
+        let start = self.pos;
+        let mut counter = Output { writer: None, len: 0 };
+        let (arg, ind) = body(self, &mut counter)?;
+        let end = self.pos;
+
+        self.pos = start;
+        self.head(output, major, arg, ind)?;
+
+        if output.writer.is_some() {
+            body(self, output)?;
+        } else {
+            output.len += counter.len;
+        }
+
+        self.pos = end;
+        Ok(())
+    }
+
+    fn item<W: enc::Write>(&mut self, output: &mut Output<'_, W>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        if let Some(depth) = self.depth.checked_sub(1) {
+            self.depth = depth;
+        } else {
+            return Err(self.error(&"diag::depth"));
+        }
+        let mut parser = ScopeGuard(self, |parser| parser.depth += 1);
+        let parser = &mut *parser;
+
+        if parser.string_major().is_some() {
+            return parser.string(output);
+        }
+
+        match parser.peek() {
+            Some(b'[') => parser.array(output),
+            Some(b'{') => parser.map(output),
+            Some(b'(') => parser.chunks(output),
+            Some(b'-') | Some(b'0' ..= b'9') => parser.number(output),
+            Some(b'a' ..= b'z') | Some(b'A' ..= b'Z') => parser.keyword(output),
+            _ => Err(parser.error(&"diag::item"))
+        }
+    }
+
+    /// Parse items separated by commas until `close`, and return the count.
+    fn list<W: enc::Write>(&mut self, output: &mut Output<'_, W>, close: &[u8], pair: bool)
+        -> Result<u64, ParseError<W::Error>>
+    {
+        let mut count = 0;
+
+        self.space()?;
+        if self.eat(close) {
+            return Ok(count);
+        }
+
+        loop {
+            self.item(output)?;
+            if pair {
+                self.space()?;
+                self.expect(b":")?;
+                self.space()?;
+                self.item(output)?;
+            }
+            count += 1;
+
+            self.space()?;
+            if self.eat(close) {
+                return Ok(count);
+            }
+            self.expect(b",")?;
+            self.space()?;
+        }
+    }
+
+    fn array<W: enc::Write>(&mut self, output: &mut Output<'_, W>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        self.container(output, major::ARRAY, b"[", b"]", false)
+    }
+
+    fn map<W: enc::Write>(&mut self, output: &mut Output<'_, W>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        self.container(output, major::MAP, b"{", b"}", true)
+    }
+
+    fn container<W: enc::Write>(
+        &mut self,
+        output: &mut Output<'_, W>,
+        major: u8,
+        open: &[u8],
+        close: &[u8],
+        pair: bool
+    ) -> Result<(), ParseError<W::Error>> {
+        let start = self.pos;
+        self.expect(open)?;
+
+        if let Err(()) = self.container_indicator()? {
+            output.put(&[(major << 5) | marker::START])?;
+            self.list(output, close, pair)?;
+            output.put(&[marker::BREAK])?;
+            return Ok(());
+        }
+
+        self.pos = start;
+        self.definite(output, major, |parser, output| {
+            parser.expect(open)?;
+            let ind = parser.container_indicator()?.unwrap_or(None);
+            let count = parser.list(output, close, pair)?;
+            Ok((count, ind))
+        })
+    }
+
+    /// Parse indefinite length string `(_ chunk, ...)`.
+    fn chunks<W: enc::Write>(&mut self, output: &mut Output<'_, W>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        let name = &"diag::chunk";
+
+        self.expect(b"(")?;
+        self.expect(b"_")?;
+        self.space()?;
+
+        // the type of empty indefinite length string is unknown
+        let major = match self.string_major() {
+            Some(major) => major,
+            None => return Err(self.error(name))
+        };
+        output.put(&[(major << 5) | marker::START])?;
+
+        loop {
+            if self.string_major() != Some(major) {
+                return Err(self.error(name));
+            }
+            self.string(output)?;
+
+            self.space()?;
+            if self.eat(b")") {
+                break
+            }
+            self.expect(b",")?;
+            self.space()?;
+        }
+
+        output.put(&[marker::BREAK])?;
+        Ok(())
+    }
+
+    fn string_major(&self) -> Option<u8> {
+        match self.peek()? {
+            b'"' => Some(major::STRING),
+            b'\'' => Some(major::BYTES),
+            b'h' if self.starts_with(b"h'") => Some(major::BYTES),
+            b'b' if self.starts_with(b"b64'") => Some(major::BYTES),
+            b'<' if self.starts_with(b"<<") => Some(major::BYTES),
+            _ => None
+        }
+    }
+
+    fn string<W: enc::Write>(&mut self, output: &mut Output<'_, W>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        let major = match self.string_major() {
+            Some(major) => major,
+            None => return Err(self.error(&"diag::string"))
+        };
+
+        self.definite(output, major, |parser, output| {
+            let start = output.len;
+
+            if parser.eat(b"h'") {
+                parser.hex(output)?;
+            } else if parser.eat(b"b64'") {
+                parser.base64(output)?;
+            } else if parser.eat(b"<<") {
+                parser.list(output, b">>", false)?;
+            } else {
+                parser.quoted(output)?;
+            }
+
+            let len = output.len - start;
+            let ind = parser.indicator()?;
+            Ok((len as u64, ind))
+        })
+    }
+
+    /// Parse quoted text, with JSON escapes.
+    fn quoted<W: enc::Write>(&mut self, output: &mut Output<'_, W>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        let name = &"diag::escape";
+
+        let quote = self.input[self.pos];
+        self.pos += 1;
+
+        loop {
+            let n = self.input[self.pos..]
+                .iter()
+                .position(|&b| b == quote || b == b'\\')
+                .ok_or_else(|| self.error(&"diag::string"))?;
+            output.put(&self.input[self.pos..][..n])?;
+            self.pos += n;
+
+            if self.eat(&[quote]) {
+                break
+            }
+
+            let start = self.pos;
+            self.pos += 1;
+            let c = match self.peek() {
+                Some(b'"') => '"',
+                Some(b'\'') => '\'',
+                Some(b'\\') => '\\',
+                Some(b'/') => '/',
+                Some(b'b') => '\u{8}',
+                Some(b'f') => '\u{c}',
+                Some(b'n') => '\n',
+                Some(b'r') => '\r',
+                Some(b't') => '\t',
+                Some(b'u') => {
+                    self.pos += 1;
+                    let hi = self.hex4()?;
+                    let c = if (0xd800..0xdc00).contains(&hi) && self.eat(b"\\u") {
+                        let lo = self.hex4()?;
+                        let c = 0x10000 + ((u32::from(hi) - 0xd800) << 10);
+                        c.checked_add(u32::from(lo).wrapping_sub(0xdc00))
+                            .filter(|_| (0xdc00..0xe000).contains(&lo))
+                            .and_then(char::from_u32)
+                    } else {
+                        char::from_u32(hi.into())
+                    };
+
+                    match c {
+                        Some(c) => {
+                            let mut buf = [0; 4];
+                            output.put(c.encode_utf8(&mut buf).as_bytes())?;
+                            continue
+                        },
+                        None => {
+                            self.pos = start;
+                            return Err(self.error(name));
+                        }
+                    }
+                },
+                _ => {
+                    self.pos = start;
+                    return Err(self.error(name));
+                }
+            };
+
+            self.pos += 1;
+            let mut buf = [0; 4];
+            output.put(c.encode_utf8(&mut buf).as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn hex4<E>(&mut self) -> Result<u16, ParseError<E>> {
+        let s = self.input.get(self.pos..self.pos + 4)
+            .filter(|s| s.iter().all(u8::is_ascii_hexdigit))
+            .ok_or_else(|| self.error(&"diag::escape"))?;
+        let mut n = 0;
+        for &b in s {
+            n = (n << 4) | u16::from(hexdigit(b));
+        }
+        self.pos += 4;
+        Ok(n)
+    }
+
+    /// Parse hex bytes until `'`, whitespace and comments are allowed.
+    fn hex<W: enc::Write>(&mut self, output: &mut Output<'_, W>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        let name = &"diag::hex";
+        let mut high = None;
+
+        loop {
+            self.space()?;
+            match self.peek() {
+                Some(b'\'') => break,
+                Some(b) if b.is_ascii_hexdigit() => {
+                    let b = hexdigit(b);
+                    match high.take() {
+                        Some(high) => output.put(&[(high << 4) | b])?,
+                        None => high = Some(b)
+                    }
+                },
+                _ => return Err(self.error(name))
+            }
+            self.pos += 1;
+        }
+
+        if high.is_some() {
+            return Err(self.error(name));
+        }
+
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Parse base64 bytes until `'`, both standard and url-safe alphabets are allowed,
+    /// padding is optional.
+    fn base64<W: enc::Write>(&mut self, output: &mut Output<'_, W>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        let name = &"diag::base64";
+        let mut acc = 0u32;
+        let mut bits = 0;
+        let mut padding = 0;
+
+        loop {
+            // `/` is in the alphabet, so comments are not allowed
+            while self.peek().filter(u8::is_ascii_whitespace).is_some() {
+                self.pos += 1;
+            }
+
+            let b = match self.peek() {
+                Some(b'\'') => break,
+                Some(b'=') if bits != 0 => {
+                    padding += 1;
+                    self.pos += 1;
+                    continue
+                },
+                Some(_) if padding != 0 => return Err(self.error(name)),
+                Some(b @ b'A' ..= b'Z') => b - b'A',
+                Some(b @ b'a' ..= b'z') => b - b'a' + 26,
+                Some(b @ b'0' ..= b'9') => b - b'0' + 52,
+                Some(b'+') | Some(b'-') => 62,
+                Some(b'/') | Some(b'_') => 63,
+                _ => return Err(self.error(name))
+            };
+            self.pos += 1;
+
+            acc = (acc << 6) | u32::from(b);
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                output.put(&[(acc >> bits) as u8])?;
+                acc &= (1 << bits) - 1;
+            }
+        }
+
+        // the unused bits must be zero
+        if bits >= 6 || acc != 0 || (padding != 0 && (bits + padding * 6) % 8 != 0) {
+            return Err(self.error(name));
+        }
+
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn number<W: enc::Write>(&mut self, output: &mut Output<'_, W>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        use core::convert::TryFrom;
+
+        let name = &"diag::number";
+        let start = self.pos;
+        let negative = self.eat(b"-");
+
+        if self.eat(b"Infinity") {
+            return self.float(output, Float::Infinity(negative));
+        }
+
+        let radix = if self.eat(b"0x") {
+            16
+        } else if self.eat(b"0o") {
+            8
+        } else if self.eat(b"0b") {
+            2
+        } else {
+            10
+        };
+
+        let digits = self.pos;
+        let mut value: Option<u128> = Some(0);
+        while let Some(d) = self.peek().and_then(|b| char::from(b).to_digit(radix)) {
+            value = value
+                .and_then(|v| v.checked_mul(radix.into()))
+                .and_then(|v| v.checked_add(d.into()));
+            self.pos += 1;
+        }
+
+        if self.pos == digits {
+            return Err(self.error(name));
+        }
+
+        if radix == 10 && matches!(self.peek(), Some(b'.') | Some(b'e') | Some(b'E')) {
+            if self.eat(b".") {
+                let frac = self.pos;
+                while self.peek().filter(u8::is_ascii_digit).is_some() {
+                    self.pos += 1;
+                }
+                if self.pos == frac {
+                    return Err(self.error(name));
+                }
+            }
+            if self.eat(b"e") || self.eat(b"E") {
+                let _ = self.eat(b"+") || self.eat(b"-");
+                let exp = self.pos;
+                while self.peek().filter(u8::is_ascii_digit).is_some() {
+                    self.pos += 1;
+                }
+                if self.pos == exp {
+                    return Err(self.error(name));
+                }
+            }
+
+            let s = core::str::from_utf8(&self.input[start..self.pos])
+                .map_err(|_| self.error(name))?;
+            return self.float(output, Float::Number(s));
+        }
+
+        let (major, arg) = match (negative, value) {
+            (_, None) => {
+                self.pos = start;
+                return Err(self.error(name));
+            },
+            (_, Some(0)) => (major::UNSIGNED, 0),
+            (false, Some(v)) => (major::UNSIGNED, v),
+            (true, Some(v)) => (major::NEGATIVE, v - 1)
+        };
+        let ind = self.indicator()?;
+
+        let arg = match u64::try_from(arg) {
+            Ok(arg) => arg,
+            // bignum, see rfc8949 section 3.4.3
+            Err(_) if ind.is_none() && self.peek() != Some(b'(') => {
+                let tag = if major == major::NEGATIVE { 3 } else { 2 };
+                let buf = arg.to_be_bytes();
+                let zeros = arg.leading_zeros() as usize / 8;
+                types::Tag(tag, types::Bytes(&buf[zeros..])).encode(output)?;
+                return Ok(());
+            },
+            Err(_) => {
+                self.pos = start;
+                return Err(self.error(name));
+            }
+        };
+
+        if self.peek() == Some(b'(') {
+            if negative {
+                return Err(self.error(name));
+            }
+
+            self.head(output, major::TAG, arg, ind)?;
+            self.pos += 1;
+            self.space()?;
+            self.item(output)?;
+            self.space()?;
+            self.expect(b")")?;
+            return Ok(());
+        }
+
+        self.head(output, major, arg, ind)
+    }
+
+    fn float<W: enc::Write>(&mut self, output: &mut Output<'_, W>, value: Float<'_>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        use crate::util::{ f32_is_f16, f64_is_f32, f32_to_f16 };
+
+        let name = &"diag::float";
+        let start = self.pos;
+        let ind = self.indicator()?;
+
+        let f64_value = match value {
+            Float::Number(s) => match s.parse::<f64>() {
+                Ok(v) if v.is_finite() => v,
+                _ => {
+                    self.pos = start;
+                    return Err(self.error(name));
+                }
+            },
+            Float::NaN => f64::NAN,
+            Float::Infinity(false) => f64::INFINITY,
+            Float::Infinity(true) => f64::NEG_INFINITY
+        };
+        let is_f16 = f64_is_f32(f64_value) && f32_is_f16(f64_value as f32);
+
+        match ind {
+            Some(1) if is_f16 => types::F16(f32_to_f16(f64_value as f32)).encode(output)?,
+            None if is_f16 => types::F16(f32_to_f16(f64_value as f32)).encode(output)?,
+            Some(2) => {
+                // the literal is rounded to the nearest single precision float
+                let v = match value {
+                    Float::Number(s) => match s.parse::<f32>() {
+                        Ok(v) if v.is_finite() => v,
+                        _ => {
+                            self.pos = start;
+                            return Err(self.error(name));
+                        }
+                    },
+                    _ => f64_value as f32
+                };
+                v.encode(output)?;
+            },
+            None if f64_is_f32(f64_value) => (f64_value as f32).encode(output)?,
+            None | Some(3) => f64_value.encode(output)?,
+            _ => {
+                self.pos = start;
+                return Err(self.error(name));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn keyword<W: enc::Write>(&mut self, output: &mut Output<'_, W>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        let name = &"diag::keyword";
+        let start = self.pos;
+        let n = self.input[self.pos..]
+            .iter()
+            .position(|b| !b.is_ascii_alphanumeric())
+            .unwrap_or(self.input.len() - self.pos);
+        self.pos += n;
+
+        let byte = match &self.input[start..self.pos] {
+            b"false" => marker::FALSE,
+            b"true" => marker::TRUE,
+            b"null" => marker::NULL,
+            b"undefined" => marker::UNDEFINED,
+            b"NaN" => return self.float(output, Float::NaN),
+            b"Infinity" => return self.float(output, Float::Infinity(false)),
+            b"simple" => return self.simple(output),
+            _ => {
+                self.pos = start;
+                return Err(self.error(name));
+            }
+        };
+
+        output.put(&[byte])?;
+        Ok(())
+    }
+
+    /// Parse `(n)` of `simple(n)`.
+    fn simple<W: enc::Write>(&mut self, output: &mut Output<'_, W>)
+        -> Result<(), ParseError<W::Error>>
+    {
+        self.expect(b"(")?;
+        self.space()?;
+
+        let start = self.pos;
+        let n = self.input[self.pos..]
+            .iter()
+            .position(|b| !b.is_ascii_digit())
+            .unwrap_or(self.input.len() - self.pos);
+        self.pos += n;
+
+        let n = core::str::from_utf8(&self.input[start..self.pos]).ok()
+            .and_then(|n| n.parse::<u8>().ok())
+            // simple values 24 to 31 are reserved and not well-formed
+            .filter(|n| !(24..32).contains(n));
+        let n = match n {
+            Some(n) => n,
+            None => {
+                self.pos = start;
+                return Err(self.error(&"diag::simple"));
+            }
+        };
+
+        self.space()?;
+        self.expect(b")")?;
+        types::Simple(n).encode(output)?;
+        Ok(())
+    }
+}
+
+#[inline]
+fn hexdigit(b: u8) -> u8 {
+    match b {
+        b'0' ..= b'9' => b - b'0',
+        b'a' ..= b'f' => b - b'a' + 10,
+        _ => b - b'A' + 10
+    }
+}
+
+#[test]
+#[cfg(feature = "use_std")]
+fn test_diag() {
+    use crate::core::dec::Decode;
+    use crate::core::utils::{ SliceReader, IoReader };
+
+    macro_rules! test {
+        ( $( $input:expr , $expected:expr );* $( ; )? ) => {
+            $(
+                {
+                    let buf = data_encoding::HEXLOWER.decode($input.as_bytes()).unwrap();
+
+                    let mut output = String::new();
+                    write(&mut SliceReader::new(&buf), &mut output, false).unwrap();
+                    assert_eq!(output, $expected, "{:?}", $input);
+
+                    // read one byte at a time
+                    let mut output = String::new();
+                    let reader = std::io::BufReader::with_capacity(1, buf.as_slice());
+                    write(&mut IoReader::new(reader), &mut output, false).unwrap();
+                    assert_eq!(output, $expected, "{:?}", $input);
+
+                    // and back
+                    let mut writer = crate::core::utils::BufWriter::new(Vec::new());
+                    parse($expected, &mut writer).unwrap();
+                    assert_eq!(writer.buffer(), buf.as_slice(), "{:?}", $expected);
+                }
+            )*
+        }
+    }
+
+    // rfc8949 appendix A
+    test!{
+        "00", "0";
+        "17", "23";
+        "1818", "24";
+        "1903e8", "1000";
+        "1bffffffffffffffff", "18446744073709551615";
+        "c249010000000000000000", "2(h'010000000000000000')";
+        "3bffffffffffffffff", "-18446744073709551616";
+        "20", "-1";
+        "3903e7", "-1000";
+        "f90000", "0.0_1";
+        "f98000", "-0.0_1";
+        "f93c00", "1.0_1";
+        "fb3ff199999999999a", "1.1_3";
+        "f93e00", "1.5_1";
+        "f97bff", "65504.0_1";
+        "fa47c35000", "100000.0_2";
+        "fa7f7fffff", "3.4028235e38_2";
+        "fb7e37e43c8800759c", "1e300_3";
+        "f90001", "5.960464477539063e-8_1";
+        "f90400", "6.103515625e-5_1";
+        "f9c400", "-4.0_1";
+        "fbc010666666666666", "-4.1_3";
+        "f97c00", "Infinity_1";
+        "f97e00", "NaN_1";
+        "f9fc00", "-Infinity_1";
+        "fa7f800000", "Infinity_2";
+        "fb7ff8000000000000", "NaN_3";
+        "f4", "false";
+        "f5", "true";
+        "f6", "null";
+        "f7", "undefined";
+        "f0", "simple(16)";
+        "f8ff", "simple(255)";
+        "c074323031332d30332d32315432303a30343a30305a", "0(\"2013-03-21T20:04:00Z\")";
+        "c11a514b67b0", "1(1363896240)";
+        "d74401020304", "23(h'01020304')";
+        "d818456449455446", "24(h'6449455446')";
+        "40", "h''";
+        "60", "\"\"";
+        "6161", "\"a\"";
+        "62225c", "\"\\\"\\\\\"";
+        "62c3bc", "\"\u{00fc}\"";
+        "63e6b0b4", "\"\u{6c34}\"";
+        "64f0908591", "\"\u{10151}\"";
+        "80", "[]";
+        "83010203", "[1, 2, 3]";
+        "8301820203820405", "[1, [2, 3], [4, 5]]";
+        "a0", "{}";
+        "a201020304", "{1: 2, 3: 4}";
+        "a26161016162820203", "{\"a\": 1, \"b\": [2, 3]}";
+        "5f42010243030405ff", "(_ h'0102', h'030405')";
+        "7f657374726561646d696e67ff", "(_ \"strea\", \"ming\")";
+        "9fff", "[_ ]";
+        "9f018202039f0405ffff", "[_ 1, [2, 3], [_ 4, 5]]";
+        "83018202039f0405ff", "[1, [2, 3], [_ 4, 5]]";
+        "bf61610161629f0203ffff", "{_ \"a\": 1, \"b\": [_ 2, 3]}";
+        // encoding indicators
+        "1801", "1_0";
+        "390001", "-2_1";
+        "7a0000000161", "\"a\"_2";
+        "980101", "[_0 1]";
+        "9800", "[_0 ]";
+        "d9000101", "1_1(1)";
+    }
+
+    // errors
+    let mut output = String::new();
+    assert!(write(&mut SliceReader::new(&[0x82, 0x01]), &mut output, false).is_err());
+    assert!(write(&mut SliceReader::new(&[0x62, 0xff, 0xff]), &mut output, false).is_err());
+    assert!(write(&mut SliceReader::new(&[0x5f, 0x61, 0x61, 0xff]), &mut output, false).is_err());
+
+    // pretty
+    let buf = data_encoding::HEXLOWER.decode(b"a26161016162829f0203ff80").unwrap();
+    let value = RawValue::decode(&mut SliceReader::new(&buf)).unwrap();
+    assert_eq!(
+        format!("{:#}", Diag(&value)),
+        "{\n  \"a\": 1,\n  \"b\": [\n    [_\n      2,\n      3\n    ],\n    []\n  ]\n}"
+    );
+    assert_eq!(format!("{}", Diag(&value)), "{\"a\": 1, \"b\": [[_ 2, 3], []]}");
+
+    let value = Value::decode(&mut SliceReader::new(&buf)).unwrap();
+    assert_eq!(
+        format!("{:#}", Diag(&value)),
+        "{\n  \"a\": 1,\n  \"b\": [\n    [\n      2,\n      3\n    ],\n    []\n  ]\n}"
+    );
+
+    let value = Value::Array(vec![
+        Value::Integer(-18446744073709551617),
+        Value::Float(1.5),
+        Value::Float(f64::NEG_INFINITY),
+        Value::Bytes(vec![0xff]),
+        Value::Text("\u{0}\n".into()),
+        Value::Tag(32, Box::new(Value::Null)),
+        Value::Map(vec![(Value::Bool(true), Value::Bool(false))])
+    ]);
+    assert_eq!(
+        format!("{}", Diag(&value)),
+        "[-18446744073709551617, 1.5, -Infinity, h'ff', \"\\u0000\\n\", 32(null), {true: false}]"
+    );
+}
+
+#[test]
+#[cfg(feature = "use_std")]
+fn test_parse() {
+    use crate::core::utils::BufWriter;
+
+    macro_rules! test {
+        ( $( $input:expr , $expected:expr );* $( ; )? ) => {
+            $(
+                {
+                    let mut writer = BufWriter::new(Vec::new());
+                    parse($input, &mut writer).unwrap();
+                    let output = data_encoding::HEXLOWER.encode(writer.buffer());
+                    assert_eq!(output, $expected, "{:?}", $input);
+                }
+            )*
+        }
+    }
+
+    test!{
+        // preferred floats
+        "1.0", "f93c00";
+        "100000.0", "fa47c35000";
+        "1.1", "fb3ff199999999999a";
+        "-Infinity", "f9fc00";
+        "NaN", "f97e00";
+        "1e2", "f95640";
+        "1.1_2", "fa3f8ccccd";
+        "1.0_3", "fb3ff0000000000000";
+        // other radixes
+        "0x10", "10";
+        "0b101", "05";
+        "0o17", "0f";
+        "-0", "00";
+        "-0x10", "2f";
+        // bignums
+        "18446744073709551616", "c249010000000000000000";
+        "-18446744073709551617", "c349010000000000000000";
+        "0xffffffffffffffffffffffffffffffff", "c250ffffffffffffffffffffffffffffffff";
+        // byte strings
+        "'hello'", "4568656c6c6f";
+        "h'01 02 / three / 03'", "43010203";
+        "b64'aGVsbG8'", "4568656c6c6f";
+        "b64'aGVsbG8='", "4568656c6c6f";
+        "b64'-_8'", "42fbff";
+        "<<1, \"a\">>", "43016161";
+        "<< >>", "40";
+        "<<[<<1>>]>>", "43814101";
+        // escapes
+        "\"\\u00fc\\/\\ud83d\\ude00\"", "67c3bc2ff09f9880";
+        "'\\'\"'", "422722";
+        // layout
+        " [ 1 ,2,\n\t3 ] # comment", "83010203";
+        "{_ 1: [_ ], 2: {}}", "bf019fff02a0ff";
+        "{_1 1: 2}", "b900010102";
+        "0_3", "1b0000000000000000";
+        "'a'_0", "580161";
+        "1(2(3))", "c1c203";
+        "55799( 1 )", "d9d9f701";
+        "simple( 0 )", "e0";
+        "simple(32)", "f820";
+    }
+
+    // errors
+    let cases: &[(&str, usize)] = &[
+        ("", 0),
+        ("[1, 2", 5),
+        ("[1 2]", 3),
+        ("[1,]", 3),
+        ("1 2", 2),
+        ("0x100000000000000000000000000000000", 0),
+        ("18446744073709551616_3", 0),
+        ("18446744073709551616(1)", 0),
+        ("1e400", 5),
+        ("-1e400", 6),
+        ("1e39_2", 4),
+        ("-1(2)", 2),
+        ("256_0", 5),
+        ("1_4", 1),
+        ("0.1_1", 3),
+        ("1.0_0", 3),
+        ("h'123'", 5),
+        ("h'0g'", 3),
+        ("b64'a'", 5),
+        ("b64'aGVsbG9='", 12),
+        ("\"\\x\"", 1),
+        ("\"abc", 1),
+        ("(_ )", 3),
+        ("(_ h'', \"\")", 8),
+        ("simple(24)", 7),
+        ("simple(256)", 7),
+        ("foo", 0),
+        ("/ comment", 0),
+    ];
+
+    for &(input, offset) in cases {
+        let mut writer = BufWriter::new(Vec::new());
+        match parse(input, &mut writer) {
+            Err(ParseError::Syntax { offset: found, .. }) => assert_eq!(found, offset, "{:?}", input),
+            result => panic!("{:?}: {:?}", input, result)
+        }
+    }
+
+    // nested too deep
+    let input = "[".repeat(300);
+    let mut writer = BufWriter::new(Vec::new());
+    assert!(parse(&input, &mut writer).is_err());
+
+    // value
+    let value = parse_value("{\"a\": [1, -2.5, h'ff', <<true>>], 1(null): undefined}").unwrap();
+    assert_eq!(value, Value::Map(vec![
+        (Value::Text("a".into()), Value::Array(vec![
+            Value::Integer(1),
+            Value::Float(-2.5),
+            Value::Bytes(vec![0xff]),
+            Value::Bytes(vec![0xf5])
+        ])),
+        (Value::Tag(1, Box::new(Value::Null)), Value::Undefined)
+    ]));
+    let text = format!("{}", Diag(&value));
+    assert_eq!(parse_value(&text).unwrap(), value);
+}
@@ -7,6 +7,19 @@ pub use crate::core::error::EncodeError as Error;
 #[cfg(feature = "use_alloc")]
 use crate::alloc::{ boxed::Box, vec::Vec, string::String };
 
+#[cfg(feature = "use_alloc")]
+use crate::alloc::collections::{ BTreeMap, BTreeSet, BinaryHeap, VecDeque };
+
+#[cfg(feature = "use_alloc")]
+use crate::core::utils::VecWriter;
+
+mod encoder;
+
+pub use encoder::Encoder;
+
+#[cfg(feature = "derive")]
+pub use cbor4ii_derive::Encode;
+
 /// Write trait
 ///
 /// This is similar to `Write` of standard library,
@@ -250,6 +263,45 @@ impl<T: Encode> Encode for &'_ [T] {
     }
 }
 
+impl<T: Encode, const N: usize> Encode for [T; N] {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        (&self[..]).encode(writer)
+    }
+}
+
+macro_rules! encode_tuple {
+    ( $( $len:expr => ( $( $t:ident ),+ ) );* $( ; )? ) => {
+        $(
+            impl<$( $t: Encode ),+> Encode for ( $( $t, )+ ) {
+                #[inline]
+                #[allow(non_snake_case)]
+                fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+                    let ( $( $t, )+ ) = self;
+                    types::Array::bounded($len, writer)?;
+                    $( $t.encode(writer)?; )+
+                    Ok(())
+                }
+            }
+        )*
+    }
+}
+
+encode_tuple!(
+    1 => (A);
+    2 => (A, B);
+    3 => (A, B, C);
+    4 => (A, B, C, D);
+    5 => (A, B, C, D, E);
+    6 => (A, B, C, D, E, F);
+    7 => (A, B, C, D, E, F, G);
+    8 => (A, B, C, D, E, F, G, H);
+    9 => (A, B, C, D, E, F, G, H, I);
+    10 => (A, B, C, D, E, F, G, H, I, J);
+    11 => (A, B, C, D, E, F, G, H, I, J, K);
+    12 => (A, B, C, D, E, F, G, H, I, J, K, L);
+);
+
 impl<K: Encode, V: Encode> Encode for types::Map<&'_ [(K, V)]> {
     #[inline]
     fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
@@ -262,6 +314,60 @@ impl<K: Encode, V: Encode> Encode for types::Map<&'_ [(K, V)]> {
     }
 }
 
+/// Map with keys sorted bytewise by their encoded form, duplicate keys are rejected.
+///
+/// Only the top-level keys are sorted, keys and values are encoded as is.
+/// The output is deterministic only if they are encoded deterministically too,
+/// use `Canonical<&Value>` for nested items.
+#[cfg(feature = "use_alloc")]
+impl<K: Encode, V: Encode> Encode for types::Canonical<types::Map<&'_ [(K, V)]>> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        let entries = (self.0).0;
+        let mut keys = Vec::with_capacity(entries.len());
+        for (i, (k, _)) in entries.iter().enumerate() {
+            let mut buf = VecWriter(Vec::new());
+            k.encode(&mut buf).map_err(Error::cast)?;
+            keys.push((buf.0, i));
+        }
+
+        sort_keys(&mut keys)?;
+
+        types::Map::bounded(keys.len(), writer)?;
+        for (k, i) in keys.iter() {
+            writer.push(k)?;
+            entries[*i].1.encode(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write items as a CBOR sequence (rfc8742),
+/// that is the concatenation of their encodings.
+pub fn write_sequence<W, I>(writer: &mut W, items: I) -> Result<(), Error<W::Error>>
+where
+    W: Write,
+    I: IntoIterator,
+    I::Item: Encode
+{
+    for item in items {
+        item.encode(writer)?;
+    }
+    Ok(())
+}
+
+/// Sort encoded keys bytewise lexicographic, and reject duplicate keys.
+#[cfg(feature = "use_alloc")]
+pub(crate) fn sort_keys<T, E>(entries: &mut [(Vec<u8>, T)]) -> Result<(), Error<E>> {
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    if entries.windows(2).any(|w| w[0].0 == w[1].0) {
+        Err(Error::duplicate_key(&"map"))
+    } else {
+        Ok(())
+    }
+}
+
 /// Implementation of markers for types with indefinite length suppport.
 macro_rules! bound_unbound_end {
     ( $( $t:ty , $major:expr );* $( ; )? ) => {
@@ -341,6 +447,84 @@ impl Encode for types::Undefined {
     }
 }
 
+/// Encode `None` as null, and `Some` as the value.
+impl<T: Encode> Encode for Option<T> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        match self {
+            Some(value) => value.encode(writer),
+            None => types::Null.encode(writer)
+        }
+    }
+}
+
+/// Encode as a single entry map, `{"Ok": value}` or `{"Err": error}`.
+impl<T: Encode, E: Encode> Encode for Result<T, E> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        types::Map::bounded(1, writer)?;
+        match self {
+            Ok(value) => {
+                "Ok".encode(writer)?;
+                value.encode(writer)
+            },
+            Err(err) => {
+                "Err".encode(writer)?;
+                err.encode(writer)
+            }
+        }
+    }
+}
+
+/// Encode as an empty array.
+impl Encode for () {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        types::Array::bounded(0, writer)
+    }
+}
+
+/// Encode as a text string.
+impl Encode for char {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        let mut buf = [0; 4];
+        (&*self.encode_utf8(&mut buf)).encode(writer)
+    }
+}
+
+/// Encode as a map, `{"secs": u64, "nanos": u32}`.
+impl Encode for core::time::Duration {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        types::Map::bounded(2, writer)?;
+        "secs".encode(writer)?;
+        self.as_secs().encode(writer)?;
+        "nanos".encode(writer)?;
+        self.subsec_nanos().encode(writer)
+    }
+}
+
+macro_rules! encode_nonzero {
+    ( $( $t:ty ),* ) => {
+        $(
+            impl Encode for $t {
+                #[inline]
+                fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+                    self.get().encode(writer)
+                }
+            }
+        )*
+    }
+}
+
+encode_nonzero!(
+    core::num::NonZeroU8, core::num::NonZeroU16, core::num::NonZeroU32,
+    core::num::NonZeroU64, core::num::NonZeroU128,
+    core::num::NonZeroI8, core::num::NonZeroI16, core::num::NonZeroI32,
+    core::num::NonZeroI64, core::num::NonZeroI128
+);
+
 impl<T: Encode> Encode for types::Maybe<&'_ Option<T>> {
     #[inline]
     fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
@@ -389,6 +573,30 @@ impl Encode for f64 {
     }
 }
 
+impl Encode for types::Preferred<f32> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        use crate::util::{ f32_is_f16, f32_to_f16 };
+
+        if f32_is_f16(self.0) {
+            types::F16(f32_to_f16(self.0)).encode(writer)
+        } else {
+            self.0.encode(writer)
+        }
+    }
+}
+
+impl Encode for types::Preferred<f64> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        if crate::util::f64_is_f32(self.0) {
+            types::Preferred(self.0 as f32).encode(writer)
+        } else {
+            self.0.encode(writer)
+        }
+    }
+}
+
 impl Encode for types::Nothing {
     #[inline]
     fn encode<W: Write>(&self, _writer: &mut W) -> Result<(), Error<W::Error>> {
@@ -404,6 +612,50 @@ impl<T: Encode> Encode for Box<T> {
     }
 }
 
+#[cfg(feature = "use_alloc")]
+impl Encode for Box<str> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        (&**self).encode(writer)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<T: Encode> Encode for Box<[T]> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        (&**self).encode(writer)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<T: Encode> Encode for crate::alloc::rc::Rc<T> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        <T as Encode>::encode(&**self, writer)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<T: Encode> Encode for crate::alloc::sync::Arc<T> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        <T as Encode>::encode(&**self, writer)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<T> Encode for crate::alloc::borrow::Cow<'_, T>
+where
+    T: crate::alloc::borrow::ToOwned + ?Sized,
+    for<'a> &'a T: Encode
+{
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        (&**self).encode(writer)
+    }
+}
+
 #[cfg(feature = "use_alloc")]
 impl<T: Encode> Encode for Vec<T> {
     #[inline]
@@ -412,6 +664,87 @@ impl<T: Encode> Encode for Vec<T> {
     }
 }
 
+#[cfg(feature = "use_alloc")]
+fn encode_array<'a, W, T, I>(len: usize, iter: I, writer: &mut W) -> Result<(), Error<W::Error>>
+where
+    W: Write,
+    T: Encode + 'a,
+    I: Iterator<Item = &'a T>
+{
+    types::Array::bounded(len, writer)?;
+    for value in iter {
+        value.encode(writer)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "use_alloc")]
+fn encode_map<'a, W, K, V, I>(len: usize, iter: I, writer: &mut W) -> Result<(), Error<W::Error>>
+where
+    W: Write,
+    K: Encode + 'a,
+    V: Encode + 'a,
+    I: Iterator<Item = (&'a K, &'a V)>
+{
+    types::Map::bounded(len, writer)?;
+    for (k, v) in iter {
+        k.encode(writer)?;
+        v.encode(writer)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "use_alloc")]
+impl<T: Encode> Encode for VecDeque<T> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        encode_array(self.len(), self.iter(), writer)
+    }
+}
+
+/// Items are written in arbitrary order.
+#[cfg(feature = "use_alloc")]
+impl<T: Encode> Encode for BinaryHeap<T> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        encode_array(self.len(), self.iter(), writer)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<T: Encode> Encode for BTreeSet<T> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        encode_array(self.len(), self.iter(), writer)
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        encode_map(self.len(), self.iter(), writer)
+    }
+}
+
+/// Items are written in arbitrary order.
+#[cfg(feature = "use_std")]
+impl<T: Encode, S> Encode for std::collections::HashSet<T, S> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        encode_array(self.len(), self.iter(), writer)
+    }
+}
+
+/// Entries are written in arbitrary order.
+#[cfg(feature = "use_std")]
+impl<K: Encode, V: Encode, S> Encode for std::collections::HashMap<K, V, S> {
+    #[inline]
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), Error<W::Error>> {
+        encode_map(self.len(), self.iter(), writer)
+    }
+}
+
 // from https://www.rfc-editor.org/rfc/rfc8949.html#name-examples-of-encoded-cbor-da
 #[test]
 #[cfg(feature = "use_std")]
@@ -503,6 +836,28 @@ fn test_encoded() -> anyhow::Result<()> {
         f64::NAN, "0xfb7ff8000000000000";
         f64::NEG_INFINITY, "0xfbfff0000000000000";
 
+        types::Preferred(0.0f64), "0xf90000";
+        types::Preferred(-0.0f64), "0xf98000";
+        types::Preferred(1.0f64), "0xf93c00";
+        types::Preferred(1.1f64), "0xfb3ff199999999999a";
+        types::Preferred(1.5f64), "0xf93e00";
+        types::Preferred(65504.0f64), "0xf97bff";
+        types::Preferred(100000.0f64), "0xfa47c35000";
+        types::Preferred(3.4028234663852886e+38f64), "0xfa7f7fffff";
+        types::Preferred(1.0e+300f64), "0xfb7e37e43c8800759c";
+        types::Preferred(5.960464477539063e-8f64), "0xf90001";
+        types::Preferred(0.00006103515625f64), "0xf90400";
+        types::Preferred(-4.0f64), "0xf9c400";
+        types::Preferred(-4.1f64), "0xfbc010666666666666";
+        types::Preferred(f64::INFINITY), "0xf97c00";
+        types::Preferred(f64::NAN), "0xf97e00";
+        types::Preferred(f64::NEG_INFINITY), "0xf9fc00";
+        types::Preferred(1.5f32), "0xf93e00";
+        types::Preferred(100000.0f32), "0xfa47c35000";
+        types::Preferred(f32::NAN), "0xf97e00";
+        types::Preferred(f64::from_bits(0x7ff8_0000_0000_0001)), "0xfb7ff8000000000001";
+        types::Preferred(f32::from_bits(0x7fc0_0001)), "0xfa7fc00001";
+
         false, "0xf4";
         true, "0xf5";
         types::Null, "0xf6";
@@ -721,3 +1076,46 @@ fn test_encoded() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(feature = "use_std")]
+fn test_canonical() {
+    use crate::core::Value;
+    use crate::core::utils::BufWriter;
+
+    // shorter keys sort first, because the length is part of the head
+    let mut buf = BufWriter::new(Vec::new());
+    types::Canonical(types::Map(&[("aa", 1u8), ("b", 2u8), (".", 3u8)][..])).encode(&mut buf).unwrap();
+    assert_eq!(buf.buffer(), [0xa3, 0x61, b'.', 0x03, 0x61, b'b', 0x02, 0x62, b'a', b'a', 0x01]);
+
+    let mut buf = BufWriter::new(Vec::new());
+    let ret = types::Canonical(types::Map(&[(1u8, 1u8), (1u8, 2u8)][..])).encode(&mut buf);
+    assert!(matches!(ret, Err(Error::DuplicateKey { .. })));
+
+    // only the top-level keys are sorted, values are encoded as is
+    let mut buf = BufWriter::new(Vec::new());
+    types::Canonical(types::Map(&[(1u8, 1.5f64), (0u8, 1.5f64)][..])).encode(&mut buf).unwrap();
+    assert_eq!(&buf.buffer()[..3], [0xa2, 0x00, 0xfb]);
+
+    // nested maps of value are sorted too
+    let value = Value::Array(vec![
+        Value::Map(vec![
+            (Value::Integer(-1), Value::Null),
+            (Value::Integer(10), Value::Tag(1, Box::new(Value::Map(vec![
+                (Value::Text("z".into()), Value::Bool(true)),
+                (Value::Integer(0), Value::Bool(false))
+            ]))))
+        ])
+    ]);
+    let mut buf = BufWriter::new(Vec::new());
+    types::Canonical(&value).encode(&mut buf).unwrap();
+    assert_eq!(buf.buffer(), [0x81, 0xa2, 0x0a, 0xc1, 0xa2, 0x00, 0xf4, 0x61, b'z', 0xf5, 0x20, 0xf6]);
+
+    let value = Value::Map(vec![
+        (Value::Text("a".into()), Value::Null),
+        (Value::Text("a".into()), Value::Null)
+    ]);
+    let mut buf = BufWriter::new(Vec::new());
+    let ret = types::Canonical(&value).encode(&mut buf);
+    assert!(matches!(ret, Err(Error::DuplicateKey { .. })));
+}
@@ -0,0 +1,255 @@
+use crate::core::{ major, types };
+use crate::core::enc::{ Write, Encode, Error };
+
+
+/// Maximum nesting of containers and tags.
+const STACK_SIZE: usize = 256;
+
+#[derive(Clone, Copy)]
+struct Frame {
+    major: u8,
+    /// declared number of items, a map pair is counted as two items
+    len: Option<u64>,
+    count: u64
+}
+
+/// Push encoder
+///
+/// This writes items to writer as they are pushed,
+/// and checks that they match the structure of open containers,
+/// without allocation.
+///
+/// Containers must be closed by [`Encoder::end`],
+/// and a tag is closed after its content item.
+/// Multiple top level items are allowed.
+pub struct Encoder<W> {
+    writer: W,
+    stack: [Frame; STACK_SIZE],
+    depth: usize
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(writer: W) -> Encoder<W> {
+        Encoder {
+            writer,
+            stack: [Frame { major: 0, len: None, count: 0 }; STACK_SIZE],
+            depth: 0
+        }
+    }
+
+    /// Number of open containers and tags.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Push a complete item.
+    pub fn value<T: Encode>(&mut self, value: &T) -> Result<(), Error<W::Error>> {
+        self.check(&"value")?;
+        value.encode(&mut self.writer)?;
+        self.complete();
+        Ok(())
+    }
+
+    /// Open an array, with definite length or indefinite length.
+    pub fn array(&mut self, len: Option<usize>) -> Result<(), Error<W::Error>> {
+        let name = &"array";
+
+        self.check(name)?;
+        self.push(name, major::ARRAY, len.map(|len| len as u64))?;
+        match len {
+            Some(len) => types::Array::bounded(len, &mut self.writer),
+            None => types::Array::unbounded(&mut self.writer)
+        }
+    }
+
+    /// Open a map, with the definite number of pairs or indefinite length.
+    pub fn map(&mut self, len: Option<usize>) -> Result<(), Error<W::Error>> {
+        let name = &"map";
+
+        let count = match len {
+            Some(len) => Some((len as u64).checked_mul(2)
+                .ok_or_else(|| Error::length_mismatch(name))?),
+            None => None
+        };
+
+        self.check(name)?;
+        self.push(name, major::MAP, count)?;
+        match len {
+            Some(len) => types::Map::bounded(len, &mut self.writer),
+            None => types::Map::unbounded(&mut self.writer)
+        }
+    }
+
+    /// Push a tag, the next item is its content.
+    pub fn tag(&mut self, tag: u64) -> Result<(), Error<W::Error>> {
+        let name = &"tag";
+
+        self.check(name)?;
+        self.push(name, major::TAG, Some(1))?;
+        types::Tag(tag, types::Nothing).encode(&mut self.writer)
+    }
+
+    /// Close the innermost container.
+    ///
+    /// It checks the declared length,
+    /// and writes the break marker for indefinite length container.
+    pub fn end(&mut self) -> Result<(), Error<W::Error>> {
+        let name = &"end";
+
+        let frame = match self.depth.checked_sub(1) {
+            Some(depth) => self.stack[depth],
+            None => return Err(Error::unbalanced(name))
+        };
+
+        if frame.major == major::TAG {
+            return Err(Error::unbalanced(name));
+        }
+
+        if frame.major == major::MAP && frame.count % 2 != 0 {
+            return Err(Error::missing_value(name));
+        }
+
+        match frame.len {
+            Some(len) if len != frame.count => return Err(Error::length_mismatch(name)),
+            Some(_) => (),
+            None => types::Array::end(&mut self.writer)?
+        }
+
+        self.depth -= 1;
+        self.complete();
+        Ok(())
+    }
+
+    /// Check that all containers are closed, and return the writer.
+    pub fn finish(self) -> Result<W, Error<W::Error>> {
+        if self.depth == 0 {
+            Ok(self.writer)
+        } else {
+            Err(Error::unbalanced(&"finish"))
+        }
+    }
+
+    /// Check that the innermost container can take one more item.
+    fn check(&self, name: crate::core::error::StaticStr) -> Result<(), Error<W::Error>> {
+        if let Some(depth) = self.depth.checked_sub(1) {
+            let frame = &self.stack[depth];
+            if frame.len.filter(|&len| frame.count >= len).is_some() {
+                return Err(Error::length_mismatch(name));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, name: crate::core::error::StaticStr, major: u8, len: Option<u64>)
+        -> Result<(), Error<W::Error>>
+    {
+        if self.depth == STACK_SIZE {
+            return Err(Error::depth_overflow(name));
+        }
+
+        self.stack[self.depth] = Frame { major, len, count: 0 };
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Complete an item of the innermost container, and close completed tags.
+    fn complete(&mut self) {
+        while let Some(depth) = self.depth.checked_sub(1) {
+            let frame = &mut self.stack[depth];
+            frame.count += 1;
+
+            if frame.major == major::TAG {
+                self.depth = depth;
+            } else {
+                break
+            }
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "use_std")]
+fn test_encoder() {
+    use crate::core::utils::BufWriter;
+    use crate::alloc::vec::Vec;
+
+    fn hex(encoder: Encoder<BufWriter>) -> String {
+        let writer = encoder.finish().unwrap();
+        data_encoding::HEXLOWER.encode(writer.buffer())
+    }
+
+    // [1, {_ "a": 1(h'01')}, [_ ]]
+    let mut encoder = Encoder::new(BufWriter::new(Vec::new()));
+    encoder.array(Some(3)).unwrap();
+    encoder.value(&1u32).unwrap();
+    encoder.map(None).unwrap();
+    encoder.value(&"a").unwrap();
+    encoder.tag(1).unwrap();
+    encoder.value(&types::Bytes(&[1][..])).unwrap();
+    assert_eq!(encoder.depth(), 2);
+    encoder.end().unwrap();
+    encoder.array(None).unwrap();
+    encoder.end().unwrap();
+    encoder.end().unwrap();
+    assert_eq!(encoder.depth(), 0);
+    assert_eq!(hex(encoder), "8301bf6161c14101ff9fff");
+
+    // tagged container, and a second top level item
+    let mut encoder = Encoder::new(BufWriter::new(Vec::new()));
+    encoder.tag(2).unwrap();
+    encoder.tag(3).unwrap();
+    encoder.map(Some(0)).unwrap();
+    encoder.end().unwrap();
+    assert_eq!(encoder.depth(), 0);
+    encoder.value(&true).unwrap();
+    assert_eq!(hex(encoder), "c2c3a0f5");
+
+    // too many items
+    let mut encoder = Encoder::new(BufWriter::new(Vec::new()));
+    encoder.array(Some(1)).unwrap();
+    encoder.value(&1u32).unwrap();
+    assert!(matches!(encoder.value(&2u32), Err(Error::LengthMismatch { .. })));
+    assert!(matches!(encoder.array(None), Err(Error::LengthMismatch { .. })));
+
+    // too few items
+    let mut encoder = Encoder::new(BufWriter::new(Vec::new()));
+    encoder.map(Some(1)).unwrap();
+    encoder.value(&1u32).unwrap();
+    assert!(matches!(encoder.end(), Err(Error::MissingValue { .. })));
+    encoder.value(&1u32).unwrap();
+    encoder.end().unwrap();
+    encoder.array(Some(2)).unwrap();
+    encoder.value(&1u32).unwrap();
+    assert!(matches!(encoder.end(), Err(Error::LengthMismatch { .. })));
+
+    // closing a map after a key
+    let mut encoder = Encoder::new(BufWriter::new(Vec::new()));
+    encoder.map(None).unwrap();
+    encoder.value(&1u32).unwrap();
+    assert!(matches!(encoder.end(), Err(Error::MissingValue { .. })));
+
+    // missing break
+    let mut encoder = Encoder::new(BufWriter::new(Vec::new()));
+    encoder.array(None).unwrap();
+    assert!(matches!(encoder.finish(), Err(Error::Unbalanced { .. })));
+
+    // tag without content
+    let mut encoder = Encoder::new(BufWriter::new(Vec::new()));
+    encoder.array(None).unwrap();
+    encoder.tag(1).unwrap();
+    assert!(matches!(encoder.end(), Err(Error::Unbalanced { .. })));
+    assert!(matches!(encoder.finish(), Err(Error::Unbalanced { .. })));
+
+    // nothing to close
+    let mut encoder = Encoder::new(BufWriter::new(Vec::new()));
+    assert!(matches!(encoder.end(), Err(Error::Unbalanced { .. })));
+
+    // depth limit
+    let mut encoder = Encoder::new(BufWriter::new(Vec::new()));
+    for _ in 0..STACK_SIZE {
+        encoder.array(None).unwrap();
+    }
+    assert!(matches!(encoder.array(None), Err(Error::DepthOverflow { .. })));
+}
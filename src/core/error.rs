@@ -1,5 +1,6 @@
 use core::fmt;
 use core::convert::TryFrom;
+use core::num::NonZeroUsize;
 
 
 /// Static String
@@ -68,6 +69,39 @@ pub enum DecodeError<E> {
     DepthOverflow {
         name: StaticStr
     },
+    /// Integer or length is not encoded in the shortest form (strict mode)
+    NonShortest {
+        name: StaticStr,
+        found: u8
+    },
+    /// Indefinite length item (strict mode)
+    IndefiniteLength {
+        name: StaticStr
+    },
+    /// Float can be encoded in a shorter form without loss (strict mode)
+    NonPreferredFloat {
+        name: StaticStr
+    },
+    /// Map keys are not sorted bytewise by their encoded form (strict mode)
+    UnsortedKey {
+        name: StaticStr
+    },
+    /// Map has duplicate keys
+    DuplicateKey {
+        name: StaticStr
+    },
+    /// Struct field is missing
+    MissingField {
+        name: StaticStr
+    },
+    /// Decode limit is exceeded, or memory allocation failed
+    LimitExceeded {
+        name: StaticStr
+    },
+    /// Input has data after the item, starting at the byte offset if the reader tracks it
+    TrailingData {
+        offset: Option<NonZeroUsize>
+    },
     Custom {
         name: StaticStr,
         num: u32
@@ -145,6 +179,60 @@ impl<E> DecodeError<E> {
     pub(crate) fn depth_overflow(name: StaticStr) -> DecodeError<E> {
         DecodeError::DepthOverflow { name }
     }
+
+    #[cold]
+    pub(crate) fn non_shortest(name: StaticStr, found: u8) -> DecodeError<E> {
+        DecodeError::NonShortest { name, found }
+    }
+
+    #[cold]
+    pub(crate) fn indefinite_length(name: StaticStr) -> DecodeError<E> {
+        DecodeError::IndefiniteLength { name }
+    }
+
+    #[cold]
+    pub(crate) fn non_preferred_float(name: StaticStr) -> DecodeError<E> {
+        DecodeError::NonPreferredFloat { name }
+    }
+
+    #[cold]
+    #[cfg(feature = "use_alloc")]
+    pub(crate) fn unsorted_key(name: StaticStr) -> DecodeError<E> {
+        DecodeError::UnsortedKey { name }
+    }
+
+    #[cold]
+    pub(crate) fn duplicate_key(name: StaticStr) -> DecodeError<E> {
+        DecodeError::DuplicateKey { name }
+    }
+
+    #[cold]
+    pub(crate) fn missing_field(name: StaticStr) -> DecodeError<E> {
+        DecodeError::MissingField { name }
+    }
+
+    #[cold]
+    pub(crate) fn limit_exceeded(name: StaticStr) -> DecodeError<E> {
+        DecodeError::LimitExceeded { name }
+    }
+
+    #[cold]
+    pub(crate) fn trailing_data(offset: Option<usize>) -> DecodeError<E> {
+        DecodeError::TrailingData { offset: offset.and_then(NonZeroUsize::new) }
+    }
+}
+
+impl<E> DecodeError<E> {
+    /// Returns the byte offset carried by the error, if known.
+    ///
+    /// Only [`DecodeError::TrailingData`] has it, the offset of other errors
+    /// is the offset of the reader when the error is returned, see [`Read::offset`](crate::core::dec::Read::offset).
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            DecodeError::TrailingData { offset } => offset.map(NonZeroUsize::get),
+            _ => None
+        }
+    }
 }
 
 impl<E: fmt::Debug> fmt::Display for DecodeError<E> {
@@ -166,7 +254,25 @@ impl<E: core::error::Error + 'static> core::error::Error for DecodeError<E> {
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum EncodeError<E> {
-    Write(E)
+    Write(E),
+    DuplicateKey {
+        name: StaticStr
+    },
+    /// The number of items does not match the declared length
+    LengthMismatch {
+        name: StaticStr
+    },
+    /// Map is closed after a key without value
+    MissingValue {
+        name: StaticStr
+    },
+    /// Container is not closed, or there is no container to close
+    Unbalanced {
+        name: StaticStr
+    },
+    DepthOverflow {
+        name: StaticStr
+    }
 }
 
 impl<E> From<E> for EncodeError<E> {
@@ -176,6 +282,50 @@ impl<E> From<E> for EncodeError<E> {
     }
 }
 
+impl<E> EncodeError<E> {
+    #[cold]
+    #[cfg(feature = "use_alloc")]
+    pub(crate) fn duplicate_key(name: StaticStr) -> EncodeError<E> {
+        EncodeError::DuplicateKey { name }
+    }
+
+    #[cold]
+    pub(crate) fn length_mismatch(name: StaticStr) -> EncodeError<E> {
+        EncodeError::LengthMismatch { name }
+    }
+
+    #[cold]
+    pub(crate) fn missing_value(name: StaticStr) -> EncodeError<E> {
+        EncodeError::MissingValue { name }
+    }
+
+    #[cold]
+    pub(crate) fn unbalanced(name: StaticStr) -> EncodeError<E> {
+        EncodeError::Unbalanced { name }
+    }
+
+    #[cold]
+    pub(crate) fn depth_overflow(name: StaticStr) -> EncodeError<E> {
+        EncodeError::DepthOverflow { name }
+    }
+}
+
+#[cfg(feature = "use_alloc")]
+impl EncodeError<Never> {
+    /// Convert an error from an infallible writer into any error type.
+    #[inline]
+    pub(crate) fn cast<E>(self) -> EncodeError<E> {
+        match self {
+            EncodeError::Write(never) => match never {},
+            EncodeError::DuplicateKey { name } => EncodeError::DuplicateKey { name },
+            EncodeError::LengthMismatch { name } => EncodeError::LengthMismatch { name },
+            EncodeError::MissingValue { name } => EncodeError::MissingValue { name },
+            EncodeError::Unbalanced { name } => EncodeError::Unbalanced { name },
+            EncodeError::DepthOverflow { name } => EncodeError::DepthOverflow { name }
+        }
+    }
+}
+
 impl<E: fmt::Debug> fmt::Display for EncodeError<E> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Debug::fmt(self, f)
@@ -186,6 +336,7 @@ impl<E: core::error::Error + 'static> core::error::Error for EncodeError<E> {
     fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
         match self {
             EncodeError::Write(err) => Some(err),
+            _ => None
         }
     }
 }
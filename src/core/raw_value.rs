@@ -1,8 +1,11 @@
+use core::convert::TryFrom;
 use core::marker::PhantomData;
-use crate::core::{ enc, dec };
+use crate::core::{ major, types, enc, dec };
+use crate::core::dec::Decode;
+use crate::core::utils::SliceReader;
 
 #[derive(PartialEq, Eq, Debug, Clone)]
-pub struct RawValue<'de>(&'de [u8]);
+pub struct RawValue<'de>(pub(crate) &'de [u8]);
 
 struct RawValueReader<'r, 'de, R>
 where R: dec::Read<'de>
@@ -61,6 +64,27 @@ where R: dec::Read<'de>
     fn step_out(&mut self) {
         self.reader.step_out()
     }
+
+    #[inline]
+    fn is_strict(&self) -> bool {
+        self.reader.is_strict()
+    }
+
+    #[inline]
+    fn duplicate_key(&self) -> dec::DuplicateKey {
+        self.reader.duplicate_key()
+    }
+
+    #[inline]
+    fn check_limit(&mut self, limit: dec::Limit) -> bool {
+        self.reader.check_limit(limit)
+    }
+
+    #[inline]
+    fn offset(&self) -> Option<usize> {
+        self.reader.offset()
+            .and_then(|offset| offset.checked_add(self.readn))
+    }
 }
 
 impl<'de> dec::Decode<'de> for RawValue<'de> {
@@ -69,7 +93,11 @@ impl<'de> dec::Decode<'de> for RawValue<'de> {
         let name = &"raw-value";
 
         let mut reader = RawValueReader::new(reader);
-        let _ignore = dec::IgnoredAny::decode(&mut reader)?;
+        if let Err(err) = dec::IgnoredAny::decode(&mut reader) {
+            // keep the offset of error
+            reader.reader.advance(reader.readn);
+            return Err(err);
+        }
 
         let buf = match reader.reader.fill(reader.readn).map_err(dec::Error::Read)? {
             dec::Reference::Long(buf)
@@ -95,6 +123,225 @@ impl<'de> RawValue<'de> {
     pub fn as_bytes(&self) -> &'de [u8] {
         self.0
     }
+
+    /// Returns the tag number and the content, if it is a tagged value.
+    pub fn tag(&self) -> Option<(u64, RawValue<'de>)> {
+        if dec::if_major(*self.0.first()?) != major::TAG {
+            return None;
+        }
+
+        let mut reader = SliceReader::new(self.0);
+        let tag = types::Tag::tag(&mut reader).ok()?;
+        let value = RawValue::decode(&mut reader).ok()?;
+        Some((tag, value))
+    }
+
+    /// Returns an iterator over the items, if it is an array.
+    pub fn iter_array(&self) -> Option<RawArrayIter<'de>> {
+        if dec::if_major(*self.0.first()?) != major::ARRAY {
+            return None;
+        }
+
+        let mut reader = SliceReader::new(self.0);
+        let len = types::Array::len(&mut reader).ok()?;
+        Some(RawArrayIter { reader, len })
+    }
+
+    /// Returns an iterator over the key and value pairs, if it is a map.
+    pub fn iter_map(&self) -> Option<RawMapIter<'de>> {
+        if dec::if_major(*self.0.first()?) != major::MAP {
+            return None;
+        }
+
+        let mut reader = SliceReader::new(self.0);
+        let len = types::Map::len(&mut reader).ok()?;
+        Some(RawMapIter { reader, len })
+    }
+
+    /// Returns the item at index, if it is an array.
+    pub fn index(&self, index: usize) -> Option<RawValue<'de>> {
+        self.iter_array()?.nth(index)
+    }
+
+    /// Returns the value of first entry with the key, if it is a map.
+    ///
+    /// Keys are compared by their encoded form,
+    /// so a key that is not encoded in the preferred form does not match.
+    pub fn get<K: enc::Encode>(&self, key: K) -> Option<RawValue<'de>> {
+        self.iter_map()?
+            .find(|(k, _)| KeyMatcher::matches(k.0, &key))
+            .map(|(_, v)| v)
+    }
+
+    /// Looks up a value by a path like `/a/3/b`.
+    ///
+    /// Each segment is an index of array, or a text key or integer key of map,
+    /// tags are skipped.
+    /// Segments use the escapes of json pointer (rfc6901),
+    /// `~1` for `/` and `~0` for `~`. The empty path refers to the whole value.
+    pub fn pointer(&self, path: &str) -> Option<RawValue<'de>> {
+        if path.is_empty() {
+            return Some(self.clone());
+        }
+
+        let path = path.strip_prefix('/')?;
+        path.split('/').try_fold(self.clone(), |value, segment| {
+            let mut value = value;
+            while let Some((_, content)) = value.tag() {
+                value = content;
+            }
+
+            match dec::if_major(*value.0.first()?) {
+                major::ARRAY => value.index(usize::try_from(parse_index(segment)?).ok()?),
+                major::MAP => value.iter_map()?
+                    .find(|(k, _)| segment_matches(k, segment))
+                    .map(|(_, v)| v),
+                _ => None
+            }
+        })
+    }
+}
+
+/// Iterator over items of a raw array
+///
+/// The raw value is checked when it is decoded, so decoding items does not fail.
+pub struct RawArrayIter<'de> {
+    reader: SliceReader<'de>,
+    len: Option<usize>
+}
+
+impl<'de> Iterator for RawArrayIter<'de> {
+    type Item = RawValue<'de>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.len.as_mut() {
+            Some(0) => return None,
+            Some(len) => *len -= 1,
+            None => if dec::is_break(&mut self.reader).ok()? {
+                self.len = Some(0);
+                return None;
+            }
+        }
+
+        RawValue::decode(&mut self.reader).ok()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.len {
+            Some(len) => (len, Some(len)),
+            None => (0, None)
+        }
+    }
+}
+
+/// Iterator over entries of a raw map
+pub struct RawMapIter<'de> {
+    reader: SliceReader<'de>,
+    len: Option<usize>
+}
+
+impl<'de> Iterator for RawMapIter<'de> {
+    type Item = (RawValue<'de>, RawValue<'de>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.len.as_mut() {
+            Some(0) => return None,
+            Some(len) => *len -= 1,
+            None => if dec::is_break(&mut self.reader).ok()? {
+                self.len = Some(0);
+                return None;
+            }
+        }
+
+        let key = RawValue::decode(&mut self.reader).ok()?;
+        let value = RawValue::decode(&mut self.reader).ok()?;
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.len {
+            Some(len) => (len, Some(len)),
+            None => (0, None)
+        }
+    }
+}
+
+/// Writer that compares the encoded key with raw key.
+struct KeyMatcher<'a> {
+    rest: &'a [u8],
+    matched: bool
+}
+
+impl KeyMatcher<'_> {
+    fn matches<K: enc::Encode>(raw: &[u8], key: &K) -> bool {
+        let mut matcher = KeyMatcher { rest: raw, matched: true };
+        key.encode(&mut matcher).is_ok() && matcher.matched && matcher.rest.is_empty()
+    }
+}
+
+impl enc::Write for KeyMatcher<'_> {
+    type Error = crate::core::error::Never;
+
+    #[inline]
+    fn push(&mut self, input: &[u8]) -> Result<(), Self::Error> {
+        match self.rest.strip_prefix(input) {
+            Some(rest) if self.matched => self.rest = rest,
+            _ => self.matched = false
+        }
+        Ok(())
+    }
+}
+
+/// Parse a decimal number without sign and leading zeros.
+fn parse_index(segment: &str) -> Option<u64> {
+    let valid = segment.bytes().all(|b| b.is_ascii_digit())
+        && (segment.len() == 1 || !segment.starts_with('0'));
+
+    if valid {
+        segment.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Compare a map key with an escaped pointer segment.
+fn segment_matches(key: &RawValue<'_>, segment: &str) -> bool {
+    let mut reader = SliceReader::new(key.0);
+
+    match key.0.first().copied().map(dec::if_major) {
+        Some(major::STRING) => match <&str>::decode(&mut reader) {
+            Ok(key) => {
+                let mut parts = segment.split('~');
+                let mut rest = key.strip_prefix(parts.next().unwrap_or_default());
+
+                for part in parts {
+                    let (escaped, part) = match part.as_bytes().first() {
+                        Some(b'0') => ("~", &part[1..]),
+                        Some(b'1') => ("/", &part[1..]),
+                        _ => return false
+                    };
+                    rest = rest
+                        .and_then(|rest| rest.strip_prefix(escaped))
+                        .and_then(|rest| rest.strip_prefix(part));
+                }
+
+                rest == Some("")
+            },
+            Err(_) => false
+        },
+        Some(major::UNSIGNED) | Some(major::NEGATIVE) => {
+            let segment_key = match segment.strip_prefix('-') {
+                Some(n) => parse_index(n).map(|n| -(n as i128)),
+                None => parse_index(segment).map(|n| n as i128)
+            };
+
+            match i128::decode(&mut reader) {
+                Ok(key) => segment_key == Some(key) && segment != "-0",
+                Err(_) => false
+            }
+        },
+        _ => false
+    }
 }
 
 #[cfg(feature = "use_alloc")]
@@ -105,7 +352,7 @@ pub mod boxed {
     use super::*;
     
     #[derive(PartialEq, Eq, Debug, Clone)]
-    pub struct BoxedRawValue(Box<[u8]>);
+    pub struct BoxedRawValue(pub(crate) Box<[u8]>);
 
     impl<'de> dec::Decode<'de> for BoxedRawValue {
         #[inline]
@@ -127,6 +374,40 @@ pub mod boxed {
             &self.0
         }
 
+        pub fn as_raw_value(&self) -> RawValue<'_> {
+            RawValue(&self.0)
+        }
+
+        /// See [`RawValue::tag`].
+        pub fn tag(&self) -> Option<(u64, RawValue<'_>)> {
+            self.as_raw_value().tag()
+        }
+
+        /// See [`RawValue::iter_array`].
+        pub fn iter_array(&self) -> Option<RawArrayIter<'_>> {
+            self.as_raw_value().iter_array()
+        }
+
+        /// See [`RawValue::iter_map`].
+        pub fn iter_map(&self) -> Option<RawMapIter<'_>> {
+            self.as_raw_value().iter_map()
+        }
+
+        /// See [`RawValue::index`].
+        pub fn index(&self, index: usize) -> Option<RawValue<'_>> {
+            self.as_raw_value().index(index)
+        }
+
+        /// See [`RawValue::get`].
+        pub fn get<K: enc::Encode>(&self, key: K) -> Option<RawValue<'_>> {
+            self.as_raw_value().get(key)
+        }
+
+        /// See [`RawValue::pointer`].
+        pub fn pointer(&self, path: &str) -> Option<RawValue<'_>> {
+            self.as_raw_value().pointer(path)
+        }
+
         pub fn from_value(value: &Value)
             -> Result<BoxedRawValue, enc::Error<crate::alloc::collections::TryReserveError>>
         {
@@ -249,3 +530,71 @@ fn test_raw_value() {
         assert_eq!(bar.0[0].1, 0x99);        
     }
 }
+
+#[test]
+#[cfg(feature = "use_std")]
+fn test_raw_value_navigation() {
+    use crate::core::diag;
+    use crate::core::utils::BufWriter;
+    use boxed::BoxedRawValue;
+
+    fn raw(input: &str) -> Vec<u8> {
+        let mut writer = BufWriter::new(Vec::new());
+        diag::parse(input, &mut writer).unwrap();
+        writer.into_inner()
+    }
+
+    let buf = raw(r#"{
+        "a": [1, {"b": h'00'}, [_ 2, 3], 4(5)],
+        1: "int",
+        -2: "neg",
+        "x/y": 6,
+        "m~n": 7,
+        {_ "c": 8}: 9,
+        "t": 32("u")
+    }"#);
+    let value = RawValue::decode(&mut SliceReader::new(&buf)).unwrap();
+    let boxed = BoxedRawValue::decode(&mut SliceReader::new(&buf)).unwrap();
+
+    // get and index
+    let a = value.get("a").unwrap();
+    assert_eq!(a.as_bytes()[0], 0x84);
+    assert_eq!(a.index(0).unwrap().as_bytes(), [0x01]);
+    assert_eq!(a.index(1).unwrap().get("b").unwrap().as_bytes(), [0x41, 0x00]);
+    assert_eq!(a.index(2).unwrap().index(1).unwrap().as_bytes(), [0x03]);
+    assert!(a.index(2).unwrap().index(2).is_none());
+    assert!(a.index(4).is_none());
+    assert!(a.get("b").is_none());
+    assert_eq!(value.get(1u32).unwrap().as_bytes(), raw(r#""int""#));
+    assert_eq!(value.get(-2i32).unwrap().as_bytes(), raw(r#""neg""#));
+    assert!(value.get("z").is_none());
+    assert!(value.index(0).is_none());
+    assert_eq!(boxed.get("a").unwrap(), a);
+
+    // tag
+    let (tag, content) = a.index(3).unwrap().tag().unwrap();
+    assert_eq!((tag, content.as_bytes()), (4, &[0x05][..]));
+    assert!(a.tag().is_none());
+
+    // iterators
+    let items = a.index(2).unwrap().iter_array().unwrap()
+        .map(|item| item.as_bytes())
+        .collect::<Vec<_>>();
+    assert_eq!(items, [[0x02], [0x03]]);
+    assert_eq!(value.iter_map().unwrap().count(), 7);
+    assert_eq!(boxed.iter_map().unwrap().size_hint(), (7, Some(7)));
+    assert!(a.iter_map().is_none());
+
+    // pointer
+    assert_eq!(value.pointer("").unwrap(), value);
+    assert_eq!(value.pointer("/a/1/b").unwrap().as_bytes(), [0x41, 0x00]);
+    assert_eq!(value.pointer("/a/3").unwrap().as_bytes(), [0xc4, 0x05]);
+    assert_eq!(value.pointer("/1").unwrap().as_bytes(), raw(r#""int""#));
+    assert_eq!(value.pointer("/-2").unwrap().as_bytes(), raw(r#""neg""#));
+    assert_eq!(value.pointer("/x~1y").unwrap().as_bytes(), [0x06]);
+    assert_eq!(value.pointer("/m~0n").unwrap().as_bytes(), [0x07]);
+    assert_eq!(boxed.pointer("/t").unwrap().as_bytes(), raw(r#"32("u")"#));
+    for path in ["a", "/a/01", "/a/+1", "/a/", "/+1", "/-02", "/-0", "/a/1/b/0", "/x/y", "/m~2n", "/t/0"] {
+        assert!(value.pointer(path).is_none(), "{}", path);
+    }
+}
@@ -10,6 +10,11 @@ pub struct Array<T>(pub T);
 
 pub struct Map<T>(pub T);
 
+/// Deterministic encoding, see rfc8949 section 4.2
+///
+/// For `Map`, this only sorts the top-level keys.
+pub struct Canonical<T>(pub T);
+
 pub struct Tag<T>(pub u64, pub T);
 
 pub struct Simple(pub u8);
@@ -18,8 +23,31 @@ pub struct Null;
 
 pub struct Undefined;
 
+/// Half precision float bits
+///
+/// This can be converted to `f32` or `f64` without the `half` crate.
 pub struct F16(pub u16);
 
+impl From<F16> for f32 {
+    #[inline]
+    fn from(v: F16) -> f32 {
+        crate::util::f16_to_f32(v.0)
+    }
+}
+
+impl From<F16> for f64 {
+    #[inline]
+    fn from(v: F16) -> f64 {
+        crate::util::f16_to_f64(v.0)
+    }
+}
+
+/// Preferred serialization of float, see rfc8949 section 4.2.2
+///
+/// The float is encoded in the shortest width that preserves its value,
+/// including infinity and NaN payload.
+pub struct Preferred<T>(pub T);
+
 pub struct Maybe<T>(pub T);
 
 pub struct Nothing;
@@ -43,15 +43,61 @@ impl enc::Write for BufWriter {
     }
 }
 
+/// An in-memory writer that grows without limit.
+///
+/// Used to buffer items that must be reordered before writing.
+#[cfg(feature = "use_alloc")]
+pub(crate) struct VecWriter(pub(crate) Vec<u8>);
+
+#[cfg(feature = "use_alloc")]
+impl enc::Write for VecWriter {
+    type Error = crate::core::error::Never;
+
+    #[inline]
+    fn push(&mut self, input: &[u8]) -> Result<(), Self::Error> {
+        self.0.extend_from_slice(input);
+        Ok(())
+    }
+}
+
 /// An in-memory reader.
 pub struct SliceReader<'a> {
     buf: &'a [u8],
-    limit: usize
+    offset: usize,
+    limit: usize,
+    strict: bool,
+    duplicate_key: dec::DuplicateKey,
+    limits: dec::Limits
 }
 
 impl SliceReader<'_> {
     pub fn new(buf: &[u8]) -> SliceReader<'_> {
-        SliceReader { buf, limit: 256 }
+        SliceReader {
+            buf,
+            offset: 0,
+            limit: 256,
+            strict: false,
+            duplicate_key: dec::DuplicateKey::Last,
+            limits: dec::Limits::new()
+        }
+    }
+
+    /// Enable strict mode, see [`dec::Read::is_strict`].
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Set duplicate key policy, see [`dec::Read::duplicate_key`].
+    pub fn duplicate_key(mut self, policy: dec::DuplicateKey) -> Self {
+        self.duplicate_key = policy;
+        self
+    }
+
+    /// Set decode limits, see [`dec::Read::check_limit`].
+    pub fn limits(mut self, limits: dec::Limits) -> Self {
+        self.limits = limits;
+        self
     }
 }
 
@@ -68,6 +114,7 @@ impl<'de> dec::Read<'de> for SliceReader<'de> {
     fn advance(&mut self, n: usize) {
         let len = core::cmp::min(self.buf.len(), n);
         self.buf = &self.buf[len..];
+        self.offset += len;
     }
 
     #[inline]
@@ -84,6 +131,26 @@ impl<'de> dec::Read<'de> for SliceReader<'de> {
     fn step_out(&mut self) {
         self.limit += 1;
     }
+
+    #[inline]
+    fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    #[inline]
+    fn duplicate_key(&self) -> dec::DuplicateKey {
+        self.duplicate_key
+    }
+
+    #[inline]
+    fn check_limit(&mut self, limit: dec::Limit) -> bool {
+        self.limits.check(limit)
+    }
+
+    #[inline]
+    fn offset(&self) -> Option<usize> {
+        Some(self.offset)
+    }
 }
 
 /// A writer to work with [`std::io::Write`].
@@ -118,13 +185,42 @@ impl<W: std::io::Write> enc::Write for IoWriter<W> {
 #[cfg(feature = "use_std")]
 pub struct IoReader<R> {
     reader: R,
+    offset: usize,
     limit: usize,
+    strict: bool,
+    duplicate_key: dec::DuplicateKey,
+    limits: dec::Limits
 }
 
 #[cfg(feature = "use_std")]
 impl<R> IoReader<R> {
     pub fn new(reader: R) -> Self {
-        Self { reader, limit: 256 }
+        Self {
+            reader,
+            offset: 0,
+            limit: 256,
+            strict: false,
+            duplicate_key: dec::DuplicateKey::Last,
+            limits: dec::Limits::new()
+        }
+    }
+
+    /// Enable strict mode, see [`dec::Read::is_strict`].
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Set duplicate key policy, see [`dec::Read::duplicate_key`].
+    pub fn duplicate_key(mut self, policy: dec::DuplicateKey) -> Self {
+        self.duplicate_key = policy;
+        self
+    }
+
+    /// Set decode limits, see [`dec::Read::check_limit`].
+    pub fn limits(mut self, limits: dec::Limits) -> Self {
+        self.limits = limits;
+        self
     }
 
     pub fn into_inner(self) -> R {
@@ -145,6 +241,7 @@ impl<'de, R: std::io::BufRead> dec::Read<'de> for IoReader<R> {
     #[inline]
     fn advance(&mut self, n: usize) {
         self.reader.consume(n);
+        self.offset += n;
     }
 
     #[inline]
@@ -161,4 +258,24 @@ impl<'de, R: std::io::BufRead> dec::Read<'de> for IoReader<R> {
     fn step_out(&mut self) {
         self.limit += 1;
     }
+
+    #[inline]
+    fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    #[inline]
+    fn duplicate_key(&self) -> dec::DuplicateKey {
+        self.duplicate_key
+    }
+
+    #[inline]
+    fn check_limit(&mut self, limit: dec::Limit) -> bool {
+        self.limits.check(limit)
+    }
+
+    #[inline]
+    fn offset(&self) -> Option<usize> {
+        Some(self.offset)
+    }
 }
@@ -0,0 +1,190 @@
+use crate::alloc::{ vec::Vec, boxed::Box, borrow::Cow };
+use crate::core::{ major, marker, types, enc, dec, Value };
+
+
+/// Borrowed value
+///
+/// Same as [`Value`], but text and bytes borrow from input if possible.
+/// Indefinite length strings are owned because they need to be concatenated.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ValueRef<'de> {
+    Null,
+    /// Through serde, `Option` decodes `undefined` as `None`.
+    Undefined,
+    Bool(bool),
+    Integer(i128),
+    Float(f64),
+    Bytes(Cow<'de, [u8]>),
+    Text(Cow<'de, str>),
+    Array(Vec<ValueRef<'de>>),
+    Map(Vec<(ValueRef<'de>, ValueRef<'de>)>),
+    Tag(u64, Box<ValueRef<'de>>),
+    /// Simple value other than `false`, `true`, `null` and `undefined`.
+    Simple(u8)
+}
+
+impl ValueRef<'_> {
+    /// Convert to owned value.
+    pub fn into_owned(self) -> Value {
+        match self {
+            ValueRef::Null => Value::Null,
+            ValueRef::Undefined => Value::Undefined,
+            ValueRef::Bool(v) => Value::Bool(v),
+            ValueRef::Integer(v) => Value::Integer(v),
+            ValueRef::Float(v) => Value::Float(v),
+            ValueRef::Bytes(v) => Value::Bytes(v.into_owned()),
+            ValueRef::Text(v) => Value::Text(v.into_owned()),
+            ValueRef::Array(v) => Value::Array(v.into_iter()
+                .map(ValueRef::into_owned)
+                .collect()),
+            ValueRef::Map(v) => Value::Map(v.into_iter()
+                .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                .collect()),
+            ValueRef::Tag(tag, v) => Value::Tag(tag, Box::new(v.into_owned())),
+            ValueRef::Simple(v) => Value::Simple(v)
+        }
+    }
+}
+
+impl<'a> From<&'a Value> for ValueRef<'a> {
+    fn from(value: &'a Value) -> ValueRef<'a> {
+        match value {
+            Value::Null => ValueRef::Null,
+            Value::Undefined => ValueRef::Undefined,
+            Value::Bool(v) => ValueRef::Bool(*v),
+            Value::Integer(v) => ValueRef::Integer(*v),
+            Value::Float(v) => ValueRef::Float(*v),
+            Value::Bytes(v) => ValueRef::Bytes(Cow::Borrowed(v)),
+            Value::Text(v) => ValueRef::Text(Cow::Borrowed(v)),
+            Value::Array(v) => ValueRef::Array(v.iter().map(ValueRef::from).collect()),
+            Value::Map(v) => ValueRef::Map(v.iter()
+                .map(|(k, v)| (ValueRef::from(k), ValueRef::from(v)))
+                .collect()),
+            Value::Tag(tag, v) => ValueRef::Tag(*tag, Box::new(ValueRef::from(&**v))),
+            Value::Simple(v) => ValueRef::Simple(*v)
+        }
+    }
+}
+
+impl enc::Encode for ValueRef<'_> {
+    fn encode<W: enc::Write>(&self, writer: &mut W) -> Result<(), enc::Error<W::Error>> {
+        match self {
+            ValueRef::Null => types::Null.encode(writer),
+            ValueRef::Undefined => types::Undefined.encode(writer),
+            ValueRef::Bool(v) => v.encode(writer),
+            ValueRef::Integer(v) => v.encode(writer),
+            ValueRef::Float(v) => v.encode(writer),
+            ValueRef::Bytes(v) => types::Bytes(v.as_ref()).encode(writer),
+            ValueRef::Text(v) => v.as_ref().encode(writer),
+            ValueRef::Array(v) => v.as_slice().encode(writer),
+            ValueRef::Map(v) => types::Map(v.as_slice()).encode(writer),
+            ValueRef::Tag(tag, v) => types::Tag(*tag, &**v).encode(writer),
+            ValueRef::Simple(v) => types::Simple(*v).encode(writer)
+        }
+    }
+}
+
+impl<'de> dec::Decode<'de> for ValueRef<'de> {
+    fn decode<R: dec::Read<'de>>(reader: &mut R) -> Result<Self, dec::Error<R::Error>> {
+        use crate::util::ScopeGuard;
+
+        let name = &"value_ref";
+
+        if !reader.step_in() {
+            return Err(dec::Error::depth_overflow(name));
+        }
+
+        let mut reader = ScopeGuard(reader, |reader| reader.step_out());
+        let reader = &mut *reader;
+
+        let byte = dec::peek_one(name, reader)?;
+
+        match dec::if_major(byte) {
+            major::UNSIGNED => u64::decode(reader)
+                .map(|i| ValueRef::Integer(i.into())),
+            major::NEGATIVE => {
+                let types::Negative(v) = <types::Negative<u64>>::decode(reader)?;
+                let v = i128::from(v);
+                let v = -v - 1;
+                Ok(ValueRef::Integer(v))
+            },
+            major::BYTES => <types::Bytes<Cow<[u8]>>>::decode(reader)
+                .map(|buf| ValueRef::Bytes(buf.0)),
+            major::STRING => <Cow<str>>::decode(reader)
+                .map(ValueRef::Text),
+            major::ARRAY => <Vec<ValueRef>>::decode(reader)
+                .map(ValueRef::Array),
+            major::MAP => <types::Map<Vec<(ValueRef, ValueRef)>>>::decode(reader)
+                .map(|map| ValueRef::Map(map.0)),
+            major::TAG => {
+                let tag = <types::Tag<ValueRef>>::decode(reader)?;
+                dec::check_limit(&"tag", reader, dec::Limit::Alloc(core::mem::size_of::<ValueRef>()))?;
+                Ok(ValueRef::Tag(tag.0, Box::new(tag.1)))
+            },
+            major::SIMPLE => match byte {
+                marker::FALSE => {
+                    reader.advance(1);
+                    Ok(ValueRef::Bool(false))
+                },
+                marker::TRUE => {
+                    reader.advance(1);
+                    Ok(ValueRef::Bool(true))
+                },
+                marker::NULL => {
+                    reader.advance(1);
+                    Ok(ValueRef::Null)
+                },
+                marker::UNDEFINED => {
+                    reader.advance(1);
+                    Ok(ValueRef::Undefined)
+                },
+                0xe0 ..= 0xf3 | 0xf8 => types::Simple::decode(reader)
+                    .map(|v| ValueRef::Simple(v.0)),
+                marker::F16 | marker::F32 | marker::F64 => f64::decode(reader)
+                    .map(ValueRef::Float),
+                _ => Err(dec::Error::unsupported(name, byte))
+            },
+            _ => Err(dec::Error::unsupported(name, byte))
+        }
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl serde::Serialize for ValueRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        use serde::ser::{ SerializeMap, SerializeSeq };
+
+        match self {
+            ValueRef::Null => serializer.serialize_none(),
+            ValueRef::Undefined => crate::serde::simple::serialize(23, serializer),
+            ValueRef::Bool(v) => serializer.serialize_bool(*v),
+            ValueRef::Integer(v) => serializer.serialize_i128(*v),
+            ValueRef::Float(v) => serializer.serialize_f64(*v),
+            ValueRef::Bytes(v) => serializer.serialize_bytes(v),
+            ValueRef::Text(v) => serializer.serialize_str(v),
+            ValueRef::Array(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for value in v.iter() {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            },
+            ValueRef::Map(v) => {
+                let mut map = serializer.serialize_map(Some(v.len()))?;
+                for (k, v) in v.iter() {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            },
+            ValueRef::Tag(tag, v) => crate::serde::Tagged(*tag, &**v).serialize(serializer),
+            ValueRef::Simple(v) => crate::serde::simple::serialize(*v, serializer)
+        }
+    }
+}
+
+#[cfg(feature = "serde1")]
+crate::core::value_deserialize!(ValueRef<'de>, ValueRef);
@@ -3,6 +3,10 @@
 mod error;
 mod ser;
 mod de;
+pub(crate) mod tag;
+mod raw;
+pub(crate) mod simple;
+mod value;
 
 #[cfg(feature = "use_std")]
 mod io_writer {
@@ -52,6 +56,9 @@ mod slice_reader {
     use crate::serde::error::DecodeError;
 
     /// Decodes a value from a bytes.
+    ///
+    /// Data after the value is ignored, see [`from_slice_exact`].
+    /// The error has the byte offset at which it was detected, see [`DecodeError::offset`].
     pub fn from_slice<'a, T>(buf: &'a [u8]) -> Result<T, DecodeError<Infallible>>
     where
         T: serde::Deserialize<'a>,
@@ -59,6 +66,20 @@ mod slice_reader {
         let reader = SliceReader::new(buf);
         let mut deserializer = de::Deserializer::new(reader);
         serde::Deserialize::deserialize(&mut deserializer)
+            .map_err(|err| err.with_offset(deserializer.offset()))
+    }
+
+    /// Decodes a value from a bytes,
+    /// and returns [`TrailingData`](crate::core::error::DecodeError::TrailingData) if there is data after it.
+    pub fn from_slice_exact<'a, T>(buf: &'a [u8]) -> Result<T, DecodeError<Infallible>>
+    where
+        T: serde::Deserialize<'a>,
+    {
+        let reader = SliceReader::new(buf);
+        let mut deserializer = de::Deserializer::new(reader);
+        serde::Deserialize::deserialize(&mut deserializer)
+            .and_then(|value| deserializer.end().map(|_| value))
+            .map_err(|err| err.with_offset(deserializer.offset()))
     }
 }
 
@@ -70,6 +91,9 @@ mod io_buf_reader {
     use crate::serde::error::DecodeError;
 
     /// Decodes a value from a reader.
+    ///
+    /// Data after the value is not read, see [`from_reader_exact`].
+    /// The error has the byte offset at which it was detected, see [`DecodeError::offset`].
     pub fn from_reader<T, R>(reader: R) -> Result<T, DecodeError<io::Error>>
     where
         T: serde::de::DeserializeOwned,
@@ -78,14 +102,31 @@ mod io_buf_reader {
         let reader = IoReader::new(reader);
         let mut deserializer = de::Deserializer::new(reader);
         serde::Deserialize::deserialize(&mut deserializer)
+            .map_err(|err| err.with_offset(deserializer.offset()))
+    }
+
+    /// Decodes a value from a reader,
+    /// and returns [`TrailingData`](crate::core::error::DecodeError::TrailingData) if the reader does not end after it.
+    pub fn from_reader_exact<T, R>(reader: R) -> Result<T, DecodeError<io::Error>>
+    where
+        T: serde::de::DeserializeOwned,
+        R: BufRead
+    {
+        let reader = IoReader::new(reader);
+        let mut deserializer = de::Deserializer::new(reader);
+        serde::Deserialize::deserialize(&mut deserializer)
+            .and_then(|value| deserializer.end().map(|_| value))
+            .map_err(|err| err.with_offset(deserializer.offset()))
     }
 }
 
 #[cfg(feature = "use_std")] pub use io_writer::to_writer;
-#[cfg(feature = "use_std")] pub use io_buf_reader::from_reader;
+#[cfg(feature = "use_std")] pub use io_buf_reader::{ from_reader, from_reader_exact };
 pub use buf_writer::to_vec;
-pub use slice_reader::from_slice;
+pub use slice_reader::{ from_slice, from_slice_exact };
 
-pub use error::{ EncodeError, DecodeError };
+pub use error::{ EncodeError, DecodeError, Path, PathSegment };
 pub use ser::Serializer;
-pub use de::Deserializer;
+pub use de::{ Deserializer, StreamDeserializer };
+pub use tag::{ Tagged, FixedTag };
+pub use value::{ to_value, from_value, ValueSerializer };
@@ -1,26 +1,93 @@
+use core::marker::PhantomData;
 use crate::alloc::borrow::Cow;
 use serde::de::{ self, Visitor };
 use crate::core::{ major, marker, types, error };
 use crate::core::dec::{ self, Decode };
+use crate::alloc::vec::Vec;
 use crate::util::ScopeGuard;
-use crate::serde::error::DecodeError;
+use crate::serde::error::{ DecodeError, PathSegment };
+use crate::serde::{ tag, raw, simple };
 
 
 pub struct Deserializer<R> {
-    reader: R
+    reader: R,
+    config: Config,
+    /// number of `with_segment` calls in progress
+    path_depth: usize
+}
+
+#[derive(Clone, Copy, Default)]
+struct Config {
+    track_path: bool,
+    packed: bool,
+    integer_keys: bool
 }
 
 impl<R> Deserializer<R> {
     pub fn new(reader: R) -> Deserializer<R> {
-        Deserializer { reader }
+        Deserializer { reader, config: Config::default(), path_depth: 0 }
+    }
+
+    /// Track the path of map keys, sequence indices and enum variants,
+    /// and attach it to decode errors, see [`DecodeError::path`].
+    ///
+    /// This records every map key and enum variant, which has some overhead.
+    pub fn track_path(mut self) -> Deserializer<R> {
+        self.config.track_path = true;
+        self
+    }
+
+    /// Accept packed encoding, see [`Serializer::packed`](crate::serde::Serializer::packed).
+    ///
+    /// Structs encoded as arrays are accepted,
+    /// and an unsigned integer is accepted as the index of struct field or enum variant.
+    pub fn packed(mut self) -> Deserializer<R> {
+        self.config.packed = true;
+        self
+    }
+
+    /// Decode integer struct field names as their decimal form,
+    /// so that fields renamed to `"1"` or `"-2"` match integer keys.
+    ///
+    /// Enum variants are not affected, so they can still be decoded by index in packed mode.
+    ///
+    /// See [`Serializer::integer_keys`](crate::serde::Serializer::integer_keys).
+    pub fn integer_keys(mut self) -> Deserializer<R> {
+        self.config.integer_keys = true;
+        self
     }
 
     pub fn into_inner(self) -> R {
         self.reader
     }
+
+    /// Turn into an iterator of items in a CBOR sequence (rfc8742).
+    pub fn into_stream<'de, T>(self) -> StreamDeserializer<'de, R, T> {
+        StreamDeserializer {
+            de: self,
+            done: false,
+            _phantom: PhantomData
+        }
+    }
 }
 
 impl<'de, R: dec::Read<'de>> Deserializer<R> {
+    /// Returns the number of bytes consumed so far, if the reader tracks it.
+    ///
+    /// After an error, this is the offset at which the error was detected.
+    #[inline]
+    pub fn offset(&self) -> Option<usize> {
+        self.reader.offset()
+    }
+
+    /// Check that the reader has no data left.
+    ///
+    /// Call this after deserializing a value if the input must contain only that item.
+    pub fn end(&mut self) -> Result<(), DecodeError<R::Error>> {
+        dec::end(&mut self.reader)?;
+        Ok(())
+    }
+
     #[inline]
     fn try_step(&mut self, name: error::StaticStr) -> Result<ScopeGuard<'_, Self>, dec::Error<R::Error>> {
         if self.reader.step_in() {
@@ -29,6 +96,111 @@ impl<'de, R: dec::Read<'de>> Deserializer<R> {
             Err(dec::Error::depth_overflow(name))
         }
     }
+
+    /// Deserialize a map key or an enum variant, and record its bytes.
+    #[inline]
+    fn record_seed<T>(&mut self, seed: T, buf: &mut Vec<u8>)
+        -> Result<T::Value, DecodeError<R::Error>>
+    where T: de::DeserializeSeed<'de>
+    {
+        let mut de = Deserializer {
+            reader: dec::KeyRecorder::new(&mut self.reader),
+            config: self.config,
+            path_depth: self.path_depth
+        };
+        let value = seed.deserialize(&mut de)?;
+        *buf = de.reader.into_key();
+        Ok(value)
+    }
+
+    /// Deserialize with `f`, and add the path segment to error if it fails.
+    ///
+    /// Segments are pushed from the innermost one,
+    /// and the path is reversed when the outermost one is pushed.
+    #[inline]
+    fn with_segment<T, S, F>(&mut self, segment: S, f: F) -> Result<T, DecodeError<R::Error>>
+    where
+        S: FnOnce() -> PathSegment,
+        F: FnOnce(&mut Self) -> Result<T, DecodeError<R::Error>>
+    {
+        self.path_depth += 1;
+        let ret = f(self);
+        self.path_depth -= 1;
+        ret.map_err(|err| err.push_segment(segment(), self.path_depth == 0))
+    }
+}
+
+/// Iterator of items in a CBOR sequence (rfc8742)
+///
+/// It deserializes items until the reader ends cleanly between items.
+/// A truncated item returns an `Eof` error, and the iteration stops after any error.
+pub struct StreamDeserializer<'de, R, T> {
+    de: Deserializer<R>,
+    done: bool,
+    _phantom: PhantomData<fn(&'de [u8]) -> T>
+}
+
+impl<'de, R: dec::Read<'de>, T> StreamDeserializer<'de, R, T> {
+    /// Returns the number of bytes consumed so far, if the reader tracks it.
+    #[inline]
+    pub fn offset(&self) -> Option<usize> {
+        self.de.offset()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.de.into_inner()
+    }
+}
+
+impl<'de, R, T> Iterator for StreamDeserializer<'de, R, T>
+where
+    R: dec::Read<'de>,
+    T: de::Deserialize<'de>
+{
+    type Item = Result<T, DecodeError<R::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.de.reader.fill(1) {
+            Ok(buf) if buf.as_ref().is_empty() => {
+                self.done = true;
+                return None;
+            },
+            Ok(_) => (),
+            Err(err) => {
+                self.done = true;
+                return Some(Err(dec::Error::Read(err).into()));
+            }
+        }
+
+        let ret = T::deserialize(&mut self.de)
+            .map_err(|err| err.with_offset(self.de.offset()));
+        self.done = ret.is_err();
+        Some(ret)
+    }
+}
+
+/// Display recorded map key or enum variant as path segment.
+#[cold]
+fn path_segment(buf: &[u8], variant: bool) -> PathSegment {
+    use crate::alloc::string::ToString;
+    use crate::core::Value;
+    use crate::core::utils::SliceReader;
+
+    let name = match Value::decode(&mut SliceReader::new(buf)) {
+        Ok(Value::Text(name)) => name.into_boxed_str(),
+        Ok(Value::Integer(name)) => name.to_string().into_boxed_str(),
+        _ => return PathSegment::Unknown
+    };
+
+    if variant {
+        PathSegment::Variant(name)
+    } else {
+        PathSegment::Key(name)
+    }
 }
 
 macro_rules! deserialize_type {
@@ -70,10 +242,25 @@ impl<'de, R: dec::Read<'de>> serde::Deserializer<'de> for &mut Deserializer<R> {
             major::MAP => de.deserialize_map(visitor),
             // NOTE: that this does not support untagged enum.
             // see https://github.com/serde-rs/serde/issues/1682
-            major::TAG => match byte {
-                0xc2 => de.deserialize_u128(visitor),
-                0xc3 => de.deserialize_i128(visitor),
-                _ => Err(dec::Error::unsupported(name, byte).into())
+            major::TAG => {
+                let tag = types::Tag::tag(&mut de.reader)?;
+
+                // bignum in range of `u128` or `i128`
+                if tag == 2 || tag == 3 {
+                    let byte = dec::peek_one(name, &mut de.reader)?;
+
+                    if dec::if_major(byte) == major::BYTES {
+                        let types::Bytes(bytes) = <types::Bytes<Cow<'de, [u8]>>>::decode(&mut de.reader)?;
+
+                        return match tag::bignum(tag, &bytes) {
+                            Some(tag::Bignum::Positive(n)) => visitor.visit_u128(n),
+                            Some(tag::Bignum::Negative(n)) => visitor.visit_i128(n),
+                            None => visitor.visit_enum(TagAccessor { de, tag: Some(tag), content: Some(bytes) })
+                        };
+                    }
+                }
+
+                visitor.visit_enum(TagAccessor { de, tag: Some(tag), content: None })
             },
             major::SIMPLE => match byte {
                 marker::FALSE => {
@@ -84,17 +271,20 @@ impl<'de, R: dec::Read<'de>> serde::Deserializer<'de> for &mut Deserializer<R> {
                     de.reader.advance(1);
                     visitor.visit_bool(true)
                 },
-                marker::NULL | marker::UNDEFINED => {
+                marker::NULL => {
                     de.reader.advance(1);
                     visitor.visit_none()
                 },
-                #[cfg(feature = "half-f16")]
-                marker::F16 => {
-                    let v = half::f16::decode(&mut de.reader)?;
-                    visitor.visit_f32(v.into())
+                marker::UNDEFINED => {
+                    de.reader.advance(1);
+                    visitor.visit_enum(simple::SimpleAccessor::new(23))
                 },
-                marker::F32 => de.deserialize_f32(visitor),
+                marker::F16 | marker::F32 => de.deserialize_f32(visitor),
                 marker::F64 => de.deserialize_f64(visitor),
+                0xe0 ..= 0xf3 | 0xf8 => {
+                    let types::Simple(v) = types::Simple::decode(&mut de.reader)?;
+                    visitor.visit_enum(simple::SimpleAccessor::new(v))
+                },
                 _ => Err(dec::Error::unsupported(name, byte).into())
             },
             _ => Err(dec::Error::unsupported(name, byte).into())
@@ -204,10 +394,22 @@ impl<'de, R: dec::Read<'de>> serde::Deserializer<'de> for &mut Deserializer<R> {
     }
 
     #[inline]
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V)
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V)
         -> Result<V::Value, Self::Error>
     where V: Visitor<'de>
     {
+        if name == raw::NAME {
+            // zero-copy if the reader lends its input
+            if let dec::Reference::Long(_) = self.reader.fill(1)? {
+                let value = crate::core::RawValue::decode(&mut self.reader)?;
+                return visitor.visit_borrowed_bytes(value.as_bytes());
+            }
+
+            let mut recorder = dec::KeyRecorder::new(&mut self.reader);
+            dec::IgnoredAny::decode(&mut recorder)?;
+            return visitor.visit_byte_buf(recorder.into_key());
+        }
+
         visitor.visit_newtype_struct(self)
     }
 
@@ -239,13 +441,26 @@ impl<'de, R: dec::Read<'de>> serde::Deserializer<'de> for &mut Deserializer<R> {
     #[inline]
     fn deserialize_tuple_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         len: usize,
         visitor: V
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>
     {
+        if name == tag::NAME && len == 2 {
+            let name = &"tag";
+            let mut de = self.try_step(name)?;
+            let byte = dec::peek_one(name, &mut de.reader)?;
+
+            return if dec::if_major(byte) == major::TAG {
+                let tag = types::Tag::tag(&mut de.reader)?;
+                visitor.visit_seq(TagAccessor { de: &mut de, tag: Some(tag), content: None })
+            } else {
+                Err(dec::Error::mismatch(name, byte).into())
+            };
+        }
+
         self.deserialize_tuple(len, visitor)
     }
 
@@ -269,6 +484,15 @@ impl<'de, R: dec::Read<'de>> serde::Deserializer<'de> for &mut Deserializer<R> {
     ) -> Result<V::Value, Self::Error>
     where V: Visitor<'de>
     {
+        if self.config.packed {
+            let byte = dec::peek_one(&"struct", &mut self.reader)?;
+
+            // packed struct
+            if dec::if_major(byte) == major::ARRAY {
+                return self.deserialize_seq(visitor);
+            }
+        }
+
         self.deserialize_map(visitor)
     }
 
@@ -293,7 +517,20 @@ impl<'de, R: dec::Read<'de>> serde::Deserializer<'de> for &mut Deserializer<R> {
         -> Result<V::Value, Self::Error>
     where V: Visitor<'de>
     {
-        self.deserialize_str(visitor)
+        use crate::alloc::string::ToString;
+
+        let byte = dec::peek_one(&"identifier", &mut self.reader)?;
+
+        match dec::if_major(byte) {
+            // integer key
+            major::UNSIGNED | major::NEGATIVE if self.config.integer_keys => {
+                let key = i64::decode(&mut self.reader)?;
+                visitor.visit_string(key.to_string())
+            },
+            // field or variant index
+            major::UNSIGNED if self.config.packed => self.deserialize_u64(visitor),
+            _ => self.deserialize_str(visitor)
+        }
     }
 
     #[inline]
@@ -313,7 +550,10 @@ impl<'de, R: dec::Read<'de>> serde::Deserializer<'de> for &mut Deserializer<R> {
 
 struct Accessor<'a, R> {
     de: &'a mut Deserializer<R>,
-    len: Option<usize>
+    len: Option<usize>,
+    order: dec::KeyOrder,
+    index: usize,
+    key: Option<Vec<u8>>
 }
 
 impl<'de, 'a, R: dec::Read<'de>> Accessor<'a, R> {
@@ -325,6 +565,9 @@ impl<'de, 'a, R: dec::Read<'de>> Accessor<'a, R> {
         Ok(Accessor {
             de,
             len,
+            order: dec::KeyOrder::new(),
+            index: 0,
+            key: None
         })
     }
 
@@ -338,6 +581,9 @@ impl<'de, 'a, R: dec::Read<'de>> Accessor<'a, R> {
             Ok(Accessor {
                 de,
                 len: array_len,
+                order: dec::KeyOrder::new(),
+                index: 0,
+                key: None
             })
         } else {
             Err(dec::Error::require_length(name, array_len))
@@ -352,10 +598,28 @@ impl<'de, 'a, R: dec::Read<'de>> Accessor<'a, R> {
         Ok(Accessor {
             de,
             len,
+            order: dec::KeyOrder::new(),
+            index: 0,
+            key: None
         })
     }
 }
 
+impl<'de, 'a, R: dec::Read<'de>> Accessor<'a, R> {
+    #[inline]
+    fn element_seed<T>(&mut self, seed: T) -> Result<T::Value, DecodeError<R::Error>>
+    where T: de::DeserializeSeed<'de>
+    {
+        if self.de.config.track_path {
+            let index = self.index;
+            self.index += 1;
+            self.de.with_segment(|| PathSegment::Index(index), |de| seed.deserialize(de))
+        } else {
+            seed.deserialize(&mut *self.de)
+        }
+    }
+}
+
 impl<'de, 'a, R> de::SeqAccess<'de> for Accessor<'a, R>
 where
     R: dec::Read<'de>
@@ -370,14 +634,15 @@ where
         if let Some(len) = self.len.as_mut() {
             if *len > 0 {
                 *len -= 1;
-                Ok(Some(seed.deserialize(&mut *self.de)?))
+                Ok(Some(self.element_seed(seed)?))
             } else {
                 Ok(None)
             }
         } else if dec::is_break(&mut self.de.reader)? {
             Ok(None)
         } else {
-            Ok(Some(seed.deserialize(&mut *self.de)?))
+            dec::check_limit(&"array", &mut self.de.reader, dec::Limit::Items(1))?;
+            Ok(Some(self.element_seed(seed)?))
         }
     }
 
@@ -387,6 +652,33 @@ where
     }
 }
 
+impl<'de, 'a, R: dec::Read<'de>> Accessor<'a, R> {
+    #[inline]
+    fn key_seed<K>(&mut self, seed: K) -> Result<K::Value, DecodeError<R::Error>>
+    where K: de::DeserializeSeed<'de>
+    {
+        let strict = self.de.reader.is_strict();
+        let track_path = self.de.config.track_path;
+
+        if !strict && !track_path {
+            return seed.deserialize(&mut *self.de);
+        }
+
+        let mut buf = Vec::new();
+        let key = self.de.record_seed(seed, &mut buf)?;
+
+        if track_path {
+            self.key = Some(buf.clone());
+        }
+
+        if strict {
+            self.order.check(&"map", buf)?;
+        }
+
+        Ok(key)
+    }
+}
+
 impl<'de, 'a, R: dec::Read<'de>> de::MapAccess<'de> for Accessor<'a, R> {
     type Error = DecodeError<R::Error>;
 
@@ -397,14 +689,15 @@ impl<'de, 'a, R: dec::Read<'de>> de::MapAccess<'de> for Accessor<'a, R> {
         if let Some(len) = self.len.as_mut() {
             if *len > 0 {
                 *len -= 1;
-                Ok(Some(seed.deserialize(&mut *self.de)?))
+                Ok(Some(self.key_seed(seed)?))
             } else {
                 Ok(None)
             }
         } else if dec::is_break(&mut self.de.reader)? {
             Ok(None)
         } else {
-            Ok(Some(seed.deserialize(&mut *self.de)?))
+            dec::check_limit(&"map", &mut self.de.reader, dec::Limit::Items(1))?;
+            Ok(Some(self.key_seed(seed)?))
         }
     }
 
@@ -412,7 +705,10 @@ impl<'de, 'a, R: dec::Read<'de>> de::MapAccess<'de> for Accessor<'a, R> {
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
     where V: de::DeserializeSeed<'de>
     {
-        seed.deserialize(&mut *self.de)
+        match self.key.take() {
+            Some(key) => self.de.with_segment(|| path_segment(&key, false), |de| seed.deserialize(de)),
+            None => seed.deserialize(&mut *self.de)
+        }
     }
 
     #[inline]
@@ -423,6 +719,7 @@ impl<'de, 'a, R: dec::Read<'de>> de::MapAccess<'de> for Accessor<'a, R> {
 
 struct EnumAccessor<'a, R> {
     de: &'a mut Deserializer<R>,
+    variant: Option<Vec<u8>>
 }
 
 impl<'de, 'a, R: dec::Read<'de>> EnumAccessor<'a, R> {
@@ -432,12 +729,12 @@ impl<'de, 'a, R: dec::Read<'de>> EnumAccessor<'a, R> {
     {
         let byte = dec::peek_one(name, &mut de.reader)?;
         match dec::if_major(byte) {
-            // string
-            major::STRING => Ok(EnumAccessor { de }),
+            // string, or index in packed mode
+            major::STRING | major::UNSIGNED => Ok(EnumAccessor { de, variant: None }),
             // 1 length map
             major::MAP if byte == (major::MAP << 5) | 1 => {
                 de.reader.advance(1);
-                Ok(EnumAccessor { de })
+                Ok(EnumAccessor { de, variant: None })
             },
             _ => Err(dec::Error::mismatch(name, byte))
         }
@@ -452,12 +749,37 @@ where
     type Variant = EnumAccessor<'a, R>;
 
     #[inline]
-    fn variant_seed<V>(self, seed: V)
+    fn variant_seed<V>(mut self, seed: V)
         -> Result<(V::Value, Self::Variant), Self::Error>
     where V: de::DeserializeSeed<'de>
     {
-        let variant = seed.deserialize(&mut *self.de)?;
-        Ok((variant, self))
+        // integer keys only apply to struct fields,
+        // an unsigned integer variant is the index in packed mode.
+        let integer_keys = core::mem::replace(&mut self.de.config.integer_keys, false);
+
+        let ret = if self.de.config.track_path {
+            let mut buf = Vec::new();
+            let ret = self.de.record_seed(seed, &mut buf);
+            self.variant = Some(buf);
+            ret
+        } else {
+            seed.deserialize(&mut *self.de)
+        };
+
+        self.de.config.integer_keys = integer_keys;
+        Ok((ret?, self))
+    }
+}
+
+impl<'de, 'a, R: dec::Read<'de>> EnumAccessor<'a, R> {
+    #[inline]
+    fn with_variant<T, F>(self, f: F) -> Result<T, DecodeError<R::Error>>
+    where F: FnOnce(&mut Deserializer<R>) -> Result<T, DecodeError<R::Error>>
+    {
+        match self.variant {
+            Some(variant) => self.de.with_segment(|| path_segment(&variant, true), f),
+            None => f(self.de)
+        }
     }
 }
 
@@ -476,7 +798,7 @@ where
     fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
     where T: de::DeserializeSeed<'de>
     {
-        seed.deserialize(&mut *self.de)
+        self.with_variant(|de| seed.deserialize(de))
     }
 
     #[inline]
@@ -486,19 +808,129 @@ where
     {
         use serde::Deserializer;
 
-        self.de.deserialize_tuple(len, visitor)
+        self.with_variant(|de| de.deserialize_tuple(len, visitor))
     }
 
     #[inline]
     fn struct_variant<V>(
         self,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V
     ) -> Result<V::Value, Self::Error>
     where V: Visitor<'de>
     {
         use serde::Deserializer;
 
-        self.de.deserialize_map(visitor)
+        self.with_variant(|de| de.deserialize_struct("", fields, visitor))
+    }
+}
+
+/// Tag number and content of tagged value.
+///
+/// It is visited as a seq by `Tagged`,
+/// and as a tuple variant named by `tag::NAME` by `deserialize_any`.
+struct TagAccessor<'a, 'de, R> {
+    de: &'a mut Deserializer<R>,
+    tag: Option<u64>,
+    /// content that has already been read, such as bignum out of range
+    content: Option<Cow<'de, [u8]>>
+}
+
+impl<'de, 'a, R> TagAccessor<'a, 'de, R>
+where
+    R: dec::Read<'de>
+{
+    #[inline]
+    fn content_seed<T>(&mut self, seed: T) -> Result<T::Value, DecodeError<R::Error>>
+    where T: de::DeserializeSeed<'de>
+    {
+        match self.content.take() {
+            Some(Cow::Borrowed(buf)) => seed.deserialize(de::value::BorrowedBytesDeserializer::new(buf)),
+            Some(Cow::Owned(buf)) => seed.deserialize(de::value::BytesDeserializer::new(&buf)),
+            None => seed.deserialize(&mut *self.de)
+        }
+    }
+}
+
+impl<'de, 'a, R> de::SeqAccess<'de> for TagAccessor<'a, 'de, R>
+where
+    R: dec::Read<'de>
+{
+    type Error = DecodeError<R::Error>;
+
+    #[inline]
+    fn next_element_seed<T>(&mut self, seed: T)
+        -> Result<Option<T::Value>, Self::Error>
+    where T: de::DeserializeSeed<'de>
+    {
+        use serde::de::IntoDeserializer;
+
+        match self.tag.take() {
+            Some(tag) => seed.deserialize(tag.into_deserializer()).map(Some),
+            None => self.content_seed(seed).map(Some)
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        Some(if self.tag.is_some() { 2 } else { 1 })
+    }
+}
+
+impl<'de, 'a, R> de::EnumAccess<'de> for TagAccessor<'a, 'de, R>
+where
+    R: dec::Read<'de>
+{
+    type Error = DecodeError<R::Error>;
+    type Variant = TagAccessor<'a, 'de, R>;
+
+    #[inline]
+    fn variant_seed<V>(self, seed: V)
+        -> Result<(V::Value, Self::Variant), Self::Error>
+    where V: de::DeserializeSeed<'de>
+    {
+        let variant = seed.deserialize(de::value::BorrowedStrDeserializer::<Self::Error>::new(tag::NAME))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, R> de::VariantAccess<'de> for TagAccessor<'a, 'de, R>
+where
+    R: dec::Read<'de>
+{
+    type Error = DecodeError<R::Error>;
+
+    #[inline]
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        use serde::de::Error;
+
+        Err(Self::Error::invalid_type(de::Unexpected::TupleVariant, &"unit variant"))
+    }
+
+    /// Content without tag number.
+    #[inline]
+    fn newtype_variant_seed<T>(mut self, seed: T) -> Result<T::Value, Self::Error>
+    where T: de::DeserializeSeed<'de>
+    {
+        self.content_seed(seed)
+    }
+
+    #[inline]
+    fn tuple_variant<V>(self, _len: usize, visitor: V)
+        -> Result<V::Value, Self::Error>
+    where V: Visitor<'de>
+    {
+        visitor.visit_seq(self)
+    }
+
+    #[inline]
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V
+    ) -> Result<V::Value, Self::Error>
+    where V: Visitor<'de>
+    {
+        visitor.visit_seq(self)
     }
 }
@@ -1,11 +1,125 @@
 use core::fmt;
+use crate::alloc::vec::Vec;
 use crate::core::{ enc, dec };
 
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum DecodeError<E> {
     Core(dec::Error<E>),
-    Custom(crate::alloc::boxed::Box<str>)
+    Custom(crate::alloc::boxed::Box<str>),
+    /// Error with the byte offset and the path at which it was detected,
+    /// use [`DecodeError::inner`] to match the error itself
+    Located {
+        offset: Option<usize>,
+        path: Option<Path>,
+        error: crate::alloc::boxed::Box<DecodeError<E>>
+    }
+}
+
+/// Path of decode error, see [`Deserializer::track_path`](crate::serde::Deserializer::track_path)
+///
+/// It is displayed like `.users[3].address.zip`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Path {
+    segments: Vec<PathSegment>
+}
+
+/// Segment of [`Path`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// Sequence index
+    Index(usize),
+    /// Map key or struct field
+    Key(crate::alloc::boxed::Box<str>),
+    /// Enum variant
+    Variant(crate::alloc::boxed::Box<str>),
+    /// Map key that cannot be displayed
+    Unknown
+}
+
+impl<E> DecodeError<E> {
+    /// Attach the byte offset to error, if it is known.
+    #[cold]
+    pub(crate) fn with_offset(self, offset: Option<usize>) -> DecodeError<E> {
+        match (self, offset) {
+            (err, None) => err,
+            (DecodeError::Located { offset: None, path, error }, offset) =>
+                DecodeError::Located { offset, path, error },
+            (err @ DecodeError::Located { .. }, _) => err,
+            (err, offset) => DecodeError::Located {
+                offset,
+                path: None,
+                error: crate::alloc::boxed::Box::new(err)
+            }
+        }
+    }
+
+    /// Push path segment to error, from the innermost one.
+    ///
+    /// The path is reversed after the outermost one is pushed.
+    #[cold]
+    pub(crate) fn push_segment(self, segment: PathSegment, outermost: bool) -> DecodeError<E> {
+        let mut err = match self {
+            err @ DecodeError::Located { .. } => err,
+            err => DecodeError::Located {
+                offset: None,
+                path: None,
+                error: crate::alloc::boxed::Box::new(err)
+            }
+        };
+
+        if let DecodeError::Located { path, .. } = &mut err {
+            let path = path.get_or_insert_with(Path::default);
+            path.segments.push(segment);
+            if outermost {
+                path.segments.reverse();
+            }
+        }
+
+        err
+    }
+
+    /// Returns the error without location.
+    pub fn inner(&self) -> &DecodeError<E> {
+        match self {
+            DecodeError::Located { error, .. } => error.inner(),
+            err => err
+        }
+    }
+
+    /// Returns the error without location.
+    pub fn into_inner(self) -> DecodeError<E> {
+        match self {
+            DecodeError::Located { error, .. } => error.into_inner(),
+            err => err
+        }
+    }
+
+    /// Returns the byte offset at which the error was detected, if known.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            DecodeError::Core(err) => err.offset(),
+            DecodeError::Custom(_) => None,
+            DecodeError::Located { offset, error, .. } => offset.or_else(|| error.offset())
+        }
+    }
+
+    /// Returns the path at which the error was detected, if tracked.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            DecodeError::Located { path: Some(path), .. } => Some(path),
+            _ => None
+        }
+    }
+}
+
+impl Path {
+    /// Returns the segments, from the outermost one.
+    #[inline]
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
 }
 
 impl<E> From<dec::Error<E>> for DecodeError<E> {
@@ -34,7 +148,38 @@ impl<E: core::error::Error + 'static> serde::de::Error for DecodeError<E> {
 
 impl<E: fmt::Debug> fmt::Display for DecodeError<E> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(self, f)
+        match self {
+            DecodeError::Located { offset, path, error } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path)?;
+                }
+                fmt::Display::fmt(error, f)?;
+                if let Some(offset) = offset {
+                    write!(f, " at offset {}", offset)?;
+                }
+                Ok(())
+            },
+            _ => fmt::Debug::fmt(self, f)
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for segment in self.segments.iter() {
+            fmt::Display::fmt(segment, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathSegment::Index(index) => write!(f, "[{}]", index),
+            PathSegment::Key(key) | PathSegment::Variant(key) => write!(f, ".{}", key),
+            PathSegment::Unknown => f.write_str(".?")
+        }
     }
 }
 
@@ -47,6 +192,17 @@ pub enum EncodeError<E> {
     Custom(crate::alloc::boxed::Box<str>)
 }
 
+impl EncodeError<core::convert::Infallible> {
+    /// Convert an error from an infallible writer into any error type.
+    #[inline]
+    pub(crate) fn cast<E>(self) -> EncodeError<E> {
+        match self {
+            EncodeError::Core(err) => EncodeError::Core(err.cast()),
+            EncodeError::Custom(msg) => EncodeError::Custom(msg)
+        }
+    }
+}
+
 impl<E> From<enc::Error<E>> for EncodeError<E> {
     #[inline]
     #[cold]
@@ -0,0 +1,125 @@
+use core::fmt;
+use serde::{ Serialize, Deserialize };
+use serde::de::{ self, Visitor };
+use crate::alloc::vec::Vec;
+use crate::core::{ RawValue, BoxedRawValue };
+use crate::core::dec::Decode;
+use crate::core::utils::SliceReader;
+
+
+/// Magic name recognized by serializer and deserializer.
+///
+/// A raw value is a newtype struct of this name, with the encoded item as bytes.
+pub(crate) const NAME: &str = "@@cbor4ii::raw@@";
+
+/// Encoded item as bytes
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    #[inline]
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Check that the bytes are exactly one well-formed item.
+fn check<E: de::Error>(buf: &[u8]) -> Result<(), E> {
+    let mut reader = SliceReader::new(buf);
+    let value = RawValue::decode(&mut reader).map_err(E::custom)?;
+
+    if value.0.len() == buf.len() {
+        Ok(())
+    } else {
+        Err(E::invalid_length(buf.len(), &"one item"))
+    }
+}
+
+/// Raw value is written as is by `cbor4ii` serializer,
+/// and as a byte string by other serde formats.
+impl Serialize for RawValue<'_> {
+    #[inline]
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(NAME, &Bytes(self.0))
+    }
+}
+
+impl Serialize for BoxedRawValue {
+    #[inline]
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(NAME, &Bytes(&self.0))
+    }
+}
+
+/// Raw value is captured zero-copy, so this requires a reader that can lend the item,
+/// such as `SliceReader`. Use `BoxedRawValue` for other readers.
+impl<'de: 'a, 'a> Deserialize<'de> for RawValue<'a> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RawValueVisitor;
+
+        impl<'de> Visitor<'de> for RawValueVisitor {
+            type Value = RawValue<'de>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("borrowed raw value")
+            }
+
+            // other formats wrap the bytes in a newtype struct
+            #[inline]
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where D: serde::Deserializer<'de>
+            {
+                deserializer.deserialize_bytes(self)
+            }
+
+            #[inline]
+            fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+            where E: de::Error
+            {
+                check(v)?;
+                Ok(RawValue(v))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(NAME, RawValueVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for BoxedRawValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BoxedRawValueVisitor;
+
+        impl<'de> Visitor<'de> for BoxedRawValueVisitor {
+            type Value = BoxedRawValue;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("raw value")
+            }
+
+            // other formats wrap the bytes in a newtype struct
+            #[inline]
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where D: serde::Deserializer<'de>
+            {
+                deserializer.deserialize_bytes(self)
+            }
+
+            #[inline]
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where E: de::Error
+            {
+                check(v)?;
+                Ok(BoxedRawValue(v.into()))
+            }
+
+            #[inline]
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+            where E: de::Error
+            {
+                check(&v)?;
+                Ok(BoxedRawValue(v.into_boxed_slice()))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(NAME, BoxedRawValueVisitor)
+    }
+}
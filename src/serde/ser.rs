@@ -1,17 +1,80 @@
 use core::fmt;
 use serde::Serialize;
+use crate::alloc::vec::Vec;
 use crate::core::types;
 use crate::core::enc::{ self, Encode };
+use crate::core::utils::VecWriter;
 use crate::serde::error::EncodeError;
+use crate::serde::{ tag, raw };
 
 
 pub struct Serializer<W> {
-    writer: W
+    writer: W,
+    config: Config,
+    /// the next `u64` is a tag number
+    tag: bool,
+    /// the next bytes are an encoded item
+    raw: bool
+}
+
+#[derive(Clone, Copy, Default)]
+struct Config {
+    deterministic: bool,
+    packed: bool,
+    integer_keys: bool,
+    preferred_float: bool
 }
 
 impl<W> Serializer<W> {
     pub fn new(writer: W) -> Serializer<W> {
-        Serializer { writer }
+        Serializer { writer, config: Config::default(), tag: false, raw: false }
+    }
+
+    /// Use deterministic encoding, see rfc8949 section 4.2.
+    ///
+    /// Map and struct keys are sorted bytewise by their encoded form,
+    /// duplicate keys are rejected, indefinite lengths are never used,
+    /// and floats use preferred serialization.
+    /// Items whose length is not known in advance are buffered in memory,
+    /// and raw values are decoded and encoded again.
+    pub fn deterministic(mut self) -> Serializer<W> {
+        self.config.deterministic = true;
+        self
+    }
+
+    /// Use packed encoding.
+    ///
+    /// Structs are encoded as definite length arrays of their fields in declaration order,
+    /// and enum variants are identified by their index instead of name.
+    /// This is more compact, but the data can only be decoded by the same type definition.
+    ///
+    /// Skipped fields shorten the array, so they must be at the end of struct
+    /// and have a default value when decoding.
+    /// Use [`Deserializer::packed`](crate::serde::Deserializer::packed) to decode it.
+    pub fn packed(mut self) -> Serializer<W> {
+        self.config.packed = true;
+        self
+    }
+
+    /// Encode struct field names that are decimal integers, such as `"1"` or `"-2"`,
+    /// as integer keys.
+    ///
+    /// This allows structs to map to integer keyed maps, as used by COSE and CTAP2,
+    /// by renaming fields, e.g. `#[serde(rename = "1")]`.
+    /// Use [`Deserializer::integer_keys`](crate::serde::Deserializer::integer_keys) to decode them.
+    pub fn integer_keys(mut self) -> Serializer<W> {
+        self.config.integer_keys = true;
+        self
+    }
+
+    /// Encode floats in the shortest width that preserves their value,
+    /// see rfc8949 section 4.2.2.
+    ///
+    /// For example `1.5f64` is encoded as half precision float.
+    /// This is always enabled by deterministic encoding.
+    pub fn preferred_float(mut self) -> Serializer<W> {
+        self.config.preferred_float = true;
+        self
     }
 
     pub fn into_inner(self) -> W {
@@ -19,6 +82,37 @@ impl<W> Serializer<W> {
     }
 }
 
+impl<W: enc::Write> Serializer<W> {
+    /// Serialize value to the end of buffer with the same configuration.
+    #[inline]
+    fn buffer<T: Serialize + ?Sized>(&self, buf: &mut Vec<u8>, value: &T)
+        -> Result<(), EncodeError<W::Error>>
+    {
+        let mut ser = Serializer {
+            writer: VecWriter(core::mem::take(buf)),
+            config: self.config,
+            tag: false,
+            raw: false
+        };
+        let ret = value.serialize(&mut ser);
+        *buf = ser.writer.0;
+        ret.map_err(EncodeError::cast)
+    }
+
+    /// Encode enum variant as name, or index in packed mode.
+    #[inline]
+    fn variant(&mut self, index: u32, name: &'static str)
+        -> Result<(), EncodeError<W::Error>>
+    {
+        if self.config.packed {
+            index.encode(&mut self.writer)?;
+        } else {
+            name.encode(&mut self.writer)?;
+        }
+        Ok(())
+    }
+}
+
 impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
     type Ok = ();
     type Error = EncodeError<W::Error>;
@@ -28,8 +122,8 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
     type SerializeTupleStruct = BoundedCollect<'a, W>;
     type SerializeTupleVariant = BoundedCollect<'a, W>;
     type SerializeMap = Collect<'a, W>;
-    type SerializeStruct = BoundedCollect<'a, W>;
-    type SerializeStructVariant = BoundedCollect<'a, W>;
+    type SerializeStruct = Collect<'a, W>;
+    type SerializeStructVariant = Collect<'a, W>;
 
     #[inline]
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
@@ -81,19 +175,31 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
 
     #[inline]
     fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-        v.encode(&mut self.writer)?;
+        if core::mem::take(&mut self.tag) {
+            types::Tag(v, types::Nothing).encode(&mut self.writer)?;
+        } else {
+            v.encode(&mut self.writer)?;
+        }
         Ok(())
     }
 
     #[inline]
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
-        v.encode(&mut self.writer)?;
+        if self.config.preferred_float || self.config.deterministic {
+            types::Preferred(v).encode(&mut self.writer)?;
+        } else {
+            v.encode(&mut self.writer)?;
+        }
         Ok(())
     }
 
     #[inline]
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-        v.encode(&mut self.writer)?;
+        if self.config.preferred_float || self.config.deterministic {
+            types::Preferred(v).encode(&mut self.writer)?;
+        } else {
+            v.encode(&mut self.writer)?;
+        }
         Ok(())
     }
 
@@ -111,7 +217,24 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
 
     #[inline]
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        types::Bytes(v).encode(&mut self.writer)?;
+        if core::mem::take(&mut self.raw) {
+            if self.config.deterministic {
+                use serde::ser::Error;
+                use crate::core::dec::{ self, Decode };
+                use crate::core::utils::SliceReader;
+
+                // the raw item may not be canonical, so decode and encode it again
+                let mut reader = SliceReader::new(v);
+                let value = crate::core::Value::decode(&mut reader)
+                    .and_then(|value| dec::end(&mut reader).map(|_| value))
+                    .map_err(Self::Error::custom)?;
+                types::Canonical(&value).encode(&mut self.writer)?;
+            } else {
+                self.writer.push(v)?;
+            }
+        } else {
+            types::Bytes(v).encode(&mut self.writer)?;
+        }
         Ok(())
     }
 
@@ -145,18 +268,31 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
     fn serialize_unit_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
+        variant_index: u32,
         variant: &'static str
     ) -> Result<Self::Ok, Self::Error> {
-        self.serialize_str(variant)
+        self.variant(variant_index, variant)
     }
 
     #[inline]
     fn serialize_newtype_struct<T: Serialize + ?Sized>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T
     ) -> Result<Self::Ok, Self::Error> {
+        if name == raw::NAME {
+            self.raw = true;
+            value.serialize(&mut *self)?;
+
+            if core::mem::take(&mut self.raw) {
+                use serde::ser::Error;
+
+                return Err(Self::Error::custom("raw value must be bytes"));
+            }
+
+            return Ok(());
+        }
+
         value.serialize(self)
     }
 
@@ -164,12 +300,12 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
     fn serialize_newtype_variant<T: Serialize + ?Sized>(
         self,
         _name: &'static str,
-        _variant_index: u32,
+        variant_index: u32,
         variant: &'static str,
         value: &T
     ) -> Result<Self::Ok, Self::Error> {
         types::Map::bounded(1, &mut self.writer)?;
-        variant.encode(&mut self.writer)?;
+        self.variant(variant_index, variant)?;
         value.serialize(self)
     }
 
@@ -179,12 +315,19 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
     {
         if let Some(len) = len {
             types::Array::bounded(len, &mut self.writer)?;
+        } else if self.config.deterministic {
+            return Ok(Collect {
+                bounded: true,
+                ser: self,
+                buf: Some(Buffer::Seq(0, Vec::new()))
+            });
         } else {
             types::Array::unbounded(&mut self.writer)?;
         }
         Ok(Collect {
             bounded: len.is_some(),
-            ser: self
+            ser: self,
+            buf: None
         })
     }
 
@@ -193,13 +336,17 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
         -> Result<Self::SerializeTuple, Self::Error>
     {
         types::Array::bounded(len, &mut self.writer)?;
-        Ok(BoundedCollect { ser: self })
+        Ok(BoundedCollect { ser: self, tag: false })
     }
 
     #[inline]
-    fn serialize_tuple_struct(self, _name: &'static str, len: usize)
+    fn serialize_tuple_struct(self, name: &'static str, len: usize)
         -> Result<Self::SerializeTupleStruct, Self::Error>
     {
+        if name == tag::NAME && len == 2 {
+            return Ok(BoundedCollect { ser: self, tag: true });
+        }
+
         self.serialize_tuple(len)
     }
 
@@ -207,20 +354,28 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
+        variant_index: u32,
         variant: &'static str,
         len: usize
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
         types::Map::bounded(1, &mut self.writer)?;
-        variant.encode(&mut self.writer)?;
+        self.variant(variant_index, variant)?;
         types::Array::bounded(len, &mut self.writer)?;
-        Ok(BoundedCollect { ser: self })
+        Ok(BoundedCollect { ser: self, tag: false })
     }
 
     #[inline]
     fn serialize_map(self, len: Option<usize>)
         -> Result<Self::SerializeMap, Self::Error>
     {
+        if self.config.deterministic {
+            return Ok(Collect {
+                bounded: true,
+                ser: self,
+                buf: Some(Buffer::Map(Vec::with_capacity(len.unwrap_or(0))))
+            });
+        }
+
         if let Some(len) = len {
             types::Map::bounded(len, &mut self.writer)?;
         } else {
@@ -228,7 +383,8 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
         }
         Ok(Collect {
             bounded: len.is_some(),
-            ser: self
+            ser: self,
+            buf: None
         })
     }
 
@@ -236,22 +392,29 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
     fn serialize_struct(self, _name: &'static str, len: usize)
         -> Result<Self::SerializeStruct, Self::Error>
     {
-        types::Map::bounded(len, &mut self.writer)?;
-        Ok(BoundedCollect { ser: self })
+        if self.config.packed {
+            types::Array::bounded(len, &mut self.writer)?;
+            return Ok(Collect {
+                bounded: true,
+                ser: self,
+                buf: None
+            });
+        }
+
+        self.serialize_map(Some(len))
     }
 
     #[inline]
     fn serialize_struct_variant(
         self,
-        _name: &'static str,
-        _variant_index: u32,
+        name: &'static str,
+        variant_index: u32,
         variant: &'static str,
         len: usize
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
         types::Map::bounded(1, &mut self.writer)?;
-        variant.encode(&mut self.writer)?;
-        types::Map::bounded(len, &mut self.writer)?;
-        Ok(BoundedCollect { ser: self })
+        self.variant(variant_index, variant)?;
+        self.serialize_struct(name, len)
     }
 
     #[inline]
@@ -271,7 +434,17 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
     where
         T: fmt::Display + ?Sized,
     {
-        collect_str(&mut self.writer, &value)
+        if self.config.deterministic {
+            use core::fmt::Write;
+            use serde::ser::Error;
+
+            // a long string would be split into indefinite length segments
+            let mut buf = crate::alloc::string::String::new();
+            write!(&mut buf, "{}", value).map_err(Self::Error::custom)?;
+            self.serialize_str(&buf)
+        } else {
+            collect_str(&mut self.writer, &value)
+        }
     }
 
     #[inline]
@@ -282,11 +455,22 @@ impl<'a, W: enc::Write> serde::Serializer for &'a mut Serializer<W> {
 
 pub struct Collect<'a, W> {
     bounded: bool,
-    ser: &'a mut Serializer<W>
+    ser: &'a mut Serializer<W>,
+    buf: Option<Buffer>
+}
+
+/// Items buffered by deterministic mode.
+enum Buffer {
+    /// Number of items and encoded items
+    Seq(usize, Vec<u8>),
+    /// Encoded keys and values
+    Map(Vec<(Vec<u8>, Vec<u8>)>)
 }
 
 pub struct BoundedCollect<'a, W> {
-    ser: &'a mut Serializer<W>
+    ser: &'a mut Serializer<W>,
+    /// the next field is a tag number
+    tag: bool
 }
 
 impl<W: enc::Write> serde::ser::SerializeSeq for Collect<'_, W> {
@@ -297,12 +481,21 @@ impl<W: enc::Write> serde::ser::SerializeSeq for Collect<'_, W> {
     fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T)
         -> Result<(), Self::Error>
     {
-        value.serialize(&mut *self.ser)
+        if let Some(Buffer::Seq(len, buf)) = self.buf.as_mut() {
+            self.ser.buffer(buf, value)?;
+            *len += 1;
+            Ok(())
+        } else {
+            value.serialize(&mut *self.ser)
+        }
     }
 
     #[inline]
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        if !self.bounded {
+        if let Some(Buffer::Seq(len, buf)) = self.buf {
+            types::Array::bounded(len, &mut self.ser.writer)?;
+            self.ser.writer.push(&buf)?;
+        } else if !self.bounded {
             types::Array::end(&mut self.ser.writer)?;
         }
 
@@ -335,7 +528,20 @@ impl<W: enc::Write> serde::ser::SerializeTupleStruct for BoundedCollect<'_, W> {
     fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T)
         -> Result<(), Self::Error>
     {
-        value.serialize(&mut *self.ser)
+        if !core::mem::take(&mut self.tag) {
+            return value.serialize(&mut *self.ser);
+        }
+
+        self.ser.tag = true;
+        value.serialize(&mut *self.ser)?;
+
+        if core::mem::take(&mut self.ser.tag) {
+            use serde::ser::Error;
+
+            Err(Self::Error::custom("tag number must be u64"))
+        } else {
+            Ok(())
+        }
     }
 
     #[inline]
@@ -369,19 +575,42 @@ impl<W: enc::Write> serde::ser::SerializeMap for Collect<'_, W> {
     fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T)
         -> Result<(), Self::Error>
     {
-        key.serialize(&mut *self.ser)
+        if let Some(Buffer::Map(entries)) = self.buf.as_mut() {
+            let mut buf = Vec::new();
+            self.ser.buffer(&mut buf, key)?;
+            entries.push((buf, Vec::new()));
+            Ok(())
+        } else {
+            key.serialize(&mut *self.ser)
+        }
     }
 
     #[inline]
     fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T)
         -> Result<(), Self::Error>
     {
-        value.serialize(&mut *self.ser)
+        if let Some(Buffer::Map(entries)) = self.buf.as_mut() {
+            use serde::ser::Error;
+
+            let (_, buf) = entries.last_mut()
+                .ok_or_else(|| Self::Error::custom("serialize value before key"))?;
+            self.ser.buffer(buf, value)
+        } else {
+            value.serialize(&mut *self.ser)
+        }
     }
 
     #[inline]
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        if !self.bounded {
+        if let Some(Buffer::Map(mut entries)) = self.buf {
+            enc::sort_keys(&mut entries)?;
+
+            types::Map::bounded(entries.len(), &mut self.ser.writer)?;
+            for (k, v) in entries.iter() {
+                self.ser.writer.push(k)?;
+                self.ser.writer.push(v)?;
+            }
+        } else if !self.bounded {
             types::Map::end(&mut self.ser.writer)?;
         }
 
@@ -389,7 +618,7 @@ impl<W: enc::Write> serde::ser::SerializeMap for Collect<'_, W> {
     }
 }
 
-impl<W: enc::Write> serde::ser::SerializeStruct for BoundedCollect<'_, W> {
+impl<W: enc::Write> serde::ser::SerializeStruct for Collect<'_, W> {
     type Ok = ();
     type Error = EncodeError<W::Error>;
 
@@ -397,17 +626,24 @@ impl<W: enc::Write> serde::ser::SerializeStruct for BoundedCollect<'_, W> {
     fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
         -> Result<(), Self::Error>
     {
-        key.serialize(&mut *self.ser)?;
-        value.serialize(&mut *self.ser)
+        if self.ser.config.packed {
+            value.serialize(&mut *self.ser)
+        } else {
+            self.field(key, value)
+        }
     }
 
     #[inline]
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        if self.ser.config.packed {
+            Ok(())
+        } else {
+            serde::ser::SerializeMap::end(self)
+        }
     }
 }
 
-impl<W: enc::Write> serde::ser::SerializeStructVariant for BoundedCollect<'_, W> {
+impl<W: enc::Write> serde::ser::SerializeStructVariant for Collect<'_, W> {
     type Ok = ();
     type Error = EncodeError<W::Error>;
 
@@ -415,13 +651,52 @@ impl<W: enc::Write> serde::ser::SerializeStructVariant for BoundedCollect<'_, W>
     fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
         -> Result<(), Self::Error>
     {
-        key.serialize(&mut *self.ser)?;
-        value.serialize(&mut *self.ser)
+        if self.ser.config.packed {
+            value.serialize(&mut *self.ser)
+        } else {
+            self.field(key, value)
+        }
     }
 
     #[inline]
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        if self.ser.config.packed {
+            Ok(())
+        } else {
+            serde::ser::SerializeMap::end(self)
+        }
+    }
+}
+
+impl<W: enc::Write> Collect<'_, W> {
+    #[inline]
+    fn field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
+        -> Result<(), EncodeError<W::Error>>
+    {
+        use serde::ser::SerializeMap;
+
+        match integer_key(key) {
+            Some(key) if self.ser.config.integer_keys => self.serialize_entry(&key, value),
+            _ => self.serialize_entry(key, value)
+        }
+    }
+}
+
+/// Parse the field name as a decimal integer in its canonical form.
+fn integer_key(key: &str) -> Option<i64> {
+    let digits = key.strip_prefix('-').unwrap_or(key);
+
+    let canonical = match digits.as_bytes() {
+        [] => false,
+        [b'0'] => digits.len() == key.len(),
+        [b'0', ..] => false,
+        bytes => bytes.iter().all(u8::is_ascii_digit)
+    };
+
+    if canonical {
+        key.parse().ok()
+    } else {
+        None
     }
 }
 
@@ -0,0 +1,80 @@
+use core::marker::PhantomData;
+use serde::Serialize;
+use serde::de::{ self, Visitor };
+use crate::core::RawValue;
+
+
+/// Magic name recognized by deserializer.
+///
+/// A simple value other than `false`, `true`, `null` and floats
+/// is visited by `deserialize_any` as a newtype variant of this name, with the number as content.
+pub(crate) const NAME: &str = "@@cbor4ii::simple@@";
+
+/// Serialize simple value as raw value,
+/// so that it is kept as is by `cbor4ii` serializer.
+pub(crate) fn serialize<S: serde::Serializer>(value: u8, serializer: S) -> Result<S::Ok, S::Error> {
+    let buf = [0xe0 | value, value];
+
+    if value < 24 {
+        RawValue(&buf[..1]).serialize(serializer)
+    } else {
+        RawValue(&[0xf8, value]).serialize(serializer)
+    }
+}
+
+/// Simple value as enum, see `NAME`.
+pub(crate) struct SimpleAccessor<E> {
+    value: u8,
+    _phantom: PhantomData<E>
+}
+
+impl<E> SimpleAccessor<E> {
+    #[inline]
+    pub(crate) fn new(value: u8) -> SimpleAccessor<E> {
+        SimpleAccessor { value, _phantom: PhantomData }
+    }
+}
+
+impl<'de, E: de::Error> de::EnumAccess<'de> for SimpleAccessor<E> {
+    type Error = E;
+    type Variant = Self;
+
+    #[inline]
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where V: de::DeserializeSeed<'de>
+    {
+        let variant = seed.deserialize(de::value::BorrowedStrDeserializer::<E>::new(NAME))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, E: de::Error> de::VariantAccess<'de> for SimpleAccessor<E> {
+    type Error = E;
+
+    #[inline]
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(E::invalid_type(de::Unexpected::NewtypeVariant, &"unit variant"))
+    }
+
+    #[inline]
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where T: de::DeserializeSeed<'de>
+    {
+        seed.deserialize(de::value::U8Deserializer::<E>::new(self.value))
+    }
+
+    #[inline]
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
+    where V: Visitor<'de>
+    {
+        Err(E::invalid_type(de::Unexpected::NewtypeVariant, &"tuple variant"))
+    }
+
+    #[inline]
+    fn struct_variant<V>(self, _fields: &'static [&'static str], _visitor: V)
+        -> Result<V::Value, Self::Error>
+    where V: Visitor<'de>
+    {
+        Err(E::invalid_type(de::Unexpected::NewtypeVariant, &"struct variant"))
+    }
+}
@@ -0,0 +1,112 @@
+use core::fmt;
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use serde::{ Serialize, Deserialize };
+use serde::de::{ self, Visitor };
+use serde::ser::SerializeTupleStruct;
+
+
+/// Magic name recognized by serializer and deserializer.
+///
+/// A tagged value is a tuple struct of this name,
+/// with the tag number and the content as fields.
+pub(crate) const NAME: &str = "@@cbor4ii::tag@@";
+
+/// Tagged value
+///
+/// This is encoded as CBOR tag by `cbor4ii` serializer,
+/// and as a two element tuple by other serde formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tagged<T>(pub u64, pub T);
+
+/// Value with a fixed tag
+///
+/// Decoding fails if the tag is different.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedTag<const TAG: u64, T>(pub T);
+
+impl<T: Serialize> Serialize for Tagged<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple_struct(NAME, 2)?;
+        tuple.serialize_field(&self.0)?;
+        tuple.serialize_field(&self.1)?;
+        tuple.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Tagged<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TaggedVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for TaggedVisitor<T> {
+            type Value = Tagged<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("tagged value")
+            }
+
+            #[inline]
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where A: de::SeqAccess<'de>
+            {
+                let tag = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let value = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                Ok(Tagged(tag, value))
+            }
+        }
+
+        deserializer.deserialize_tuple_struct(NAME, 2, TaggedVisitor(PhantomData))
+    }
+}
+
+impl<const TAG: u64, T: Serialize> Serialize for FixedTag<TAG, T> {
+    #[inline]
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Tagged(TAG, &self.0).serialize(serializer)
+    }
+}
+
+impl<'de, const TAG: u64, T: Deserialize<'de>> Deserialize<'de> for FixedTag<TAG, T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let Tagged(tag, value) = Tagged::deserialize(deserializer)?;
+
+        if tag == TAG {
+            Ok(FixedTag(value))
+        } else {
+            Err(D::Error::invalid_value(de::Unexpected::Unsigned(tag), &Expected(TAG)))
+        }
+    }
+}
+
+struct Expected(u64);
+
+impl de::Expected for Expected {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "tag {}", self.0)
+    }
+}
+
+pub(crate) enum Bignum {
+    Positive(u128),
+    Negative(i128)
+}
+
+/// Decode bignum of tag 2 and 3,
+/// if it is in range of `u128` or `i128`.
+#[inline]
+pub(crate) fn bignum(tag: u64, bytes: &[u8]) -> Option<Bignum> {
+    let mut buf = [0; 16];
+    let pos = buf.len().checked_sub(bytes.len())?;
+    buf[pos..].copy_from_slice(bytes);
+    let n = u128::from_be_bytes(buf);
+
+    match tag {
+        2 => Some(Bignum::Positive(n)),
+        3 => i128::try_from(n).ok().map(|n| Bignum::Negative(-1 - n)),
+        _ => None
+    }
+}
@@ -0,0 +1,832 @@
+use core::borrow::Borrow;
+use core::convert::{ TryFrom, Infallible };
+use serde::Serialize;
+use serde::de::{ self, Visitor, IntoDeserializer };
+use serde::de::value::{ SeqDeserializer, MapDeserializer };
+use crate::alloc::{ vec::Vec, boxed::Box };
+use crate::core::Value;
+use crate::serde::error::{ EncodeError, DecodeError };
+use crate::serde::{ tag, raw, simple };
+
+
+/// Serializes a value to [`Value`].
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, EncodeError<Infallible>> {
+    value.serialize(ValueSerializer)
+}
+
+/// Deserializes a value from [`Value`].
+pub fn from_value<'de, T: de::Deserialize<'de>>(value: Value) -> Result<T, DecodeError<Infallible>> {
+    T::deserialize(value)
+}
+
+/// Serializer that produces [`Value`]
+///
+/// The result is the same as decoding the output of [`Serializer`](crate::serde::Serializer),
+/// except that integers are never converted to bignum tags unless they are out of range of `i128`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValueSerializer;
+
+impl serde::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = EncodeError<Infallible>;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    #[inline]
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Bool(v))
+    }
+
+    #[inline]
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    #[inline]
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    #[inline]
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    #[inline]
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    #[inline]
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Integer(v))
+    }
+
+    #[inline]
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    #[inline]
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    #[inline]
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    #[inline]
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    #[inline]
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        match i128::try_from(v) {
+            Ok(v) => Ok(Value::Integer(v)),
+            Err(_) => {
+                let bytes = v.to_be_bytes().to_vec();
+                Ok(Value::Tag(2, Box::new(Value::Bytes(bytes))))
+            }
+        }
+    }
+
+    #[inline]
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Float(v.into()))
+    }
+
+    #[inline]
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Float(v))
+    }
+
+    #[inline]
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Text(v.into()))
+    }
+
+    #[inline]
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Text(v.into()))
+    }
+
+    #[inline]
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Bytes(v.into()))
+    }
+
+    #[inline]
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Null)
+    }
+
+    #[inline]
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T)
+        -> Result<Self::Ok, Self::Error>
+    {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Array(Vec::new()))
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, _name: &'static str)
+        -> Result<Self::Ok, Self::Error>
+    {
+        self.serialize_unit()
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str
+    ) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(variant)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T
+    ) -> Result<Self::Ok, Self::Error> {
+        use serde::ser::Error;
+        use crate::core::dec::Decode;
+        use crate::core::utils::SliceReader;
+
+        if name != raw::NAME {
+            return value.serialize(self);
+        }
+
+        match value.serialize(self)? {
+            Value::Bytes(buf) => Value::decode(&mut SliceReader::new(&buf))
+                .map_err(Self::Error::custom),
+            _ => Err(Self::Error::custom("raw value must be bytes"))
+        }
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T
+    ) -> Result<Self::Ok, Self::Error> {
+        let value = value.serialize(self)?;
+        Ok(Value::Map(crate::alloc::vec![(Value::Text(variant.into()), value)]))
+    }
+
+    #[inline]
+    fn serialize_seq(self, len: Option<usize>)
+        -> Result<Self::SerializeSeq, Self::Error>
+    {
+        Ok(SerializeArray {
+            list: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+            tag: false
+        })
+    }
+
+    #[inline]
+    fn serialize_tuple(self, len: usize)
+        -> Result<Self::SerializeTuple, Self::Error>
+    {
+        self.serialize_seq(Some(len))
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(self, name: &'static str, len: usize)
+        -> Result<Self::SerializeTupleStruct, Self::Error>
+    {
+        let mut seq = self.serialize_seq(Some(len))?;
+        seq.tag = name == tag::NAME && len == 2;
+        Ok(seq)
+    }
+
+    #[inline]
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        let mut seq = self.serialize_seq(Some(len))?;
+        seq.variant = Some(variant);
+        Ok(seq)
+    }
+
+    #[inline]
+    fn serialize_map(self, len: Option<usize>)
+        -> Result<Self::SerializeMap, Self::Error>
+    {
+        Ok(SerializeMap {
+            map: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+            variant: None
+        })
+    }
+
+    #[inline]
+    fn serialize_struct(self, _name: &'static str, len: usize)
+        -> Result<Self::SerializeStruct, Self::Error>
+    {
+        self.serialize_map(Some(len))
+    }
+
+    #[inline]
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        let mut map = self.serialize_map(Some(len))?;
+        map.variant = Some(variant);
+        Ok(map)
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+pub struct SerializeArray {
+    list: Vec<Value>,
+    variant: Option<&'static str>,
+    /// tag number and content of tagged value
+    tag: bool
+}
+
+pub struct SerializeMap {
+    map: Vec<(Value, Value)>,
+    key: Option<Value>,
+    variant: Option<&'static str>
+}
+
+/// Wrap value in a single entry map keyed by enum variant.
+#[inline]
+fn with_variant(variant: Option<&'static str>, value: Value) -> Value {
+    match variant {
+        Some(variant) => Value::Map(crate::alloc::vec![(Value::Text(variant.into()), value)]),
+        None => value
+    }
+}
+
+impl SerializeArray {
+    #[inline]
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError<Infallible>> {
+        self.list.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    #[inline]
+    fn finish(self) -> Result<Value, EncodeError<Infallible>> {
+        if !self.tag {
+            return Ok(with_variant(self.variant, Value::Array(self.list)));
+        }
+
+        let mut iter = self.list.into_iter();
+        match (iter.next(), iter.next()) {
+            (Some(Value::Integer(tag)), Some(value)) => match u64::try_from(tag) {
+                Ok(tag) => Ok(Value::Tag(tag, Box::new(value))),
+                Err(_) => Err(serde::ser::Error::custom("tag number must be u64"))
+            },
+            _ => Err(serde::ser::Error::custom("tag number must be u64"))
+        }
+    }
+}
+
+impl serde::ser::SerializeSeq for SerializeArray {
+    type Ok = Value;
+    type Error = EncodeError<Infallible>;
+
+    #[inline]
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T)
+        -> Result<(), Self::Error>
+    {
+        self.push(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl serde::ser::SerializeTuple for SerializeArray {
+    type Ok = Value;
+    type Error = EncodeError<Infallible>;
+
+    #[inline]
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T)
+        -> Result<(), Self::Error>
+    {
+        self.push(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl serde::ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Value;
+    type Error = EncodeError<Infallible>;
+
+    #[inline]
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T)
+        -> Result<(), Self::Error>
+    {
+        self.push(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl serde::ser::SerializeTupleVariant for SerializeArray {
+    type Ok = Value;
+    type Error = EncodeError<Infallible>;
+
+    #[inline]
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T)
+        -> Result<(), Self::Error>
+    {
+        self.push(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl serde::ser::SerializeMap for SerializeMap {
+    type Ok = Value;
+    type Error = EncodeError<Infallible>;
+
+    #[inline]
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T)
+        -> Result<(), Self::Error>
+    {
+        self.key = Some(key.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T)
+        -> Result<(), Self::Error>
+    {
+        use serde::ser::Error;
+
+        let key = self.key.take()
+            .ok_or_else(|| Self::Error::custom("serialize value before key"))?;
+        self.map.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(with_variant(self.variant, Value::Map(self.map)))
+    }
+}
+
+impl serde::ser::SerializeStruct for SerializeMap {
+    type Ok = Value;
+    type Error = EncodeError<Infallible>;
+
+    #[inline]
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
+        -> Result<(), Self::Error>
+    {
+        serde::ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        serde::ser::SerializeMap::end(self)
+    }
+}
+
+impl serde::ser::SerializeStructVariant for SerializeMap {
+    type Ok = Value;
+    type Error = EncodeError<Infallible>;
+
+    #[inline]
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
+        -> Result<(), Self::Error>
+    {
+        serde::ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        serde::ser::SerializeMap::end(self)
+    }
+}
+
+#[inline]
+fn visit_integer<'de, V: Visitor<'de>>(visitor: V, v: i128)
+    -> Result<V::Value, DecodeError<Infallible>>
+{
+    if let Ok(v) = u64::try_from(v) {
+        visitor.visit_u64(v)
+    } else if let Ok(v) = i64::try_from(v) {
+        visitor.visit_i64(v)
+    } else if v > 0 {
+        visitor.visit_u128(v as u128)
+    } else {
+        visitor.visit_i128(v)
+    }
+}
+
+#[inline]
+fn copy<T: Copy>(v: impl Borrow<T>) -> T {
+    *v.borrow()
+}
+
+/// Visit content of seq and check that it is exhausted.
+#[inline]
+fn visit_array<'de, V, I>(visitor: V, iter: I) -> Result<V::Value, DecodeError<Infallible>>
+where
+    V: Visitor<'de>,
+    I: Iterator,
+    I::Item: IntoDeserializer<'de, DecodeError<Infallible>>
+{
+    let mut seq = SeqDeserializer::new(iter);
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+/// Visit content of map and check that it is exhausted.
+#[inline]
+fn visit_map<'de, V, I, K, T>(visitor: V, iter: I) -> Result<V::Value, DecodeError<Infallible>>
+where
+    V: Visitor<'de>,
+    I: Iterator<Item = (K, T)>,
+    K: IntoDeserializer<'de, DecodeError<Infallible>>,
+    T: IntoDeserializer<'de, DecodeError<Infallible>>
+{
+    let mut map = MapDeserializer::new(iter);
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+/// Tag number and content of tagged value, see `de::TagAccessor`.
+struct TagDeserializer<T> {
+    tag: Option<u64>,
+    value: Option<T>
+}
+
+impl<'de, T> de::SeqAccess<'de> for TagDeserializer<T>
+where
+    T: IntoDeserializer<'de, DecodeError<Infallible>>
+{
+    type Error = DecodeError<Infallible>;
+
+    #[inline]
+    fn next_element_seed<S>(&mut self, seed: S) -> Result<Option<S::Value>, Self::Error>
+    where S: de::DeserializeSeed<'de>
+    {
+        if let Some(tag) = self.tag.take() {
+            seed.deserialize(tag.into_deserializer()).map(Some)
+        } else if let Some(value) = self.value.take() {
+            seed.deserialize(value.into_deserializer()).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        Some(usize::from(self.tag.is_some()) + usize::from(self.value.is_some()))
+    }
+}
+
+impl<'de, T> de::EnumAccess<'de> for TagDeserializer<T>
+where
+    T: IntoDeserializer<'de, DecodeError<Infallible>>
+{
+    type Error = DecodeError<Infallible>;
+    type Variant = Self;
+
+    #[inline]
+    fn variant_seed<S>(self, seed: S) -> Result<(S::Value, Self::Variant), Self::Error>
+    where S: de::DeserializeSeed<'de>
+    {
+        let variant = seed.deserialize(de::value::BorrowedStrDeserializer::<Self::Error>::new(tag::NAME))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, T> de::VariantAccess<'de> for TagDeserializer<T>
+where
+    T: IntoDeserializer<'de, DecodeError<Infallible>>
+{
+    type Error = DecodeError<Infallible>;
+
+    #[inline]
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        use serde::de::Error;
+
+        Err(Self::Error::invalid_type(de::Unexpected::TupleVariant, &"unit variant"))
+    }
+
+    #[inline]
+    fn newtype_variant_seed<S>(mut self, seed: S) -> Result<S::Value, Self::Error>
+    where S: de::DeserializeSeed<'de>
+    {
+        self.tag = None;
+        de::SeqAccess::next_element_seed(&mut self, seed)?
+            .ok_or_else(|| de::Error::invalid_length(0, &"tagged value"))
+    }
+
+    #[inline]
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where V: Visitor<'de>
+    {
+        visitor.visit_seq(self)
+    }
+
+    #[inline]
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V)
+        -> Result<V::Value, Self::Error>
+    where V: Visitor<'de>
+    {
+        visitor.visit_seq(self)
+    }
+}
+
+/// Enum variant and its content.
+struct EnumDeserializer<K, T> {
+    variant: K,
+    value: Option<T>
+}
+
+impl<'de, K, T> de::EnumAccess<'de> for EnumDeserializer<K, T>
+where
+    K: IntoDeserializer<'de, DecodeError<Infallible>>,
+    T: IntoDeserializer<'de, DecodeError<Infallible>>
+{
+    type Error = DecodeError<Infallible>;
+    type Variant = VariantDeserializer<T>;
+
+    #[inline]
+    fn variant_seed<S>(self, seed: S) -> Result<(S::Value, Self::Variant), Self::Error>
+    where S: de::DeserializeSeed<'de>
+    {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, VariantDeserializer(self.value)))
+    }
+}
+
+struct VariantDeserializer<T>(Option<T>);
+
+impl<'de, T> de::VariantAccess<'de> for VariantDeserializer<T>
+where
+    T: IntoDeserializer<'de, DecodeError<Infallible>>
+{
+    type Error = DecodeError<Infallible>;
+
+    #[inline]
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    #[inline]
+    fn newtype_variant_seed<S>(self, seed: S) -> Result<S::Value, Self::Error>
+    where S: de::DeserializeSeed<'de>
+    {
+        match self.0 {
+            Some(value) => seed.deserialize(value.into_deserializer()),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"newtype variant"))
+        }
+    }
+
+    #[inline]
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where V: Visitor<'de>
+    {
+        use serde::Deserializer;
+
+        match self.0 {
+            Some(value) => value.into_deserializer().deserialize_tuple(len, visitor),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"tuple variant"))
+        }
+    }
+
+    #[inline]
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V)
+        -> Result<V::Value, Self::Error>
+    where V: Visitor<'de>
+    {
+        use serde::Deserializer;
+
+        match self.0 {
+            Some(value) => value.into_deserializer().deserialize_struct("", fields, visitor),
+            None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"struct variant"))
+        }
+    }
+}
+
+/// Implement `Deserializer` for owned and borrowed value.
+///
+/// The owned value visits owned strings and bytes,
+/// and the borrowed value visits borrowed ones.
+macro_rules! value_deserializer {
+    (
+        $t:ty,
+        $visit_string:ident,
+        $visit_bytes:ident,
+        |$boxed:ident| $unbox:expr
+    ) => {
+        impl<'de> IntoDeserializer<'de, DecodeError<Infallible>> for $t {
+            type Deserializer = Self;
+
+            #[inline]
+            fn into_deserializer(self) -> Self::Deserializer {
+                self
+            }
+        }
+
+        impl<'de> serde::Deserializer<'de> for $t {
+            type Error = DecodeError<Infallible>;
+
+            fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where V: Visitor<'de>
+            {
+                match self {
+                    Value::Null => visitor.visit_none(),
+                    Value::Undefined => visitor.visit_enum(simple::SimpleAccessor::new(23)),
+                    Value::Bool(v) => visitor.visit_bool(copy(v)),
+                    Value::Integer(v) => visit_integer(visitor, copy(v)),
+                    Value::Float(v) => visitor.visit_f64(copy(v)),
+                    Value::Bytes(v) => visitor.$visit_bytes(v),
+                    Value::Text(v) => visitor.$visit_string(v),
+                    Value::Array(v) => visit_array(visitor, v.into_iter()),
+                    Value::Map(v) => visit_map(visitor, v.into_iter().map(|(k, v)| (k, v))),
+                    Value::Tag(tag, $boxed) => {
+                        let tag = copy::<u64>(tag);
+                        let value = $unbox;
+
+                        if let Value::Bytes(bytes) = &value {
+                            match tag::bignum(tag, bytes) {
+                                Some(tag::Bignum::Positive(n)) => return visitor.visit_u128(n),
+                                Some(tag::Bignum::Negative(n)) => return visitor.visit_i128(n),
+                                None => ()
+                            }
+                        }
+
+                        visitor.visit_enum(TagDeserializer { tag: Some(tag), value: Some(value) })
+                    },
+                    Value::Simple(v) => visitor.visit_enum(simple::SimpleAccessor::new(copy(v)))
+                }
+            }
+
+            #[inline]
+            fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where V: Visitor<'de>
+            {
+                match self {
+                    Value::Null | Value::Undefined => visitor.visit_none(),
+                    _ => visitor.visit_some(self)
+                }
+            }
+
+            #[inline]
+            fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where V: Visitor<'de>
+            {
+                match self {
+                    Value::Array(v) if v.is_empty() => visitor.visit_unit(),
+                    _ => self.deserialize_any(visitor)
+                }
+            }
+
+            #[inline]
+            fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V)
+                -> Result<V::Value, Self::Error>
+            where V: Visitor<'de>
+            {
+                self.deserialize_unit(visitor)
+            }
+
+            #[inline]
+            fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V)
+                -> Result<V::Value, Self::Error>
+            where V: Visitor<'de>
+            {
+                if name == raw::NAME {
+                    use crate::core::enc::Encode;
+                    use crate::core::utils::VecWriter;
+
+                    let mut writer = VecWriter(Vec::new());
+                    self.encode(&mut writer).map_err(|err| match err {
+                        crate::core::enc::Error::Write(never) => match never {},
+                        err => <Self::Error as de::Error>::custom(err)
+                    })?;
+                    return visitor.visit_byte_buf(writer.0);
+                }
+
+                visitor.visit_newtype_struct(self)
+            }
+
+            #[inline]
+            fn deserialize_tuple_struct<V>(self, name: &'static str, len: usize, visitor: V)
+                -> Result<V::Value, Self::Error>
+            where V: Visitor<'de>
+            {
+                match self {
+                    Value::Tag(tag, $boxed) if name == tag::NAME && len == 2 => {
+                        let value = $unbox;
+                        visitor.visit_seq(TagDeserializer { tag: Some(copy(tag)), value: Some(value) })
+                    },
+                    Value::Tag(..) => self.deserialize_any(visitor),
+                    _ if name == tag::NAME && len == 2 => {
+                        use serde::de::Error;
+
+                        Err(Self::Error::invalid_type(de::Unexpected::Other("untagged value"), &"tagged value"))
+                    },
+                    _ => self.deserialize_any(visitor)
+                }
+            }
+
+            #[inline]
+            fn deserialize_enum<V>(
+                self,
+                _name: &'static str,
+                _variants: &'static [&'static str],
+                visitor: V
+            ) -> Result<V::Value, Self::Error>
+            where V: Visitor<'de>
+            {
+                use serde::de::Error;
+
+                match self {
+                    Value::Text(v) => visitor.visit_enum(EnumDeserializer {
+                        variant: v.as_str(),
+                        value: None::<Self>
+                    }),
+                    // packed variant index
+                    Value::Integer(v) => visitor.visit_enum(EnumDeserializer {
+                        variant: u64::try_from(copy::<i128>(v)).map_err(Self::Error::custom)?,
+                        value: None::<Self>
+                    }),
+                    Value::Map(v) if v.len() == 1 => {
+                        let (variant, value) = v.into_iter().next().unwrap(); // checked by if
+                        visitor.visit_enum(EnumDeserializer { variant, value: Some(value) })
+                    },
+                    _ => Err(Self::Error::invalid_type(de::Unexpected::Other("value"), &"enum"))
+                }
+            }
+
+            #[inline]
+            fn deserialize_struct<V>(
+                self,
+                _name: &'static str,
+                _fields: &'static [&'static str],
+                visitor: V
+            ) -> Result<V::Value, Self::Error>
+            where V: Visitor<'de>
+            {
+                self.deserialize_any(visitor)
+            }
+
+            #[inline]
+            fn is_human_readable(&self) -> bool {
+                false
+            }
+
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+                bytes byte_buf seq tuple map identifier ignored_any
+            }
+        }
+    }
+}
+
+value_deserializer!(Value, visit_string, visit_byte_buf, |v| *v);
+value_deserializer!(&'de Value, visit_borrowed_str, visit_borrowed_bytes, |v| &**v);
@@ -25,3 +25,90 @@ impl<T> Drop for ScopeGuard<'_, T> {
         (self.1)(self.0);
     }
 }
+
+/// Check that `f32` can be converted to half precision float without loss.
+#[inline]
+pub(crate) fn f32_is_f16(value: f32) -> bool {
+    let bits = value.to_bits();
+    let exp = (bits >> 23) & 0xff;
+    let man = bits & 0x7f_ffff;
+
+    match exp {
+        // zero, or too small for half precision
+        0 => man == 0,
+        // infinity and nan
+        0xff => man.trailing_zeros() >= 13,
+        // normal half precision
+        113 ..= 142 => man.trailing_zeros() >= 13,
+        // subnormal half precision
+        exp @ 103 ..= 112 => (man | (1 << 23)).trailing_zeros() >= 126 - exp,
+        _ => false
+    }
+}
+
+/// Check that `f64` can be converted to single precision float without loss.
+#[inline]
+pub(crate) fn f64_is_f32(value: f64) -> bool {
+    f64::from(value as f32).to_bits() == value.to_bits()
+}
+
+/// Convert half precision float bits to `f64` without loss.
+#[inline]
+pub(crate) fn f16_to_f64(bits: u16) -> f64 {
+    let sign = u64::from(bits >> 15) << 63;
+    let exp = (bits >> 10) & 0x1f;
+    let man = u64::from(bits & 0x3ff);
+
+    match exp {
+        // zero and subnormal
+        0 => {
+            let value = man as f64 / f64::from(1u32 << 24);
+            f64::from_bits(value.to_bits() | sign)
+        },
+        // infinity and nan
+        0x1f => f64::from_bits(sign | (0x7ff << 52) | (man << 42)),
+        exp => f64::from_bits(sign | ((u64::from(exp) + 1023 - 15) << 52) | (man << 42))
+    }
+}
+
+/// Convert half precision float bits to `f32` without loss.
+#[inline]
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits >> 15) << 31;
+    let exp = (bits >> 10) & 0x1f;
+    let man = u32::from(bits & 0x3ff);
+
+    match exp {
+        // zero and subnormal
+        0 => {
+            let value = man as f32 / (1u32 << 24) as f32;
+            f32::from_bits(value.to_bits() | sign)
+        },
+        // infinity and nan
+        0x1f => f32::from_bits(sign | (0xff << 23) | (man << 13)),
+        exp => f32::from_bits(sign | ((u32::from(exp) + 127 - 15) << 23) | (man << 13))
+    }
+}
+
+/// Convert `f32` to half precision float bits.
+///
+/// The value must pass `f32_is_f16`, otherwise low bits are truncated.
+#[inline]
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = (bits >> 23) & 0xff;
+    let man = bits & 0x7f_ffff;
+
+    let half = match exp {
+        0 => 0,
+        0xff => 0x7c00 | (man >> 13),
+        113 ..= 142 => ((exp - 112) << 10) | (man >> 13),
+        exp @ 103 ..= 112 => (man | (1 << 23)) >> (126 - exp),
+        // out of range, saturate to infinity
+        exp if exp > 142 => 0x7c00,
+        _ => 0
+    };
+
+    sign | half as u16
+}
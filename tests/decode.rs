@@ -8,6 +8,17 @@ use cbor4ii::core::dec::{ self, Decode };
 use cbor4ii::core::types;
 use cbor4ii::core::utils::{ BufWriter, SliceReader };
 
+
+fn encode<T: Encode>(value: &T) -> Vec<u8> {
+    let mut writer = BufWriter::new(Vec::new());
+    value.encode(&mut writer).unwrap();
+    writer.into_inner()
+}
+
+fn decode<'a, T: Decode<'a>>(mut reader: SliceReader<'a>) -> Result<T, dec::Error<Infallible>> {
+    T::decode(&mut reader)
+}
+
 #[test]
 fn test_decode_value() {
     macro_rules! test {
@@ -456,3 +467,482 @@ fn test_mock_chunk_string() {
 
     assert_eq!(s, "123456");
 }
+
+#[test]
+fn test_strict_decode() {
+    use cbor4ii::core::RawValue;
+    use cbor4ii::core::error::DecodeError;
+
+    fn strict<'a, T: Decode<'a>>(buf: &'a [u8]) -> Result<T, DecodeError<Infallible>> {
+        let mut reader = SliceReader::new(buf).strict();
+        T::decode(&mut reader)
+    }
+
+    // non-shortest integer and length
+    assert_eq!(u64::decode(&mut SliceReader::new(&[0x18, 0x05])).unwrap(), 5);
+    assert!(matches!(strict::<u64>(&[0x18, 0x05]), Err(DecodeError::NonShortest { found: 0x18, .. })));
+    assert!(matches!(strict::<u16>(&[0x19, 0x00, 0xff]), Err(DecodeError::NonShortest { .. })));
+    assert!(matches!(strict::<i32>(&[0x3a, 0x00, 0x00, 0xff, 0xff]), Err(DecodeError::NonShortest { .. })));
+    assert!(matches!(strict::<Value>(&[0x1b, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]), Err(DecodeError::NonShortest { .. })));
+    assert!(matches!(strict::<&str>(&[0x78, 0x01, b'a']), Err(DecodeError::NonShortest { .. })));
+    assert_eq!(strict::<u64>(&[0x18, 0x18]).unwrap(), 24);
+    assert_eq!(strict::<u64>(&[0x1b, 0, 0, 0, 1, 0, 0, 0, 0]).unwrap(), 1 << 32);
+
+    // indefinite length
+    assert!(matches!(strict::<Vec<u8>>(&[0x9f, 0x01, 0xff]), Err(DecodeError::IndefiniteLength { .. })));
+    assert!(matches!(strict::<String>(&[0x7f, 0x61, b'a', 0xff]), Err(DecodeError::IndefiniteLength { .. })));
+    assert!(matches!(strict::<RawValue>(&[0x81, 0xbf, 0xff]), Err(DecodeError::IndefiniteLength { .. })));
+
+    // non-preferred float
+    let mut buf = BufWriter::new(Vec::new());
+    1.5f64.encode(&mut buf).unwrap();
+    assert_eq!(f64::decode(&mut SliceReader::new(buf.buffer())).unwrap(), 1.5);
+    assert!(matches!(strict::<f64>(buf.buffer()), Err(DecodeError::NonPreferredFloat { .. })));
+    let mut buf = BufWriter::new(Vec::new());
+    1.5f32.encode(&mut buf).unwrap();
+    assert!(matches!(strict::<Value>(buf.buffer()), Err(DecodeError::NonPreferredFloat { .. })));
+    let mut buf = BufWriter::new(Vec::new());
+    f32::NAN.encode(&mut buf).unwrap();
+    assert!(matches!(strict::<RawValue>(buf.buffer()), Err(DecodeError::NonPreferredFloat { .. })));
+    let mut buf = BufWriter::new(Vec::new());
+    1.1f64.encode(&mut buf).unwrap();
+    assert_eq!(strict::<f64>(buf.buffer()).unwrap(), 1.1);
+    let mut buf = BufWriter::new(Vec::new());
+    100000.0f32.encode(&mut buf).unwrap();
+    assert_eq!(strict::<f32>(buf.buffer()).unwrap(), 100000.0);
+    let mut buf = BufWriter::new(Vec::new());
+    f32::from_bits(0x3380_0000).encode(&mut buf).unwrap(); // smallest half precision subnormal
+    assert!(matches!(strict::<f32>(buf.buffer()), Err(DecodeError::NonPreferredFloat { .. })));
+
+    // map keys
+    let map = |list: &[(Value, Value)]| {
+        let mut buf = BufWriter::new(Vec::new());
+        types::Map(list).encode(&mut buf).unwrap();
+        buf.into_inner()
+    };
+    let sorted = map(&[
+        (Value::Integer(10), Value::Null),
+        (Value::Integer(-1), Value::Map(vec![
+            (Value::Text("b".into()), Value::Null),
+            (Value::Text("aa".into()), Value::Null)
+        ]))
+    ]);
+    let unsorted = map(&[
+        (Value::Integer(-1), Value::Null),
+        (Value::Integer(10), Value::Null)
+    ]);
+    let unsorted_inner = map(&[
+        (Value::Integer(10), Value::Null),
+        (Value::Integer(-1), Value::Map(vec![
+            (Value::Text("aa".into()), Value::Null),
+            (Value::Text("b".into()), Value::Null)
+        ]))
+    ]);
+    let duplicate = map(&[
+        (Value::Text("a".into()), Value::Null),
+        (Value::Text("a".into()), Value::Null)
+    ]);
+    let key_map = map(&[
+        (Value::Map(vec![(Value::Integer(1), Value::Null), (Value::Integer(0), Value::Null)]), Value::Null)
+    ]);
+
+    assert!(strict::<Value>(&sorted).is_ok());
+    assert!(strict::<RawValue>(&sorted).is_ok());
+    assert!(matches!(strict::<Value>(&unsorted), Err(DecodeError::UnsortedKey { .. })));
+    assert!(matches!(strict::<RawValue>(&unsorted), Err(DecodeError::UnsortedKey { .. })));
+    assert!(matches!(strict::<Value>(&unsorted_inner), Err(DecodeError::UnsortedKey { .. })));
+    assert!(matches!(strict::<dec::IgnoredAny>(&unsorted_inner), Err(DecodeError::UnsortedKey { .. })));
+    assert!(matches!(strict::<Value>(&duplicate), Err(DecodeError::DuplicateKey { .. })));
+    assert!(matches!(
+        strict::<types::Map<Vec<(&str, Option<u8>)>>>(&duplicate),
+        Err(DecodeError::DuplicateKey { .. })
+    ));
+    assert!(matches!(strict::<Value>(&key_map), Err(DecodeError::UnsortedKey { .. })));
+    assert!(Value::decode(&mut SliceReader::new(&duplicate)).is_ok());
+}
+
+#[test]
+fn test_decode_offset() {
+    use dec::Read;
+    use cbor4ii::core::RawValue;
+    use cbor4ii::core::error::DecodeError;
+    use cbor4ii::core::utils::IoReader;
+
+    let mut buf = BufWriter::new(Vec::new());
+    types::Map(&[
+        ("a", Value::Array(Vec::new())),
+        ("bb", Value::Array(vec![Value::Integer(1), Value::Text("x".into())]))
+    ][..]).encode(&mut buf).unwrap();
+    let buf = buf.into_inner();
+
+    type Item<'a> = types::Map<Vec<(&'a str, Vec<u32>)>>;
+
+    // map(1) "a"(2) array(1) "bb"(3) array(1) 1(1) -> "x" at 9
+    let mut reader = SliceReader::new(&buf);
+    let err = Item::decode(&mut reader).err().unwrap();
+    assert!(matches!(err, DecodeError::Mismatch { found: 0x61, .. }));
+    assert_eq!(reader.offset(), Some(9));
+
+    let mut reader = IoReader::new(std::io::BufReader::with_capacity(2, buf.as_slice()));
+    let err = <types::Map<Vec<(String, Vec<u32>)>>>::decode(&mut reader).err().unwrap();
+    assert!(matches!(err, DecodeError::Mismatch { found: 0x61, .. }));
+    assert_eq!(reader.offset(), Some(9));
+
+    // offset is tracked in raw value
+    let mut reader = SliceReader::new(&buf[..buf.len() - 1]);
+    let err = <types::Map<Vec<(&str, RawValue)>>>::decode(&mut reader).err().unwrap();
+    assert!(matches!(err, DecodeError::Eof { .. }));
+    assert_eq!(reader.offset(), Some(10));
+
+    let mut reader = SliceReader::new(&buf);
+    let _ = <types::Map<Vec<(&str, RawValue)>>>::decode(&mut reader).unwrap();
+    assert_eq!(reader.offset(), Some(buf.len()));
+}
+
+#[test]
+fn test_decode_sequence() {
+    use cbor4ii::core::error::DecodeError;
+    use cbor4ii::core::utils::IoReader;
+
+    let mut buf = BufWriter::new(Vec::new());
+    enc::write_sequence(&mut buf, &[
+        Value::Integer(1),
+        Value::Text("a".into()),
+        Value::Array(vec![Value::Null])
+    ]).unwrap();
+    let buf = buf.into_inner();
+    assert_eq!(buf, [0x01, 0x61, 0x61, 0x81, 0xf6]);
+
+    let list = dec::Sequence::<_, Value>::new(SliceReader::new(&buf))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(list.len(), 3);
+    assert_eq!(list[2], Value::Array(vec![Value::Null]));
+
+    let reader = IoReader::new(std::io::BufReader::with_capacity(1, buf.as_slice()));
+    let list = dec::Sequence::<_, Value>::new(reader)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(list.len(), 3);
+
+    // empty sequence
+    assert_eq!(dec::Sequence::<_, Value>::new(SliceReader::new(&[])).count(), 0);
+
+    // truncated item
+    let mut iter = dec::Sequence::<_, Value>::new(SliceReader::new(&buf[..4]));
+    assert!(iter.next().unwrap().is_ok());
+    assert!(iter.next().unwrap().is_ok());
+    assert!(matches!(iter.next(), Some(Err(DecodeError::Eof { .. }))));
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn test_decode_value_ref() {
+    use std::borrow::Cow;
+    use std::io::BufReader;
+    use cbor4ii::core::ValueRef;
+    use cbor4ii::core::utils::IoReader;
+
+    let value = Value::Array(vec![
+        Value::Text("hello".into()),
+        Value::Bytes(vec![1, 2, 3]),
+        Value::Map(vec![(Value::Integer(-1), Value::Float(1.5))]),
+        Value::Tag(24, Box::new(Value::Bool(true))),
+        Value::Null
+    ]);
+
+    let mut writer = BufWriter::new(Vec::new());
+    value.encode(&mut writer).unwrap();
+    let buf = writer.into_inner();
+
+    // borrowed from slice
+    let value_ref = ValueRef::decode(&mut SliceReader::new(&buf)).unwrap();
+    match &value_ref {
+        ValueRef::Array(list) => {
+            assert!(matches!(&list[0], ValueRef::Text(Cow::Borrowed("hello"))));
+            assert!(matches!(&list[1], ValueRef::Bytes(Cow::Borrowed([1, 2, 3]))));
+        },
+        _ => panic!()
+    }
+    assert_eq!(value_ref, ValueRef::from(&value));
+    assert_eq!(value_ref.clone().into_owned(), value);
+
+    // encode
+    let mut writer = BufWriter::new(Vec::new());
+    value_ref.encode(&mut writer).unwrap();
+    assert_eq!(writer.buffer(), buf.as_slice());
+
+    // owned from reader
+    let mut reader = IoReader::new(BufReader::with_capacity(1, buf.as_slice()));
+    let value_ref2 = ValueRef::decode(&mut reader).unwrap();
+    assert_eq!(value_ref2, value_ref);
+
+    // chunked string is owned
+    let mut writer = BufWriter::new(Vec::new());
+    types::UncheckedStr::unbounded(&mut writer).unwrap();
+    "123".encode(&mut writer).unwrap();
+    "456".encode(&mut writer).unwrap();
+    types::UncheckedStr::end(&mut writer).unwrap();
+    let value_ref = ValueRef::decode(&mut SliceReader::new(writer.buffer())).unwrap();
+    assert!(matches!(value_ref, ValueRef::Text(Cow::Owned(ref s)) if s == "123456"));
+}
+
+#[test]
+fn test_decode_float_widening() {
+    // half precision, see rfc8949 appendix A
+    let cases: &[(&[u8], f64)] = &[
+        (&[0xf9, 0x00, 0x00], 0.0),
+        (&[0xf9, 0x3c, 0x00], 1.0),
+        (&[0xf9, 0x3e, 0x00], 1.5),
+        (&[0xf9, 0x7b, 0xff], 65504.0),
+        (&[0xf9, 0x00, 0x01], 5.960464477539063e-8),
+        (&[0xf9, 0x04, 0x00], 0.00006103515625),
+        (&[0xf9, 0xc4, 0x00], -4.0),
+        (&[0xf9, 0x7c, 0x00], f64::INFINITY),
+        (&[0xf9, 0xfc, 0x00], f64::NEG_INFINITY)
+    ];
+
+    for &(buf, expected) in cases {
+        assert_eq!(decode::<f64>(SliceReader::new(buf)).unwrap(), expected);
+        assert_eq!(decode::<f32>(SliceReader::new(buf)).unwrap(), expected as f32);
+        assert_eq!(decode::<Value>(SliceReader::new(buf)).unwrap(), Value::Float(expected));
+    }
+
+    let v = decode::<f64>(SliceReader::new(&[0xf9, 0x80, 0x00])).unwrap();
+    assert!(v == 0.0 && v.is_sign_negative());
+    assert!(decode::<f64>(SliceReader::new(&[0xf9, 0x7e, 0x00])).unwrap().is_nan());
+    assert!(decode::<f32>(SliceReader::new(&[0xf9, 0x7e, 0x00])).unwrap().is_nan());
+
+    // single precision into f64
+    assert_eq!(decode::<f64>(SliceReader::new(&[0xfa, 0x47, 0xc3, 0x50, 0x00])).unwrap(), 100000.0);
+    assert_eq!(decode::<Value>(SliceReader::new(&[0xfa, 0x47, 0xc3, 0x50, 0x00])).unwrap(), Value::Float(100000.0));
+
+    // narrowing is rejected
+    let ret = f32::decode(&mut SliceReader::new(&[0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]));
+    assert!(matches!(ret, Err(dec::Error::Mismatch { .. })));
+
+    // preferred encoding round trips
+    for &(_, expected) in cases {
+        let mut buf = BufWriter::new(Vec::new());
+        types::Preferred(expected).encode(&mut buf).unwrap();
+        assert_eq!(buf.buffer().len(), 3);
+        assert_eq!(decode::<f64>(SliceReader::new(buf.buffer())).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_decode_simple_value() {
+    use cbor4ii::core::ValueRef;
+
+    // [undefined, simple(16), simple(255), null]
+    let buf = [0x84, 0xf7, 0xf0, 0xf8, 0xff, 0xf6];
+
+    let value = Value::decode(&mut SliceReader::new(&buf)).unwrap();
+    assert_eq!(value, Value::Array(vec![
+        Value::Undefined,
+        Value::Simple(16),
+        Value::Simple(255),
+        Value::Null
+    ]));
+
+    let mut writer = BufWriter::new(Vec::new());
+    value.encode(&mut writer).unwrap();
+    assert_eq!(writer.buffer(), buf);
+
+    let value_ref = ValueRef::decode(&mut SliceReader::new(&buf)).unwrap();
+    assert_eq!(value_ref, ValueRef::from(&value));
+    assert_eq!(value_ref.into_owned(), value);
+
+    let _ignore = dec::IgnoredAny::decode(&mut SliceReader::new(&buf)).unwrap();
+}
+
+#[test]
+fn test_decode_collections() {
+    use std::collections::{ BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque };
+    use cbor4ii::core::dec::DuplicateKey;
+
+    let map: BTreeMap<String, u32> = vec![("a".into(), 1), ("b".into(), 2)].into_iter().collect();
+    let buf = encode(&map);
+    assert_eq!(buf, [0xa2, 0x61, b'a', 0x01, 0x61, b'b', 0x02]);
+    assert_eq!(decode::<BTreeMap<String, u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::Error)).unwrap(), map);
+
+    let hash_map: HashMap<String, u32> = map.clone().into_iter().collect();
+    let buf2 = encode(&hash_map);
+    assert_eq!(decode::<HashMap<String, u32>>(SliceReader::new(&buf2).duplicate_key(DuplicateKey::Error)).unwrap(), hash_map);
+    assert_eq!(decode::<HashMap<String, u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::Error)).unwrap(), hash_map);
+
+    let set: BTreeSet<u32> = vec![3, 1, 2].into_iter().collect();
+    let buf = encode(&set);
+    assert_eq!(buf, [0x83, 0x01, 0x02, 0x03]);
+    assert_eq!(decode::<BTreeSet<u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::Error)).unwrap(), set);
+    assert_eq!(decode::<HashSet<u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::Error)).unwrap(), set.iter().copied().collect());
+
+    let deque: VecDeque<u32> = vec![3, 1, 2].into_iter().collect();
+    let buf = encode(&deque);
+    assert_eq!(buf, [0x83, 0x03, 0x01, 0x02]);
+    assert_eq!(decode::<VecDeque<u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::Error)).unwrap(), deque);
+    let heap = decode::<BinaryHeap<u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::Error)).unwrap();
+    assert_eq!(heap.into_sorted_vec(), [1, 2, 3]);
+    assert_eq!(encode(&BinaryHeap::from(vec![1u32])), [0x81, 0x01]);
+
+    // duplicate keys, {"a": 1, "a": 2}
+    let buf = [0xa2, 0x61, b'a', 0x01, 0x61, b'a', 0x02];
+
+    let ret = decode::<BTreeMap<String, u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::Error));
+    assert!(matches!(ret, Err(dec::Error::DuplicateKey { .. })));
+    let ret = decode::<HashMap<String, u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::Error));
+    assert!(matches!(ret, Err(dec::Error::DuplicateKey { .. })));
+
+    let map = decode::<BTreeMap<String, u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::First)).unwrap();
+    assert_eq!(map["a"], 1);
+    let map = decode::<HashMap<String, u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::Last)).unwrap();
+    assert_eq!(map["a"], 2);
+
+    // last wins by default
+    let map = BTreeMap::<String, u32>::decode(&mut SliceReader::new(&buf)).unwrap();
+    assert_eq!(map["a"], 2);
+
+    // strict mode rejects them regardless of policy
+    let mut reader = SliceReader::new(&buf).strict().duplicate_key(DuplicateKey::Last);
+    let ret = BTreeMap::<String, u32>::decode(&mut reader);
+    assert!(matches!(ret, Err(dec::Error::DuplicateKey { .. })));
+
+    // duplicate items of set, indefinite length
+    let buf = [0x9f, 0x01, 0x01, 0xff];
+    let ret = decode::<BTreeSet<u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::Error));
+    assert!(matches!(ret, Err(dec::Error::DuplicateKey { .. })));
+    assert_eq!(decode::<HashSet<u32>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::First)).unwrap().len(), 1);
+
+    // depth limit is shared with other types
+    let mut buf = vec![0xa1, 0x00];
+    buf.extend_from_slice(&[0x81; 300]);
+    buf.push(0x80);
+    let ret = decode::<BTreeMap<u32, Value>>(SliceReader::new(&buf).duplicate_key(DuplicateKey::Error));
+    assert!(matches!(ret, Err(dec::Error::DepthOverflow { .. })));
+}
+
+#[test]
+fn test_decode_array_tuple() {
+    let arr = [1u32, 2, 3];
+    let buf = encode(&arr);
+    assert_eq!(buf, [0x83, 0x01, 0x02, 0x03]);
+    assert_eq!(decode::<[u32; 3]>(SliceReader::new(&buf)).unwrap(), arr);
+    assert_eq!(decode::<Vec<u32>>(SliceReader::new(&buf)).unwrap(), arr);
+    assert_eq!(decode::<[u32; 0]>(SliceReader::new(&[0x80])).unwrap(), []);
+
+    let ret = decode::<[u32; 2]>(SliceReader::new(&buf));
+    assert!(matches!(ret, Err(dec::Error::RequireLength { .. })));
+    let ret = decode::<[u32; 3]>(SliceReader::new(&[0x83, 0x01, 0x02, 0x20]));
+    assert!(matches!(ret, Err(dec::Error::Mismatch { .. })));
+
+    // indefinite length must have exactly N items
+    assert_eq!(decode::<[u32; 3]>(SliceReader::new(&[0x9f, 0x01, 0x02, 0x03, 0xff])).unwrap(), arr);
+    let ret = decode::<[u32; 2]>(SliceReader::new(&[0x9f, 0x01, 0x02, 0x03, 0xff]));
+    assert!(matches!(ret, Err(dec::Error::RequireLength { .. })));
+    let ret = decode::<[u32; 3]>(SliceReader::new(&[0x9f, 0x01, 0x02, 0xff]));
+    assert!(ret.is_err());
+    assert_eq!(decode::<(u8, u8)>(SliceReader::new(&[0x9f, 0x01, 0x02, 0xff])).unwrap(), (1, 2));
+    let ret = decode::<(u8, u8)>(SliceReader::new(&[0x9f, 0x01, 0x02, 0x03, 0xff]));
+    assert!(matches!(ret, Err(dec::Error::RequireLength { .. })));
+    let ret = <(u8, u8)>::decode(&mut SliceReader::new(&[0x9f, 0x01, 0x02, 0xff]).strict());
+    assert!(matches!(ret, Err(dec::Error::IndefiniteLength { .. })));
+
+    // depth is accounted
+    let buf = [0x81, 0x00, 0x81, 0x00];
+    let mut reader = SliceReader::new(&buf);
+    while dec::Read::step_in(&mut reader) {}
+    let ret = <[u8; 1]>::decode(&mut reader);
+    assert!(matches!(ret, Err(dec::Error::DepthOverflow { .. })));
+    let ret = <(u8,)>::decode(&mut reader);
+    assert!(matches!(ret, Err(dec::Error::DepthOverflow { .. })));
+    dec::Read::step_out(&mut reader);
+    assert_eq!(<[u8; 1]>::decode(&mut reader).unwrap(), [0]);
+    assert_eq!(<(u8,)>::decode(&mut reader).unwrap(), (0,));
+
+    let time: (u64, (u32, u32)) = (1, (2, 3));
+    let buf = encode(&time);
+    assert_eq!(buf, [0x82, 0x01, 0x82, 0x02, 0x03]);
+    assert_eq!(decode::<(u64, (u32, u32))>(SliceReader::new(&buf)).unwrap(), time);
+
+    let ret = decode::<(u64, u32, u32)>(SliceReader::new(&buf));
+    assert!(matches!(ret, Err(dec::Error::RequireLength { .. })));
+
+    let tuple = (1u8, "a", true, 1.5f64, -1i32, [0u8; 2], 2u16, (3u8,), types::Bytes(&b"b"[..]), 4u64, "c", 5u8);
+    let buf = encode(&tuple);
+    assert_eq!(buf[0], 0x8c);
+    let (a, b, c, d, e, f, g, (h,), i, j, k, l) = decode::<(
+        u8, &str, bool, f64, i32, [u8; 2], u16, (u8,), types::Bytes<&[u8]>, u64, &str, u8
+    )>(SliceReader::new(&buf)).unwrap();
+    assert_eq!((a, b, c, d, e, f, g, h, i.0, j, k, l), (1, "a", true, 1.5, -1, [0, 0], 2, 3, &b"b"[..], 4, "c", 5));
+}
+
+#[test]
+fn test_decode_limits() {
+    use dec::Limits;
+
+    // string and bytes length, only when buffered
+    let buf = encode(&"0123456789");
+    let limits = Limits::new().max_bytes_len(9);
+    assert!(matches!(decode::<String>(SliceReader::new(&buf).limits(limits.clone())), Err(dec::Error::LimitExceeded { .. })));
+    assert_eq!(decode::<&str>(SliceReader::new(&buf).limits(limits.clone())).unwrap(), "0123456789");
+    assert_eq!(decode::<String>(SliceReader::new(&buf).limits(limits.max_bytes_len(10))).unwrap(), "0123456789");
+
+    // chunks are added up
+    let buf = [0x7f, 0x65, b'0', b'1', b'2', b'3', b'4', 0x65, b'5', b'6', b'7', b'8', b'9', 0xff];
+    let ret = decode::<String>(SliceReader::new(&buf).limits(Limits::new().max_bytes_len(9)));
+    assert!(matches!(ret, Err(dec::Error::LimitExceeded { .. })));
+
+    // array and map length is checked before decoding items
+    let buf = encode(&[1u32, 2, 3]);
+    let limits = Limits::new().max_len(2);
+    assert!(matches!(decode::<Vec<u32>>(SliceReader::new(&buf).limits(limits.clone())), Err(dec::Error::LimitExceeded { .. })));
+    assert!(matches!(decode::<dec::IgnoredAny>(SliceReader::new(&buf).limits(limits.clone())), Err(dec::Error::LimitExceeded { .. })));
+    assert!(matches!(decode::<Value>(SliceReader::new(&buf).limits(limits)), Err(dec::Error::LimitExceeded { .. })));
+    let buf = [0xbb, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+    let ret = decode::<types::Map<Vec<(u32, u32)>>>(SliceReader::new(&buf).limits(Limits::new().max_len(1024)));
+    assert!(matches!(ret, Err(dec::Error::LimitExceeded { .. })));
+
+    // total items, including indefinite length
+    let buf = encode(&vec![vec![1u32, 2], vec![3, 4]]);
+    assert!(decode::<Vec<Vec<u32>>>(SliceReader::new(&buf).limits(Limits::new().max_items(6))).is_ok());
+    let ret = decode::<Vec<Vec<u32>>>(SliceReader::new(&buf).limits(Limits::new().max_items(5)));
+    assert!(matches!(ret, Err(dec::Error::LimitExceeded { .. })));
+    let buf = [0x9f, 0x01, 0x02, 0x03, 0xff];
+    let ret = decode::<std::collections::BTreeSet<u32>>(SliceReader::new(&buf).limits(Limits::new().max_items(2)));
+    assert!(matches!(ret, Err(dec::Error::LimitExceeded { .. })));
+
+    // total allocated bytes, many medium strings
+    let value = vec!["a".repeat(100); 16];
+    let buf = encode(&value);
+    assert_eq!(decode::<Vec<String>>(SliceReader::new(&buf).limits(Limits::new().max_alloc(4096))).unwrap(), value);
+    let ret = decode::<Vec<String>>(SliceReader::new(&buf).limits(Limits::new().max_alloc(1024)));
+    assert!(matches!(ret, Err(dec::Error::LimitExceeded { .. })));
+    let ret = decode::<Value>(SliceReader::new(&buf).limits(Limits::new().max_alloc(1024)));
+    assert!(matches!(ret, Err(dec::Error::LimitExceeded { .. })));
+
+    // the usage is accumulated over values until reset
+    let buf = [0x82, 0x01, 0x02, 0x82, 0x03, 0x04];
+    let mut reader = SliceReader::new(&buf).limits(Limits::new().max_items(3));
+    assert!(<Vec<u32>>::decode(&mut reader).is_ok());
+    let ret = <Vec<u32>>::decode(&mut reader);
+    assert!(matches!(ret, Err(dec::Error::LimitExceeded { .. })));
+
+    let mut limits = Limits::new().max_items(3);
+    assert!(limits.check(dec::Limit::Items(2)));
+    assert!(!limits.check(dec::Limit::Items(2)));
+    limits.reset();
+    assert!(limits.check(dec::Limit::Items(2)));
+}
+
+#[test]
+fn test_decode_end() {
+    let buf = [0x01, 0x02];
+    let mut reader = SliceReader::new(&buf);
+    assert_eq!(u8::decode(&mut reader).unwrap(), 1);
+    let err = dec::end(&mut reader).unwrap_err();
+    assert!(matches!(err, dec::Error::TrailingData { .. }));
+    assert_eq!(err.offset(), Some(1));
+
+    assert_eq!(u8::decode(&mut reader).unwrap(), 2);
+    dec::end(&mut reader).unwrap();
+}
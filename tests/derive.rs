@@ -0,0 +1,344 @@
+#![cfg(all(feature = "derive", feature = "use_std"))]
+
+use std::io::BufReader;
+use cbor4ii::core::enc::Encode;
+use cbor4ii::core::dec::{ self, Decode };
+use cbor4ii::core::utils::{ BufWriter, SliceReader, IoReader };
+
+
+fn encode<T: Encode>(value: &T) -> Vec<u8> {
+    let mut writer = BufWriter::new(Vec::new());
+    value.encode(&mut writer).unwrap();
+    writer.into_inner()
+}
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+struct Person {
+    name: String,
+    #[cbor4ii(rename = "yrs")]
+    age: u32,
+    #[cbor4ii(optional)]
+    email: Option<String>,
+    #[cbor4ii(default)]
+    tags: Vec<String>
+}
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+struct Keyed {
+    #[cbor4ii(key = 1)]
+    id: u64,
+    #[cbor4ii(key = -1)]
+    flag: bool
+}
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+struct Point(i32, i32);
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+#[cbor4ii(array, tag = 1000)]
+struct Tagged {
+    x: u8,
+    #[cbor4ii(optional)]
+    y: Option<u8>
+}
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+struct Unit;
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+#[cbor4ii(transparent)]
+struct Meters(f64);
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+enum Shape {
+    Empty,
+    Circle(u32),
+    Line(u32, u32),
+    #[cbor4ii(rename = "rect")]
+    Rect { w: u32, h: u32 }
+}
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+#[cbor4ii(index)]
+enum Op {
+    Nop,
+    Push(i64),
+    #[cbor4ii(key = 10)]
+    Jump { #[cbor4ii(key = 0)] to: u32 }
+}
+
+#[derive(Debug, PartialEq, Encode, Decode)]
+struct Borrowed<'a, T> {
+    name: &'a str,
+    value: T
+}
+
+#[test]
+fn test_derive_map_struct() {
+    let person = Person {
+        name: "a".into(),
+        age: 7,
+        email: None,
+        tags: Vec::new()
+    };
+    let buf = encode(&person);
+    assert_eq!(buf, [
+        0xa3,
+        0x64, b'n', b'a', b'm', b'e', 0x61, b'a',
+        0x63, b'y', b'r', b's', 0x07,
+        0x64, b't', b'a', b'g', b's', 0x80
+    ]);
+    assert_eq!(Person::decode(&mut SliceReader::new(&buf)).unwrap(), person);
+
+    // missing optional and default fields, unknown keys are skipped
+    let buf = [
+        0xbf,
+        0x63, b'y', b'r', b's', 0x07,
+        0x01, 0x82, 0x01, 0x02,
+        0x64, b'n', b'a', b'm', b'e', 0x61, b'a',
+        0x65, b'e', b'm', b'a', b'i', b'x', 0xf6,
+        0xff
+    ];
+    assert_eq!(Person::decode(&mut SliceReader::new(&buf)).unwrap(), person);
+
+    let person = Person { email: Some("b".into()), ..person };
+    let buf = encode(&person);
+    assert_eq!(buf[0], 0xa4);
+    assert_eq!(Person::decode(&mut SliceReader::new(&buf)).unwrap(), person);
+
+    let keyed = Keyed { id: 3, flag: true };
+    let buf = encode(&keyed);
+    assert_eq!(buf, [0xa2, 0x01, 0x03, 0x20, 0xf5]);
+    assert_eq!(Keyed::decode(&mut SliceReader::new(&buf)).unwrap(), keyed);
+}
+
+#[test]
+fn test_derive_array_struct() {
+    let point = Point(1, -2);
+    let buf = encode(&point);
+    assert_eq!(buf, [0x82, 0x01, 0x21]);
+    assert_eq!(Point::decode(&mut SliceReader::new(&buf)).unwrap(), point);
+
+    let tagged = Tagged { x: 1, y: Some(2) };
+    let buf = encode(&tagged);
+    assert_eq!(buf, [0xd9, 0x03, 0xe8, 0x82, 0x01, 0x02]);
+    assert_eq!(Tagged::decode(&mut SliceReader::new(&buf)).unwrap(), tagged);
+
+    // trailing optional field can be omitted
+    let buf = [0xd9, 0x03, 0xe8, 0x81, 0x01];
+    assert_eq!(Tagged::decode(&mut SliceReader::new(&buf)).unwrap(), Tagged { x: 1, y: None });
+
+    // wrong tag
+    let buf = [0xd9, 0x03, 0xe9, 0x81, 0x01];
+    assert!(Tagged::decode(&mut SliceReader::new(&buf)).is_err());
+
+    // too short or too long
+    let buf = [0x81, 0x01];
+    assert!(matches!(
+        Point::decode(&mut SliceReader::new(&buf)),
+        Err(dec::Error::RequireLength { .. })
+    ));
+    let buf = [0x83, 0x01, 0x02, 0x03];
+    assert!(matches!(
+        Point::decode(&mut SliceReader::new(&buf)),
+        Err(dec::Error::RequireLength { .. })
+    ));
+
+    let buf = encode(&Unit);
+    assert_eq!(buf, [0x80]);
+    assert_eq!(Unit::decode(&mut SliceReader::new(&buf)).unwrap(), Unit);
+
+    let meters = Meters(1.5);
+    let buf = encode(&meters);
+    assert_eq!(buf, encode(&1.5f64));
+    assert_eq!(Meters::decode(&mut SliceReader::new(&buf)).unwrap(), meters);
+}
+
+#[test]
+fn test_derive_enum() {
+    let cases: &[(Shape, &[u8])] = &[
+        (Shape::Empty, &[0x65, b'E', b'm', b'p', b't', b'y']),
+        (Shape::Circle(1), &[0xa1, 0x66, b'C', b'i', b'r', b'c', b'l', b'e', 0x01]),
+        (Shape::Line(1, 2), &[0xa1, 0x64, b'L', b'i', b'n', b'e', 0x82, 0x01, 0x02]),
+        (Shape::Rect { w: 1, h: 2 }, &[
+            0xa1, 0x64, b'r', b'e', b'c', b't',
+            0xa2, 0x61, b'w', 0x01, 0x61, b'h', 0x02
+        ])
+    ];
+
+    for (shape, expected) in cases {
+        let buf = encode(shape);
+        assert_eq!(&buf, expected);
+        assert_eq!(&Shape::decode(&mut SliceReader::new(&buf)).unwrap(), shape);
+    }
+
+    let cases: &[(Op, &[u8])] = &[
+        (Op::Nop, &[0x00]),
+        (Op::Push(-1), &[0xa1, 0x01, 0x20]),
+        (Op::Jump { to: 2 }, &[0xa1, 0x0a, 0xa1, 0x00, 0x02])
+    ];
+
+    for (op, expected) in cases {
+        let buf = encode(op);
+        assert_eq!(&buf, expected);
+        assert_eq!(&Op::decode(&mut SliceReader::new(&buf)).unwrap(), op);
+    }
+
+    // raw identifiers are used without prefix
+    #[derive(Debug, PartialEq, Encode, Decode)]
+    #[allow(non_camel_case_types)]
+    enum Raw {
+        r#type,
+        r#struct { r#loop: u8 }
+    }
+
+    let cases: &[(Raw, &[u8])] = &[
+        (Raw::r#type, &[0x64, b't', b'y', b'p', b'e']),
+        (Raw::r#struct { r#loop: 1 }, &[
+            0xa1, 0x66, b's', b't', b'r', b'u', b'c', b't',
+            0xa1, 0x64, b'l', b'o', b'o', b'p', 0x01
+        ])
+    ];
+
+    for (raw, expected) in cases {
+        let buf = encode(raw);
+        assert_eq!(&buf, expected);
+        assert_eq!(&Raw::decode(&mut SliceReader::new(&buf)).unwrap(), raw);
+    }
+
+    // unknown variant, unit variant with content, and variant with extra entry
+    for buf in [
+        &[0x64, b'N', b'o', b'n', b'e'][..],
+        &[0xa1, 0x65, b'E', b'm', b'p', b't', b'y', 0xf6],
+        &[0xa2, 0x66, b'C', b'i', b'r', b'c', b'l', b'e', 0x01, 0x00, 0x00]
+    ] {
+        assert!(Shape::decode(&mut SliceReader::new(buf)).is_err());
+    }
+}
+
+#[test]
+fn test_derive_borrowed() {
+    let value = Borrowed { name: "hello", value: 1u8 };
+    let buf = encode(&value);
+    let decoded: Borrowed<'_, u8> = Borrowed::decode(&mut SliceReader::new(&buf)).unwrap();
+    assert_eq!(decoded, value);
+    assert!(buf.as_ptr_range().contains(&decoded.name.as_ptr()));
+}
+
+#[test]
+fn test_derive_prefix_key() {
+    #[derive(Debug, PartialEq, Encode, Decode)]
+    struct S {
+        values: u8,
+        value: u8,
+        v: u8
+    }
+
+    #[derive(Debug, PartialEq, Encode, Decode)]
+    enum E {
+        Ab,
+        A
+    }
+
+    let s = S { values: 1, value: 2, v: 3 };
+    let buf = encode(&s);
+    assert_eq!(S::decode(&mut SliceReader::new(&buf)).unwrap(), s);
+
+    let mut reader = IoReader::new(BufReader::with_capacity(1, buf.as_slice()));
+    assert_eq!(S::decode(&mut reader).unwrap(), s);
+
+    for e in [E::Ab, E::A] {
+        let buf = encode(&e);
+        assert_eq!(E::decode(&mut SliceReader::new(&buf)).unwrap(), e);
+    }
+}
+
+#[test]
+fn test_derive_io_reader() {
+    let person = Person {
+        name: "streamed".into(),
+        age: 30,
+        email: Some("x@y".into()),
+        tags: vec!["a".into(), "b".into()]
+    };
+    let buf = encode(&person);
+
+    // keys are matched while the reader only has one byte buffered
+    let mut reader = IoReader::new(BufReader::with_capacity(1, buf.as_slice()));
+    assert_eq!(Person::decode(&mut reader).unwrap(), person);
+
+    // indefinite length key split in chunks
+    let buf = [
+        0xa2,
+        0x7f, 0x62, b'n', b'a', 0x62, b'm', b'e', 0xff, 0x61, b'a',
+        0x63, b'y', b'r', b's', 0x07
+    ];
+    let mut reader = IoReader::new(BufReader::with_capacity(1, &buf[..]));
+    assert_eq!(Person::decode(&mut reader).unwrap(), Person {
+        name: "a".into(),
+        age: 7,
+        email: None,
+        tags: Vec::new()
+    });
+}
+
+#[test]
+fn test_derive_missing_and_duplicate() {
+    let buf = [0xa1, 0x64, b'n', b'a', b'm', b'e', 0x61, b'a'];
+    assert!(matches!(
+        Person::decode(&mut SliceReader::new(&buf)),
+        Err(dec::Error::MissingField { name: &"age" })
+    ));
+
+    let buf = [0xa3, 0x01, 0x03, 0x20, 0xf5, 0x01, 0x04];
+    assert_eq!(
+        Keyed::decode(&mut SliceReader::new(&buf)).unwrap(),
+        Keyed { id: 4, flag: true }
+    );
+
+    let mut reader = SliceReader::new(&buf).duplicate_key(dec::DuplicateKey::First);
+    assert_eq!(
+        Keyed::decode(&mut reader).unwrap(),
+        Keyed { id: 3, flag: true }
+    );
+
+    let mut reader = SliceReader::new(&buf).duplicate_key(dec::DuplicateKey::Error);
+    assert!(matches!(
+        Keyed::decode(&mut reader),
+        Err(dec::Error::DuplicateKey { .. })
+    ));
+}
+
+#[test]
+fn test_derive_strict() {
+    #[derive(Debug, PartialEq, Encode, Decode)]
+    struct D {
+        a: u8,
+        b: u8
+    }
+
+    let buf = encode(&D { a: 1, b: 2 });
+    assert_eq!(buf, [0xa2, 0x61, b'a', 0x01, 0x61, b'b', 0x02]);
+    assert_eq!(D::decode(&mut SliceReader::new(&buf).strict()).unwrap(), D { a: 1, b: 2 });
+
+    // unsorted keys
+    let buf = [0xa2, 0x61, b'b', 0x02, 0x61, b'a', 0x01];
+    assert_eq!(D::decode(&mut SliceReader::new(&buf)).unwrap(), D { a: 1, b: 2 });
+    assert!(matches!(
+        D::decode(&mut SliceReader::new(&buf).strict()),
+        Err(dec::Error::UnsortedKey { .. })
+    ));
+
+    // duplicate unknown keys
+    let buf = [0xa4, 0x61, b'a', 0x01, 0x61, b'b', 0x02, 0x61, b'c', 0x03, 0x61, b'c', 0x03];
+    assert_eq!(D::decode(&mut SliceReader::new(&buf)).unwrap(), D { a: 1, b: 2 });
+    assert!(matches!(
+        D::decode(&mut SliceReader::new(&buf).strict()),
+        Err(dec::Error::DuplicateKey { .. })
+    ));
+
+    // also checked with a reader that can not borrow
+    let buf = [0xa2, 0x61, b'b', 0x02, 0x61, b'a', 0x01];
+    let mut reader = IoReader::new(BufReader::with_capacity(1, &buf[..])).strict();
+    assert!(matches!(D::decode(&mut reader), Err(dec::Error::UnsortedKey { .. })));
+}
@@ -5,8 +5,29 @@ use std::collections::BTreeMap;
 use serde::{ Serialize, Deserialize };
 use cbor4ii::core::dec;
 use cbor4ii::serde::{ to_vec, from_slice };
+use cbor4ii::core::utils::{ BufWriter, SliceReader };
+use cbor4ii::serde::{ Serializer, Deserializer, EncodeError, DecodeError };
 
 
+fn to_vec_with<T, F>(value: &T, config: F) -> Result<Vec<u8>, EncodeError<std::collections::TryReserveError>>
+where
+    T: Serialize + ?Sized,
+    F: FnOnce(Serializer<BufWriter>) -> Serializer<BufWriter>
+{
+    let mut ser = config(Serializer::new(BufWriter::new(Vec::new())));
+    value.serialize(&mut ser)?;
+    Ok(ser.into_inner().into_inner())
+}
+
+fn from_slice_with<'a, T, F>(buf: &'a [u8], config: F) -> Result<T, DecodeError<std::convert::Infallible>>
+where
+    T: Deserialize<'a>,
+    F: FnOnce(Deserializer<SliceReader<'a>>) -> Deserializer<SliceReader<'a>>
+{
+    let mut de = config(Deserializer::new(SliceReader::new(buf)));
+    T::deserialize(&mut de)
+}
+
 #[track_caller]
 fn de<'a,T>(bytes: &'a [u8], _value: &T)
     -> T
@@ -524,7 +545,6 @@ fn test_display_ser_len() {
 fn test_regression_f16() {
     use cbor4ii::core::Value;
     use cbor4ii::core::enc::Encode;
-    use cbor4ii::core::utils::BufWriter;
 
     let mut buf = BufWriter::new(Vec::new());
     half::f16::from_f32(12.).encode(&mut buf).unwrap();
@@ -535,3 +555,929 @@ fn test_regression_f16() {
         _ => panic!()
     }
 }
+
+#[test]
+fn test_serde_deterministic() {
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Foo {
+        aa: u8,
+        b: Vec<HashMap<u32, bool>>
+    }
+
+    let map: HashMap<u32, bool> = (0..100).map(|i| (i * 7, i % 2 == 0)).collect();
+    let foo = Foo { aa: 1, b: vec![map.clone()] };
+    let buf = to_vec_with(&foo, Serializer::deterministic).unwrap();
+    assert_eq!(&buf[..3], [0xa2, 0x61, b'b']);
+    assert_eq!(de(&buf, &foo), foo);
+
+    // independent of iteration order
+    let map2: HashMap<u32, bool> = (0..100).rev().map(|i| (i * 7, i % 2 == 0)).collect();
+    let buf2 = to_vec_with(&Foo { aa: 1, b: vec![map2] }, Serializer::deterministic).unwrap();
+    assert_eq!(buf, buf2);
+
+    // unknown length is definite
+    struct Seq;
+
+    impl Serialize for Seq {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq((0u32..3).filter(|_| true))
+        }
+    }
+
+    assert_eq!(to_vec(Vec::new(), &Seq).unwrap(), [0x9f, 0x00, 0x01, 0x02, 0xff]);
+    assert_eq!(to_vec_with(&Seq, Serializer::deterministic).unwrap(), [0x83, 0x00, 0x01, 0x02]);
+
+    // long display string is definite
+    let long = "*".repeat(300);
+    let buf = to_vec_with(&Args(&long), Serializer::deterministic).unwrap();
+    assert_eq!(&buf[..3], [0x79, 0x01, 0x2c]);
+
+    struct Args<'a>(&'a str);
+
+    impl Serialize for Args<'_> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self.0)
+        }
+    }
+
+    // duplicate key
+    struct Dup;
+
+    impl Serialize for Dup {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_map([("a", 1), ("a", 2)])
+        }
+    }
+
+    assert!(to_vec(Vec::new(), &Dup).is_ok());
+    assert!(matches!(
+        to_vec_with(&Dup, Serializer::deterministic),
+        Err(cbor4ii::serde::EncodeError::Core(cbor4ii::core::error::EncodeError::DuplicateKey { .. }))
+    ));
+
+    // raw values are encoded again
+    {
+        use cbor4ii::core::RawValue;
+        use cbor4ii::core::dec::Decode;
+        use cbor4ii::core::utils::SliceReader;
+
+        // {_ "b": 1, "a": 2, "c": 1.5}
+        let buf = [0xbf, 0x61, b'b', 0x01, 0x61, b'a', 0x02, 0x61, b'c', 0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 0xff];
+        let raw = RawValue::decode(&mut SliceReader::new(&buf)).unwrap();
+        assert_eq!(to_vec(Vec::new(), &vec![raw.clone()]).unwrap()[1..], buf);
+        assert_eq!(
+            to_vec_with(&vec![raw], Serializer::deterministic).unwrap(),
+            [0x81, 0xa3, 0x61, b'a', 0x02, 0x61, b'b', 0x01, 0x61, b'c', 0xf9, 0x3e, 0x00]
+        );
+
+        // {1: 1, 1: 2}
+        let buf = [0xa2, 0x01, 0x01, 0x01, 0x02];
+        let raw = RawValue::decode(&mut SliceReader::new(&buf)).unwrap();
+        assert!(to_vec_with(&raw, Serializer::deterministic).is_err());
+    }
+}
+
+#[test]
+fn test_serde_strict() {
+    use std::collections::HashMap;
+    use cbor4ii::core::error::DecodeError as CoreError;
+    use cbor4ii::core::utils::IoReader;
+
+    fn strict<'a, T: Deserialize<'a>>(buf: &'a [u8]) -> Result<T, DecodeError<std::convert::Infallible>> {
+        let mut de = Deserializer::new(SliceReader::new(buf).strict());
+        T::deserialize(&mut de)
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Foo {
+        aa: u32,
+        b: HashMap<u32, f64>
+    }
+
+    let foo = Foo {
+        aa: 1000,
+        b: (0..50).map(|i| (i * 10, i as f64 + 0.1)).collect()
+    };
+
+    // deterministic output is accepted
+    let buf = to_vec_with(&foo, Serializer::deterministic).unwrap();
+    assert_eq!(strict::<Foo>(&buf).unwrap(), foo);
+    let mut deserializer = Deserializer::new(IoReader::new(io::BufReader::new(buf.as_slice())).strict());
+    assert_eq!(Foo::deserialize(&mut deserializer).unwrap(), foo);
+
+    // struct fields out of order
+    let buf = to_vec(Vec::new(), &foo).unwrap();
+    assert_eq!(de(&buf, &foo), foo);
+    assert!(matches!(strict::<Foo>(&buf), Err(DecodeError::Core(CoreError::UnsortedKey { .. }))));
+
+    // unsorted keys of ignored field
+    let buf = {
+        use cbor4ii::core::Value;
+        use cbor4ii::core::enc::Encode;
+
+        let value = Value::Map(vec![
+            (Value::Text("b".into()), Value::Map(Vec::new())),
+            (Value::Text("z".into()), Value::Map(vec![
+                (Value::Integer(-1), Value::Null),
+                (Value::Integer(1), Value::Null)
+            ])),
+            (Value::Text("aa".into()), Value::Integer(1))
+        ]);
+        let mut buf = cbor4ii::core::utils::BufWriter::new(Vec::new());
+        value.encode(&mut buf).unwrap();
+        buf.into_inner()
+    };
+    assert_eq!(de(&buf, &foo).aa, 1);
+    assert!(matches!(strict::<Foo>(&buf), Err(DecodeError::Core(CoreError::UnsortedKey { .. }))));
+
+    // indefinite length and non-shortest
+    assert!(matches!(strict::<Vec<u8>>(&[0x9f, 0xff]), Err(DecodeError::Core(CoreError::IndefiniteLength { .. }))));
+    assert!(matches!(strict::<Vec<u8>>(&[0x98, 0x01, 0x01]), Err(DecodeError::Core(CoreError::NonShortest { .. }))));
+    assert!(matches!(
+        strict::<serde_cbor::Value>(&[0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]),
+        Err(DecodeError::Core(CoreError::NonPreferredFloat { .. }))
+    ));
+}
+
+#[test]
+fn test_serde_error_offset() {
+    use cbor4ii::core::error::DecodeError as CoreError;
+    use cbor4ii::core::utils::IoReader;
+    use cbor4ii::serde::from_reader;
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Foo {
+        a: Vec<u32>,
+        b: String
+    }
+
+    #[derive(Serialize)]
+    struct Bar {
+        a: (u32, &'static str),
+        b: String
+    }
+
+    let buf = to_vec(Vec::new(), &Bar { a: (1, "x"), b: "y".into() }).unwrap();
+
+    // map(1) "a"(2) array(1) 1(1) -> "x" at 5
+    let err = from_slice::<Foo>(&buf).unwrap_err();
+    assert_eq!(err.offset(), Some(5));
+    assert!(matches!(err.inner(), DecodeError::Core(CoreError::Mismatch { found: 0x61, .. })));
+    assert!(err.to_string().ends_with(" at offset 5"), "{}", err);
+    assert!(matches!(err.into_inner(), DecodeError::Core(CoreError::Mismatch { .. })));
+
+    let err = from_reader::<Foo, _>(buf.as_slice()).unwrap_err();
+    assert_eq!(err.offset(), Some(5));
+
+    let buf = to_vec(Vec::new(), &Foo { a: vec![1], b: "y".into() }).unwrap();
+    let err = from_slice::<Foo>(&buf[..buf.len() - 1]).unwrap_err();
+    assert_eq!(err.offset(), Some(buf.len() - 1));
+    assert!(matches!(err.inner(), DecodeError::Core(CoreError::Eof { .. })));
+
+    // the reader offset is still available when deserializing directly
+    let mut de = Deserializer::new(IoReader::new(buf.as_slice()));
+    let _: Foo = Foo::deserialize(&mut de).unwrap();
+    assert_eq!(de.offset(), Some(buf.len()));
+}
+
+#[test]
+fn test_serde_error_path() {
+    use cbor4ii::serde::PathSegment;
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Users {
+        users: Vec<User>
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct User {
+        name: String,
+        address: Address
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    enum Address {
+        Local { zip: u32 },
+        Remote(BTreeMap<u32, u32>)
+    }
+
+    #[derive(Serialize)]
+    struct BadUsers {
+        users: Vec<BadUser>
+    }
+
+    #[derive(Serialize)]
+    struct BadUser {
+        name: &'static str,
+        address: BadAddress
+    }
+
+    #[derive(Serialize)]
+    enum BadAddress {
+        Local { zip: &'static str },
+        Remote(BTreeMap<u32, &'static str>)
+    }
+
+    fn path<T: serde::de::DeserializeOwned + fmt::Debug>(buf: &[u8]) -> DecodeError<std::convert::Infallible> {
+        let mut de = Deserializer::new(SliceReader::new(buf)).track_path();
+        T::deserialize(&mut de).unwrap_err()
+    }
+
+    let buf = to_vec(Vec::new(), &BadUsers {
+        users: vec![
+            BadUser { name: "a", address: BadAddress::Remote(BTreeMap::new()) },
+            BadUser { name: "b", address: BadAddress::Local { zip: "x" } }
+        ]
+    }).unwrap();
+
+    let err = path::<Users>(&buf);
+    assert_eq!(err.path().unwrap().segments(), [
+        PathSegment::Key("users".into()),
+        PathSegment::Index(1),
+        PathSegment::Key("address".into()),
+        PathSegment::Variant("Local".into()),
+        PathSegment::Key("zip".into())
+    ]);
+    assert_eq!(err.path().unwrap().to_string(), ".users[1].address.Local.zip");
+    assert!(err.to_string().starts_with(".users[1].address.Local.zip: "), "{}", err);
+    assert!(matches!(err.inner(), DecodeError::Core(_)));
+
+    let buf = to_vec(Vec::new(), &BadUsers {
+        users: vec![
+            BadUser { name: "a", address: BadAddress::Remote(BTreeMap::from([(7, "x")])) }
+        ]
+    }).unwrap();
+
+    let err = path::<Users>(&buf);
+    assert!(err.to_string().starts_with(".users[0].address.Remote.7: "), "{}", err);
+
+    // with offset
+    let mut stream = Deserializer::new(SliceReader::new(&buf)).track_path().into_stream::<Users>();
+    let err = stream.next().unwrap().unwrap_err();
+    assert_eq!(err.path().unwrap().to_string(), ".users[0].address.Remote.7");
+    assert_eq!(err.offset(), Some(buf.len() - 2));
+    assert!(err.to_string().ends_with(&format!(" at offset {}", buf.len() - 2)), "{}", err);
+
+    // without path tracking
+    let err = from_slice::<Users>(&buf).unwrap_err();
+    assert!(err.path().is_none());
+    assert!(err.offset().is_some());
+}
+
+#[test]
+fn test_serde_sequence() {
+    use cbor4ii::core::utils::IoReader;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Entry<'a> {
+        id: u32,
+        #[serde(borrow)]
+        name: &'a str
+    }
+
+    let entries = [
+        Entry { id: 1, name: "a" },
+        Entry { id: 2, name: "bb" }
+    ];
+
+    let mut ser = Serializer::new(BufWriter::new(Vec::new()));
+    for entry in entries.iter() {
+        entry.serialize(&mut ser).unwrap();
+    }
+    let buf = ser.into_inner().into_inner();
+
+    let list = Deserializer::new(SliceReader::new(&buf))
+        .into_stream::<Entry>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(list, entries);
+
+    // stop after error
+    let mut stream = Deserializer::new(SliceReader::new(&buf)).into_stream::<(u32, String)>();
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+
+    let reader = IoReader::new(io::BufReader::with_capacity(3, buf.as_slice()));
+    let mut stream = Deserializer::new(reader)
+        .into_stream::<BTreeMap<String, serde::de::IgnoredAny>>();
+    assert!(stream.next().unwrap().is_ok());
+    assert!(stream.next().unwrap().is_ok());
+    assert!(stream.next().is_none());
+    assert_eq!(stream.offset(), Some(buf.len()));
+
+    // truncated item
+    let mut stream = Deserializer::new(SliceReader::new(&buf[..buf.len() - 1]))
+        .into_stream::<Entry>();
+    assert!(stream.next().unwrap().is_ok());
+    let err = stream.next().unwrap().unwrap_err();
+    assert_eq!(err.offset(), Some(buf.len() - 1));
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn test_serde_trailing_data() {
+    use cbor4ii::core::error::DecodeError as CoreError;
+    use cbor4ii::serde::{ from_slice_exact, from_reader_exact };
+
+    let mut buf = to_vec(Vec::new(), &(1u32, "a")).unwrap();
+    assert_eq!(from_slice_exact::<(u32, String)>(&buf).unwrap(), (1, "a".into()));
+    assert_eq!(from_reader_exact::<(u32, String), _>(buf.as_slice()).unwrap(), (1, "a".into()));
+
+    let len = buf.len();
+    buf.push(0x00);
+
+    // lenient
+    assert!(from_slice::<(u32, String)>(&buf).is_ok());
+
+    let err = from_slice_exact::<(u32, String)>(&buf).unwrap_err();
+    assert!(matches!(err.inner(), DecodeError::Core(CoreError::TrailingData { offset }) if offset.map(|n| n.get()) == Some(len)));
+    assert_eq!(err.offset(), Some(len));
+
+    let err = from_reader_exact::<(u32, String), _>(buf.as_slice()).unwrap_err();
+    assert!(matches!(err.inner(), DecodeError::Core(CoreError::TrailingData { offset }) if offset.map(|n| n.get()) == Some(len)));
+    assert_eq!(err.offset(), Some(len));
+
+    // errors in the item are reported first
+    let err = from_slice_exact::<(u32, u32)>(&buf).unwrap_err();
+    assert!(!matches!(err.inner(), DecodeError::Core(CoreError::TrailingData { .. })));
+
+    let mut de = Deserializer::new(SliceReader::new(&buf));
+    let _: (u32, String) = Deserialize::deserialize(&mut de).unwrap();
+    assert!(de.end().is_err());
+    let _: u32 = Deserialize::deserialize(&mut de).unwrap();
+    assert!(de.end().is_ok());
+}
+
+#[test]
+fn test_serde_packed() {
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Enum {
+        Unit,
+        Newtype(u32),
+        Tuple(u32, bool),
+        Struct { a: u32 }
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Foo {
+        name: String,
+        value: u32,
+        list: Vec<Enum>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        extra: Option<u32>
+    }
+
+    let foo = Foo {
+        name: "a".into(),
+        value: 1,
+        list: vec![Enum::Unit, Enum::Newtype(2), Enum::Tuple(3, true), Enum::Struct { a: 4 }],
+        extra: None
+    };
+
+    let buf = to_vec_with(&foo, Serializer::packed).unwrap();
+    let expected = data_encoding::HEXLOWER.decode(
+        b"83616101\
+          8400a10102a1028203f5a1038104"
+    ).unwrap();
+    assert_eq!(buf, expected);
+    assert!(buf.len() < to_vec(Vec::new(), &foo).unwrap().len());
+    assert_eq!(from_slice_with::<Foo, _>(&buf, Deserializer::packed).unwrap(), foo);
+
+    // variant index is rejected by default
+    assert!(from_slice::<Foo>(&buf).is_err());
+
+    // skipped field is present
+    let foo = Foo { extra: Some(5), ..foo };
+    let buf = to_vec_with(&foo, Serializer::packed).unwrap();
+    assert_eq!(buf[0], 0x84);
+    assert_eq!(from_slice_with::<Foo, _>(&buf, Deserializer::packed).unwrap(), foo);
+
+    // named form is still accepted
+    let buf = to_vec(Vec::new(), &foo).unwrap();
+    assert_eq!(from_slice_with::<Foo, _>(&buf, Deserializer::packed).unwrap(), foo);
+
+    // variant index out of range
+    assert!(from_slice_with::<Enum, _>(&[0x04], Deserializer::packed).is_err());
+    assert!(from_slice_with::<Enum, _>(&[0xa1, 0x05, 0x00], Deserializer::packed).is_err());
+
+    // missing field
+    assert!(from_slice_with::<Foo, _>(&[0x82, 0x61, 0x61, 0x01], Deserializer::packed).is_err());
+
+    // field index is only accepted in packed mode
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Point {
+        x: u32,
+        y: u32
+    }
+
+    let buf = [0xa2, 0x00, 0x01, 0x01, 0x02];
+    assert!(from_slice::<Point>(&buf).is_err());
+    assert_eq!(from_slice_with::<Point, _>(&buf, Deserializer::packed).unwrap(), Point { x: 1, y: 2 });
+
+    // so is a struct encoded as array
+    let buf = [0x82, 0x01, 0x02];
+    assert!(from_slice::<Point>(&buf).is_err());
+    assert_eq!(from_slice_with::<Point, _>(&buf, Deserializer::packed).unwrap(), Point { x: 1, y: 2 });
+}
+
+#[test]
+fn test_serde_integer_keys() {
+
+    // COSE_Key
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Key {
+        #[serde(rename = "1")]
+        kty: u32,
+        #[serde(rename = "-1")]
+        crv: u32,
+        #[serde(rename = "-2", with = "serde_bytes")]
+        x: Vec<u8>,
+        #[serde(rename = "01")]
+        name: bool
+    }
+
+    let key = Key { kty: 2, crv: 1, x: vec![0xaa], name: true };
+    let buf = to_vec_with(&key, Serializer::integer_keys).unwrap();
+    let expected = data_encoding::HEXLOWER.decode(b"a4010220012141aa623031f5").unwrap();
+    assert_eq!(buf, expected);
+    assert_eq!(from_slice_with::<Key, _>(&buf, Deserializer::integer_keys).unwrap(), key);
+
+    // field names are kept by default
+    let buf = to_vec(Vec::new(), &key).unwrap();
+    assert_eq!(&buf[..3], [0xa4, 0x61, b'1']);
+    assert_eq!(from_slice_with::<Key, _>(&buf, Deserializer::integer_keys).unwrap(), key);
+
+    // without integer keys, an integer key is rejected
+    let buf = to_vec_with(&key, Serializer::integer_keys).unwrap();
+    assert!(from_slice::<Key>(&buf).is_err());
+
+    // unknown integer key is ignored
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Kty {
+        #[serde(rename = "1")]
+        kty: u32
+    }
+
+    assert_eq!(from_slice_with::<Kty, _>(&buf, Deserializer::integer_keys).unwrap(), Kty { kty: 2 });
+
+    // with packed mode, variant index is kept as integer
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum E {
+        A,
+        B(u32),
+        #[serde(rename = "1")]
+        C { #[serde(rename = "1")] x: u32 }
+    }
+
+    for value in [E::A, E::B(1), E::C { x: 2 }] {
+        let buf = to_vec_with(&value, |ser| ser.packed().integer_keys()).unwrap();
+        assert_eq!(from_slice_with::<E, _>(&buf, |de| de.packed().integer_keys()).unwrap(), value);
+    }
+
+    let buf = to_vec_with(&E::C { x: 2 }, Serializer::integer_keys).unwrap();
+    assert_eq!(buf, [0xa1, 0x61, b'1', 0xa1, 0x01, 0x02]);
+    assert_eq!(from_slice_with::<E, _>(&buf, Deserializer::integer_keys).unwrap(), E::C { x: 2 });
+}
+
+#[test]
+fn test_serde_tag() {
+    use cbor4ii::core::Value;
+    use cbor4ii::serde::{ Tagged, FixedTag };
+
+    // epoch datetime
+    let value = Tagged(1, 1363896240u32);
+    let buf = to_vec(Vec::new(), &value).unwrap();
+    assert_eq!(buf, [0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]);
+    assert_eq!(de(&buf, &value), value);
+
+    // nested, in struct
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Foo {
+        cid: FixedTag<42, Tagged<String>>,
+        list: Vec<Tagged<bool>>
+    }
+
+    let foo = Foo {
+        cid: FixedTag(Tagged(7, "a".into())),
+        list: vec![Tagged(0, true)]
+    };
+    let buf = to_vec(Vec::new(), &foo).unwrap();
+    assert_eq!(&buf[5..9], [0xd8, 0x2a, 0xc7, 0x61]);
+    assert_eq!(de(&buf, &foo), foo);
+
+    // wrong or missing tag
+    let buf = to_vec(Vec::new(), &Tagged(41, Tagged(7, "a"))).unwrap();
+    assert!(from_slice::<FixedTag<42, Tagged<String>>>(&buf).is_err());
+    assert!(from_slice::<Tagged<String>>(&[0x61, 0x61]).is_err());
+
+    // value
+    let value = Value::Array(vec![
+        Value::Tag(1, Box::new(Value::Integer(1))),
+        Value::Tag(32, Box::new(Value::Tag(33, Box::new(Value::Text("a".into())))))
+    ]);
+    let buf = to_vec(Vec::new(), &value).unwrap();
+    assert_eq!(buf, [0x82, 0xc1, 0x01, 0xd8, 0x20, 0xd8, 0x21, 0x61, 0x61]);
+    assert_eq!(de(&buf, &value), value);
+
+    // bignum that is not in range of `u128` or `i128`
+    let value = Value::Array(vec![
+        Value::Tag(2, Box::new(Value::Bytes(vec![0xff; 20]))),
+        Value::Tag(3, Box::new(Value::Bytes(vec![0xff; 16]))),
+        Value::Tag(2, Box::new(Value::Text("a".into()))),
+        Value::Tag(3, Box::new(Value::Integer(1)))
+    ]);
+    let buf = to_vec(Vec::new(), &value).unwrap();
+    assert_eq!(de(&buf, &value), value);
+    assert_eq!(cbor4ii::serde::from_reader::<Value, _>(buf.as_slice()).unwrap(), value);
+    assert_eq!(cbor4ii::serde::from_value::<Value>(value.clone()).unwrap(), value);
+
+    // bignum in range
+    let buf = [0x82, 0xc2, 0x42, 0x01, 0x00, 0xc3, 0x41, 0x00];
+    let value = Value::Array(vec![Value::Integer(0x100), Value::Integer(-1)]);
+    assert_eq!(de(&buf, &value), value);
+
+    // ignored
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Bar {
+        a: u32
+    }
+
+    let buf = [0xa2, 0x61, b'b', 0xc1, 0x01, 0x61, b'a', 0x01];
+    assert_eq!(from_slice::<Bar>(&buf).unwrap(), Bar { a: 1 });
+
+    // other serializers see a tuple
+    let value = Tagged(1, "a");
+    assert_eq!(serde_cbor::to_vec(&value).unwrap(), [0x82, 0x01, 0x61, 0x61]);
+}
+
+#[test]
+fn test_serde_to_from_value() {
+    use std::collections::HashMap;
+    use cbor4ii::core::Value;
+    use cbor4ii::core::dec::Decode;
+    use cbor4ii::serde::{ to_value, from_value, Tagged };
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Enum {
+        Unit,
+        Newtype(i32),
+        Tuple(u8, String),
+        Struct { a: bool }
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Foo<'a> {
+        int: i64,
+        big: u128,
+        float: f32,
+        #[serde(with = "serde_bytes")]
+        bytes: Vec<u8>,
+        #[serde(borrow)]
+        text: &'a str,
+        option: Option<()>,
+        list: Vec<Enum>,
+        map: HashMap<u32, (char, Tagged<bool>)>
+    }
+
+    let foo = Foo {
+        int: -1,
+        big: u128::MAX,
+        float: 1.5,
+        bytes: vec![1, 2],
+        text: "a",
+        option: Some(()),
+        list: vec![
+            Enum::Unit,
+            Enum::Newtype(-2),
+            Enum::Tuple(3, "b".into()),
+            Enum::Struct { a: true }
+        ],
+        map: (0..3).map(|i| (i, ('c', Tagged(i.into(), i % 2 == 0)))).collect()
+    };
+
+    // same as encoding and decoding
+    let value = to_value(&foo).unwrap();
+    let buf = to_vec(Vec::new(), &foo).unwrap();
+    let value2 = Value::decode(&mut SliceReader::new(&buf)).unwrap();
+    assert_eq!(value, value2);
+
+    // borrowed
+    let foo2: Foo = Deserialize::deserialize(&value).unwrap();
+    assert_eq!(foo2, foo);
+    assert!(matches!(
+        <&str>::deserialize(&Value::Text("a".into())),
+        Ok("a")
+    ));
+
+    // owned, a borrowed field cannot be decoded from it
+    assert!(from_value::<Foo>(value.clone()).is_err());
+    let list: Vec<Enum> = from_value(to_value(&foo.list).unwrap()).unwrap();
+    assert_eq!(list, foo.list);
+
+    // value to value
+    assert_eq!(from_value::<Value>(value.clone()).unwrap(), value);
+    assert_eq!(to_value(&value).unwrap(), value);
+
+    // dynamic transform
+    let mut value = to_value(&Enum::Struct { a: true }).unwrap();
+    if let Value::Map(map) = &mut value {
+        map[0].1 = Value::Map(vec![(Value::Text("a".into()), Value::Bool(false))]);
+    }
+    assert_eq!(from_value::<Enum>(value).unwrap(), Enum::Struct { a: false });
+
+    // errors
+    assert!(from_value::<u8>(Value::Integer(256)).is_err());
+    assert!(from_value::<Enum>(Value::Text("Other".into())).is_err());
+    assert!(from_value::<Tagged<bool>>(Value::Bool(true)).is_err());
+    assert!(from_value::<(u8, u8)>(Value::Array(vec![Value::Integer(1)])).is_err());
+    assert!(from_value::<(u8,)>(Value::Array(vec![Value::Integer(1), Value::Integer(2)])).is_err());
+}
+
+#[test]
+fn test_serde_value_ref() {
+    use std::borrow::Cow;
+    use cbor4ii::core::{ Value, ValueRef };
+    use cbor4ii::serde::Tagged;
+
+    let value = (
+        "text",
+        serde_bytes::Bytes::new(&[1, 2]),
+        Tagged(1, vec![1u32, 2]),
+        Some(1.5),
+        std::iter::once((1, false)).collect::<BTreeMap<_, _>>()
+    );
+    let buf = to_vec(Vec::new(), &value).unwrap();
+
+    let value_ref: ValueRef = from_slice(&buf).unwrap();
+    match &value_ref {
+        ValueRef::Array(list) => {
+            assert!(matches!(&list[0], ValueRef::Text(Cow::Borrowed("text"))));
+            assert!(matches!(&list[1], ValueRef::Bytes(Cow::Borrowed([1, 2]))));
+        },
+        _ => panic!()
+    }
+
+    let value2: Value = from_slice(&buf).unwrap();
+    assert_eq!(value_ref.clone().into_owned(), value2);
+    assert_eq!(to_vec(Vec::new(), &value_ref).unwrap(), buf);
+}
+
+#[test]
+fn test_serde_raw_value() {
+    use cbor4ii::core::{ Value, RawValue, BoxedRawValue };
+    use cbor4ii::core::utils::IoReader;
+    use cbor4ii::serde::{ from_reader, to_value, from_value };
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Envelope<'a> {
+        kind: u32,
+        #[serde(borrow)]
+        body: RawValue<'a>
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct BoxedEnvelope {
+        kind: u32,
+        body: BoxedRawValue
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Body {
+        list: Vec<(String, Option<i32>)>
+    }
+
+    let body = Body { list: vec![("a".into(), Some(-1)), ("b".into(), None)] };
+    let body_buf = to_vec(Vec::new(), &body).unwrap();
+    let buf = to_vec(Vec::new(), &(1u32, &body)).unwrap();
+    let buf = {
+        // {"kind": 1, "body": ..}
+        let mut buf2 = vec![0xa2, 0x64, b'k', b'i', b'n', b'd', 0x01, 0x64, b'b', b'o', b'd', b'y'];
+        buf2.extend_from_slice(&buf[2..]);
+        buf2
+    };
+
+    // zero-copy from slice
+    let envelope: Envelope = from_slice(&buf).unwrap();
+    assert_eq!(envelope.kind, 1);
+    assert_eq!(envelope.body.as_bytes(), body_buf.as_slice());
+    assert!(std::ptr::eq(envelope.body.as_bytes().as_ptr(), buf[12..].as_ptr()));
+    assert_eq!(from_slice::<Body>(envelope.body.as_bytes()).unwrap(), body);
+
+    // pass through unchanged
+    assert_eq!(to_vec(Vec::new(), &envelope).unwrap(), buf);
+
+    // owned from reader
+    let reader = io::BufReader::with_capacity(3, buf.as_slice());
+    let boxed: BoxedEnvelope = from_reader(reader).unwrap();
+    assert_eq!(boxed.body.as_bytes(), body_buf.as_slice());
+    assert_eq!(to_vec(Vec::new(), &boxed).unwrap(), buf);
+    let mut de = Deserializer::new(IoReader::new(buf.as_slice()));
+    assert!(Envelope::deserialize(&mut de).is_err());
+
+    // value
+    let value = to_value(&envelope).unwrap();
+    let value2: Value = from_slice(&buf).unwrap();
+    assert_eq!(value, value2);
+    let boxed2: BoxedEnvelope = from_value(value).unwrap();
+    assert_eq!(boxed2, boxed);
+
+    // other serializers see bytes
+    let buf2 = serde_cbor::to_vec(&envelope.body).unwrap();
+    assert_eq!(&buf2[1..], body_buf.as_slice());
+    let boxed3: BoxedRawValue = serde_cbor::from_slice(&buf2).unwrap();
+    assert_eq!(boxed3, boxed.body);
+    let bad = serde_cbor::to_vec(&serde_bytes::Bytes::new(&[0x82, 0x01])).unwrap();
+    assert!(serde_cbor::from_slice::<BoxedRawValue>(&bad).is_err());
+
+    // malformed input
+    assert!(from_slice::<Envelope>(&buf[..buf.len() - 1]).is_err());
+}
+
+#[test]
+fn test_serde_preferred_float() {
+    #[derive(Serialize)]
+    struct Floats {
+        a: f64,
+        b: f32,
+        c: f64,
+        d: f64
+    }
+
+    let floats = Floats { a: 1.5, b: 100000.0, c: 1.1, d: f64::NAN };
+    let buf = to_vec_with(&floats, Serializer::preferred_float).unwrap();
+    let expected = data_encoding::HEXLOWER
+        .decode(b"a46161f93e006162fa47c350006163fb3ff199999999999a6164f97e00")
+        .unwrap();
+    assert_eq!(buf, expected);
+
+    assert_eq!(to_vec_with(&f64::NEG_INFINITY, Serializer::preferred_float).unwrap(), [0xf9, 0xfc, 0x00]);
+    assert_eq!(to_vec_with(&-0.0f32, Serializer::preferred_float).unwrap(), [0xf9, 0x80, 0x00]);
+
+    // default keeps the native width
+    let buf = to_vec(Vec::new(), &1.5f64).unwrap();
+    assert_eq!(buf, [0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]);
+
+    // deterministic implies preferred floats
+    assert_eq!(to_vec_with(&0.5f64, Serializer::deterministic).unwrap(), [0xf9, 0x38, 0x00]);
+}
+
+#[test]
+fn test_serde_float_widening() {
+    use cbor4ii::core::Value;
+
+    let buf = [0xf9, 0x3e, 0x00];
+    assert_eq!(from_slice::<f64>(&buf).unwrap(), 1.5);
+    assert_eq!(from_slice::<f32>(&buf).unwrap(), 1.5);
+    assert_eq!(from_slice::<Value>(&buf).unwrap(), Value::Float(1.5));
+
+    let buf = [0xfa, 0x47, 0xc3, 0x50, 0x00];
+    assert_eq!(from_slice::<f64>(&buf).unwrap(), 100000.0);
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Point {
+        x: f64,
+        y: f64
+    }
+
+    // written with preferred serialization by another encoder
+    let buf = data_encoding::HEXLOWER.decode(b"a26178f9c4006179fa47c35000").unwrap();
+    let point: Point = from_slice(&buf).unwrap();
+    assert_eq!(point, Point { x: -4.0, y: 100000.0 });
+}
+
+#[test]
+fn test_serde_simple_value() {
+    use cbor4ii::core::{ Value, ValueRef };
+    use cbor4ii::serde::{ to_value, from_value };
+
+    // [undefined, simple(16), simple(255)]
+    let buf = [0x83, 0xf7, 0xf0, 0xf8, 0xff];
+
+    let value: Value = from_slice(&buf).unwrap();
+    assert_eq!(value, Value::Array(vec![Value::Undefined, Value::Simple(16), Value::Simple(255)]));
+    let value_ref: ValueRef = from_slice(&buf).unwrap();
+    assert_eq!(value_ref.into_owned(), value);
+
+    assert_eq!(to_vec(Vec::new(), &value).unwrap(), buf);
+    assert_eq!(to_value(&value).unwrap(), value);
+    assert_eq!(from_value::<Value>(value.clone()).unwrap(), value);
+    assert_eq!(from_value::<Value>(Value::Simple(16)).unwrap(), Value::Simple(16));
+
+    // option has no undefined
+    assert_eq!(from_slice::<Option<u8>>(&[0xf7]).unwrap(), None);
+    assert_eq!(from_value::<Option<u8>>(Value::Undefined).unwrap(), None);
+
+    // simple value is skipped
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Foo {
+        a: u8
+    }
+
+    let buf = data_encoding::HEXLOWER.decode(b"a2616101616cf8ff").unwrap();
+    let foo: Foo = from_slice(&buf).unwrap();
+    assert_eq!(foo, Foo { a: 1 });
+}
+
+#[test]
+fn test_serde_core_agree() {
+    use std::borrow::Cow;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use std::num::{ NonZeroU32, NonZeroI64 };
+    use cbor4ii::core::enc::Encode;
+    use cbor4ii::core::dec::Decode;
+
+    #[track_caller]
+    fn check<T>(value: T)
+    where T: Serialize + Encode + for<'a> Decode<'a> + PartialEq + fmt::Debug
+    {
+        let mut writer = BufWriter::new(Vec::new());
+        value.encode(&mut writer).unwrap();
+        let buf = to_vec(Vec::new(), &value).unwrap();
+        assert_eq!(writer.buffer(), buf.as_slice(), "{:?}", value);
+
+        let value2 = T::decode(&mut SliceReader::new(&buf)).unwrap();
+        assert_eq!(value2, value);
+    }
+
+    check('a');
+    check('\u{1f980}');
+    check(());
+    check(Some(1u32));
+    check(None::<u32>);
+    check(Ok::<u32, String>(1));
+    check(Err::<u32, String>("bad".into()));
+    check(String::from("hello").into_boxed_str());
+    check(vec![1u8, 2, 3].into_boxed_slice());
+    check(NonZeroU32::new(7).unwrap());
+    check(NonZeroI64::new(-7).unwrap());
+    check(Duration::new(5, 123));
+    check(Duration::new(u64::MAX, 999_999_999));
+
+    let value: Cow<str> = Cow::Borrowed("cow");
+    let mut writer = BufWriter::new(Vec::new());
+    value.encode(&mut writer).unwrap();
+    assert_eq!(writer.buffer(), to_vec(Vec::new(), &value).unwrap().as_slice());
+
+    let mut writer = BufWriter::new(Vec::new());
+    (Rc::new(1u32), Arc::new("a".to_string())).encode(&mut writer).unwrap();
+    let value = <(Rc<u32>, Arc<String>)>::decode(&mut SliceReader::new(writer.buffer())).unwrap();
+    assert_eq!((*value.0, value.1.as_str()), (1, "a"));
+
+    // errors
+    let ret = char::decode(&mut SliceReader::new(&[0x62, b'a', b'b']));
+    assert!(matches!(ret, Err(dec::Error::RequireLength { .. })));
+    let ret = NonZeroU32::decode(&mut SliceReader::new(&[0x00]));
+    assert!(matches!(ret, Err(dec::Error::CastOverflow { .. })));
+    let ret = <Result<u32, u32>>::decode(&mut SliceReader::new(&[0xa1, 0x62, b'O', b'K', 0x01]));
+    assert!(matches!(ret, Err(dec::Error::Mismatch { .. })));
+
+    // duration from packed array, and out of range nanos
+    let buf = to_vec(Vec::new(), &(1u64, 2u32)).unwrap();
+    assert_eq!(Duration::decode(&mut SliceReader::new(&buf)).unwrap(), Duration::new(1, 2));
+    let buf = to_vec(Vec::new(), &(u64::MAX, 1_000_000_000u32)).unwrap();
+    let ret = Duration::decode(&mut SliceReader::new(&buf));
+    assert!(matches!(ret, Err(dec::Error::ArithmeticOverflow { .. })));
+
+    // duration map with keys out of order, and duplicate key
+    let buf = [0xa2, 0x65, b'n', b'a', b'n', b'o', b's', 0x02, 0x64, b's', b'e', b'c', b's', 0x01];
+    assert_eq!(Duration::decode(&mut SliceReader::new(&buf)).unwrap(), Duration::new(1, 2));
+    let ret = Duration::decode(&mut SliceReader::new(&buf).strict());
+    assert!(matches!(ret, Err(dec::Error::UnsortedKey { .. })));
+    let buf = [0xa2, 0x64, b's', b'e', b'c', b's', 0x01, 0x64, b's', b'e', b'c', b's', 0x01];
+    let ret = Duration::decode(&mut SliceReader::new(&buf));
+    assert!(matches!(ret, Err(dec::Error::DuplicateKey { .. })));
+    let ret = Duration::decode(&mut SliceReader::new(&buf).strict());
+    assert!(matches!(ret, Err(dec::Error::DuplicateKey { .. })));
+}
+
+#[test]
+fn test_serde_limits() {
+    use cbor4ii::core::utils::IoReader;
+    use cbor4ii::core::error::DecodeError as CoreError;
+
+    let value = vec!["a".repeat(100); 16];
+    let buf = to_vec(Vec::new(), &value).unwrap();
+
+    let limits = dec::Limits::new().max_alloc(1024);
+    let mut de = Deserializer::new(IoReader::new(io::BufReader::new(buf.as_slice())).limits(limits));
+    let ret = Vec::<String>::deserialize(&mut de);
+    assert!(matches!(ret, Err(DecodeError::Core(CoreError::LimitExceeded { .. }))));
+
+    // indefinite length items are counted
+    let buf = [0x9f, 0x01, 0x02, 0x03, 0xff];
+    let limits = dec::Limits::new().max_items(2);
+    let mut de = Deserializer::new(SliceReader::new(&buf).limits(limits));
+    let ret = Vec::<u32>::deserialize(&mut de);
+    assert!(matches!(ret, Err(DecodeError::Core(CoreError::LimitExceeded { .. }))));
+
+    let buf = [0xbf, 0x01, 0x02, 0x03, 0x04, 0xff];
+    let limits = dec::Limits::new().max_items(1);
+    let mut de = Deserializer::new(SliceReader::new(&buf).limits(limits));
+    let ret = BTreeMap::<u32, u32>::deserialize(&mut de);
+    assert!(matches!(ret, Err(DecodeError::Core(CoreError::LimitExceeded { .. }))));
+}